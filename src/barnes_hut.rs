@@ -1,440 +1,661 @@
 use crate::body::*;
+use crate::softening::Softening;
+use crate::solver::ForceSolver;
+use rayon::prelude::*;
 
-type Vec2 = nalgebra::Vector2<f64>;
+type Vec3 = nalgebra::Vector3<f64>;
+type Mat3 = nalgebra::Matrix3<f64>;
 
 const MASS_SUN: f64 = 1.988435e30;
 const PC_IN_M: f64 = 3.08567758129e16; //???
 const GAMMA_SI: f64 = 6.67428e-11;
 pub const GAMMA: f64 =
     GAMMA_SI / (PC_IN_M * PC_IN_M * PC_IN_M) * MASS_SUN * (365.25 * 86400.0) * (365.25 * 86400.0);
+// parsec per year, same units as GAMMA
+pub const SPEED_OF_LIGHT: f64 = 299792458.0 * (365.25 * 86400.0) / PC_IN_M;
 
-mod quadrant {
-    pub const NORTH_WEST: usize = 0;
-    pub const NORTH_EAST: usize = 1;
-    pub const SOUTH_WEST: usize = 2;
-    pub const SOUTH_EAST: usize = 3;
-    pub const MAX: usize = 4;
-    pub const INVALID: usize = 5;
+// depth of the leaves, morton keys hold 21 bits per axis. Bodies closer than root size / 2^21 share
+// a leaf and interact with each other directly
+const MAX_DEPTH: u32 = 21;
+
+/*
+ * Decides when a node is far enough from a particle to use its multipole expansion instead of
+ * opening it. Smaller parameters mean more opened nodes, more accurate forces and slower evaluation.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OpeningCriterion {
+    // classic Barnes-Hut, node size / distance to the mass center <= theta
+    Geometric(f64),
+    // node size / distance to the closest point of the node <= theta (Barnes 1994),
+    // nodes containing the particle are always opened
+    MinimumDistance(f64),
+    // GADGET-2 relative error criterion, G * M / r^2 * (size / r)^2 <= alpha * |a_old|, uses the
+    // acceleration of the particle from the previous evaluation
+    RelativeAcceleration(f64),
+}
+
+impl Default for OpeningCriterion {
+    fn default() -> OpeningCriterion {
+        OpeningCriterion::Geometric(0.9)
+    }
+}
+
+// geometric theta used by RelativeAcceleration before the first acceleration is known
+const FIRST_STEP_THETA: f64 = 0.5;
+
+// everything the tree walk needs to compute interactions
+struct Interaction {
+    gravitational_constant: f64,
+    softening: Softening,
+    criterion: OpeningCriterion,
+    quadrupole: bool, // monopole only walks skip the zero quadrupole terms
 }
 
 /* https://beltoforion.de/en/barnes-hut-galaxy-simulator/
  * The Barnes-Hut Algorithm describes an effective method for solving n-body problems. It was originally published in 1986 by Josh Barnes and Piet Hut. Instead of directly summing up all forces, it is using a tree based approximation scheme which reduces the computational complexity of the problem from O(N2) to O(N log N).
  */
 
-struct Node {
-    mass: f64,
-    mass_center: Vec2,
-    pos_upper_bound: Vec2,
-    pos_lower_bound: Vec2,
-    center: Vec2,
-    nested: Option<NestedBody>,
-}
+/*
+ * The tree lives in one contiguous arena which is cleared, not freed, between steps. Particles are
+ * sorted along the Morton (Z-order) curve first, so every node owns a contiguous range of them and
+ * children of a node are stored next to each other. Nodes only keep indices, no bodies are copied
+ * into them.
+ */
 
-type NestedQuadrants = [Option<Box<Node>>; 4];
+// what the tree needs to know about a body, kept compact and in morton order for cache locality
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Particle {
+    pub(crate) pos: Vec3,
+    pub(crate) mass: f64,
+    pub(crate) softening2: f64,
+}
 
-enum NestedBody {
-    Single(Body),
-    Multiple(NestedQuadrants),
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct Node {
+    pub(crate) mass: f64,
+    mass_center: Vec3,
+    pub(crate) softening2: f64, // mass weighted mean of squared epsilons, used by Softening::PerBody
+    // traceless quadrupole tensor around mass_center, sum of m * (3 * d * d^T - |d|^2 * I)
+    // stays zero unless quadrupoles are enabled
+    quadrupole: Mat3,
+    pub(crate) pos_upper_bound: Vec3,
+    pub(crate) pos_lower_bound: Vec3,
+    first_child: u32, // children are stored next to each other, one level deeper in the arena
+    pub(crate) children: u32, // leaf when zero
+    first_particle: u32,
+    particles: u32,
 }
 
-impl Default for Node {
-    fn default() -> Node {
+impl Node {
+    fn new(lower: Vec3, upper: Vec3, first_particle: usize, particles: usize) -> Self {
         Node {
             mass: 0.0,
-            mass_center: Vec2::new(0.0, 0.0),
-            pos_upper_bound: Vec2::new(0.0, 0.0),
-            pos_lower_bound: Vec2::new(0.0, 0.0),
-            center: Vec2::new(0.0, 0.0),
-            nested: Default::default(), // [None; 4]
+            mass_center: Vec3::new(0.0, 0.0, 0.0),
+            softening2: 0.0,
+            quadrupole: Mat3::zeros(),
+            pos_upper_bound: upper,
+            pos_lower_bound: lower,
+            first_child: 0,
+            children: 0,
+            first_particle: first_particle as u32,
+            particles: particles as u32,
         }
     }
-}
 
-impl Node {
-    fn new(body: Body, lower: Vec2, upper: Vec2) -> Self {
-        let mut obj: Self = Default::default();
-        obj.nested = Some(NestedBody::Single(body));
-        obj.center = (lower + upper) / 2.0;
-        obj.pos_lower_bound = lower;
-        obj.pos_upper_bound = upper;
-        obj
+    pub(crate) fn child_range(&self) -> std::ops::Range<usize> {
+        self.first_child as usize..(self.first_child + self.children) as usize
     }
 
-    fn calculate_force(&self, body: Body) -> Vec2 {
-        let acc = self.calculate_force_on_tree(body);
-        // calculate the force from particles not in the barnes hut tree on particle p
-        /*
-        for (std::size_t i=0; i<s_renegades.size(); ++i)
-        {
-        Vec2D buf = CalcAcc(p1, s_renegades[i]);
-        acc.x += buf.x;
-        acc.y += buf.y;
-        }*/
-        acc
+    pub(crate) fn particle_range(&self) -> std::ops::Range<usize> {
+        self.first_particle as usize..(self.first_particle + self.particles) as usize
     }
-    // Compute the force acting from this node and it's child to a particle p
-    fn calculate_force_on_tree(&self, body: Body) -> Vec2 {
-        match self.nested.as_ref().unwrap() {
-            NestedBody::Single(body2) => self.calculate_acceleration(body, *body2),
-            NestedBody::Multiple(data) => {
-                let r = (body.pos - self.mass_center)
-                    .dot(&(body.pos - self.mass_center))
-                    .sqrt();
-                let d = self.pos_upper_bound.x - self.pos_lower_bound.x; // TODO FIXME why only x?
-                const S_THETA: f64 = 0.9;
-                if d / r <= S_THETA {
-                    // THE HEART OF THE ALGORITHM
-                    // self.too_close = false;
-                    let k = GAMMA * self.mass / (r * r * r);
-                    let acc = k * (self.mass_center - body.pos);
-                    return acc;
-                } else {
-                    // self.too_close = true;
-                    let mut acc = Vec2::new(0.0, 0.0);
-                    for i in 0..quadrant::MAX {
-                        match data[i].as_ref() {
-                            Some(node) => {
-                                acc += node.calculate_force_on_tree(body);
-                            }
-                            None => {}
-                        }
-                    }
-                    return acc;
-                }
-            }
+
+    // children of the node, particles are already sorted so every octant is a contiguous subrange
+    // of keys. Nodes with up to leaf_size bodies stay leaves
+    fn split(&self, keys: &[(u64, u32)], depth: u32, leaf_size: usize) -> ([Node; 8], usize) {
+        let mut children = [*self; 8];
+        if keys.len() <= leaf_size {
+            return (children, 0);
         }
-    }
 
-    fn calculate_acceleration(&self, body1: Body, body2: Body) -> Vec2 {
-        if body1.pos == body2.pos {
-            // same body
-            return Vec2::new(0.0, 0.0);
+        let shift = 3 * (MAX_DEPTH - 1 - depth);
+        let mut splits = [0, 0, 0, 0, 0, 0, 0, 0, keys.len()];
+        for (octant, split) in splits.iter_mut().enumerate().take(8).skip(1) {
+            *split = keys.partition_point(|&(key, _)| ((key >> shift) & 7) < octant as u64);
         }
 
-        return body1.compute_force(&body2, GAMMA);
+        let half = (self.pos_upper_bound - self.pos_lower_bound) / 2.0;
+        let mut count = 0;
+        for octant in 0..8 {
+            let (from, to) = (splits[octant], splits[octant + 1]);
+            if from == to {
+                continue;
+            }
+            let lower = self.pos_lower_bound + half.component_mul(&octant_offset(octant));
+            children[count] = Node::new(
+                lower,
+                lower + half,
+                self.first_particle as usize + from,
+                to - from,
+            );
+            count += 1;
+        }
+        (children, count)
     }
 
-    fn get_quadrant(&self, x: f64, y: f64) -> usize {
-        if x <= self.center.x && y <= self.center.y {
-            return quadrant::SOUTH_WEST;
-        } else if x <= self.center.x && y >= self.center.y {
-            return quadrant::NORTH_WEST;
-        } else if x >= self.center.x && y >= self.center.y {
-            return quadrant::NORTH_EAST;
-        } else if x >= self.center.x && y <= self.center.y {
-            return quadrant::SOUTH_EAST;
+    // leaves sum their particles, other nodes their children
+    fn compute_mass_distribution(&mut self, children: &[Node], particles: &[Particle], quadrupole: bool) {
+        if particles.is_empty() {
+            return; // empty tree
         }
-        unreachable!();
-        //quadrant::INVALID
-    }
 
-    fn compute_mass_distribution(&mut self) -> Vec2 {
-        match &mut self.nested {
-            None => {
-                unreachable!();
+        if children.is_empty() {
+            for particle in particles {
+                self.mass += particle.mass;
+                self.mass_center += particle.pos * particle.mass;
+                self.softening2 += particle.softening2 * particle.mass;
             }
-            Some(NestedBody::Multiple(data)) => {
-                assert!(self.mass == 0.0);
-                assert!(self.mass_center == Vec2::new(0.0, 0.0));
-                for i in 0..quadrant::MAX {
-                    match data[i].as_mut() {
-                        Some(node) => {
-                            node.compute_mass_distribution();
-                            self.mass += node.mass;
-                            self.mass_center += node.mass_center * node.mass;
-                        }
-                        None => {}
-                    }
-                }
-                self.mass_center /= self.mass;
+            self.normalize(particles);
+            if quadrupole && particles.len() > 1 {
+                self.quadrupole = quadrupole_of(particles, self.mass_center);
             }
-            Some(NestedBody::Single(data)) => {
-                self.mass = data.mass;
-                self.mass_center = data.pos;
+            return;
+        }
+
+        for child in children {
+            self.mass += child.mass;
+            self.mass_center += child.mass_center * child.mass;
+            self.softening2 += child.softening2 * child.mass;
+        }
+        self.normalize(particles);
+
+        if quadrupole {
+            // parallel axis theorem, shift children tensors to the new mass center
+            for child in children {
+                let s = child.mass_center - self.mass_center;
+                self.quadrupole += child.quadrupole
+                    + child.mass * (3.0 * s * s.transpose() - s.dot(&s) * Mat3::identity());
             }
         }
-        self.mass_center
     }
 
-    fn insert_particle(&mut self, body: Body) {
-        let quad = self.get_quadrant(body.pos.x, body.pos.y);
-        if body.pos.x < self.pos_lower_bound.x
-            || body.pos.x > self.pos_upper_bound.x
-            || body.pos.y < self.pos_lower_bound.y
-            || body.pos.y > self.pos_upper_bound.y
-        {
-            return;
+    // weighted sums to means. Nodes of massless bodies have no mass center, they take the geometric
+    // centre of their particles so no NaN reaches the nodes above them
+    fn normalize(&mut self, particles: &[Particle]) {
+        if self.mass == 0.0 {
+            self.mass_center = particles.iter().map(|particle| particle.pos).sum::<Vec3>() / particles.len() as f64;
+            self.softening2 = 0.0;
+        } else {
+            self.mass_center /= self.mass;
+            self.softening2 /= self.mass;
         }
+    }
 
-        let get_new_quadrant_bounds =
-            |quad, lower: Vec2, center: Vec2, upper: Vec2| -> (Vec2, Vec2) {
-                match quad {
-                    quadrant::SOUTH_WEST => (lower, center),
-                    quadrant::NORTH_WEST => {
-                        (Vec2::new(lower.x, center.y), Vec2::new(center.x, upper.y))
-                    }
-                    quadrant::NORTH_EAST => (center, upper),
-                    quadrant::SOUTH_EAST => {
-                        (Vec2::new(center.x, lower.y), Vec2::new(upper.x, center.y))
-                    }
-                    _ => unreachable!(),
-                }
-            };
-
-        match &mut self.nested {
-            None => {
-                self.nested = Some(NestedBody::Single(body));
+    // decides if the whole node can be replaced by its multipole expansion, r is the distance from
+    // the particle to the mass center
+    fn is_far_enough(&self, body: &Body, r: f64, interaction: &Interaction) -> bool {
+        let size = (self.pos_upper_bound - self.pos_lower_bound).max();
+        match interaction.criterion {
+            OpeningCriterion::Geometric(theta) => size <= theta * r,
+            OpeningCriterion::MinimumDistance(theta) => {
+                // distance to the closest point of the node, zero when the particle is inside
+                let outside = (self.pos_lower_bound - body.pos)
+                    .sup(&(body.pos - self.pos_upper_bound))
+                    .sup(&Vec3::zeros());
+                size <= theta * outside.norm()
             }
-            Some(NestedBody::Multiple(data)) => match &mut data[quad] {
-                Some(node) => node.insert_particle(body),
-                None => {
-                    let (lower, upper) = get_new_quadrant_bounds(
-                        quad,
-                        self.pos_lower_bound,
-                        self.center,
-                        self.pos_upper_bound,
-                    );
-                    data[quad] = Some(Box::new(Node::new(body, lower, upper)));
-                }
-            },
-            Some(NestedBody::Single(data)) => {
-                let prev_body = *data; // steal
-                let mut quads: NestedQuadrants = Default::default();
-
-                if prev_body.pos == body.pos {
-                    panic!("two same positions"); // TODO add to renegades?
+            OpeningCriterion::RelativeAcceleration(alpha) => {
+                let acc = body.acc.norm();
+                if acc == 0.0 {
+                    return size <= FIRST_STEP_THETA * r;
                 }
-                // add previosuly existing body
-                let prev_quad = self.get_quadrant(prev_body.pos.x, prev_body.pos.y);
-                let (lower, upper) = get_new_quadrant_bounds(
-                    prev_quad,
-                    self.pos_lower_bound,
-                    self.center,
-                    self.pos_upper_bound,
-                );
-                quads[prev_quad] = Some(Box::new(Node::new(prev_body, lower, upper)));
-
-                // add new body
-                if prev_quad != quad {
-                    let (lower, upper) = get_new_quadrant_bounds(
-                        quad,
-                        self.pos_lower_bound,
-                        self.center,
-                        self.pos_upper_bound,
-                    );
-                    quads[quad] = Some(Box::new(Node::new(body, lower, upper)));
-                } else {
-                    match &mut quads[quad] {
-                        Some(node) => {
-                            node.insert_particle(body);
-                        }
-                        _ => unreachable!("we allocated node with single body few lines above"),
-                    }
+                // never accept nodes the particle is in or next to, the expansion is poor there
+                let margin = 0.6 * size;
+                let center = (self.pos_upper_bound + self.pos_lower_bound) / 2.0;
+                if (body.pos - center).abs().max() < margin {
+                    return false;
                 }
-
-                self.nested = Some(NestedBody::Multiple(quads));
+                let r2 = r * r;
+                interaction.gravitational_constant * self.mass * size * size
+                    <= alpha * acc * r2 * r2
             }
         }
     }
+
+    // quadrupole term of the expansion, r points from the mass center to the particle
+    // softening is not applied, nodes are only accepted far away from the particle
+    fn quadrupole_acceleration(&self, r: Vec3, dist: f64) -> Vec3 {
+        let qr = self.quadrupole * r;
+        let r2 = dist * dist;
+        let r5 = r2 * r2 * dist;
+        qr / r5 - 2.5 * r.dot(&qr) * r / (r5 * r2)
+    }
+
+    fn quadrupole_potential(&self, r: Vec3, dist: f64) -> f64 {
+        let r2 = dist * dist;
+        -r.dot(&(self.quadrupole * r)) / (2.0 * r2 * r2 * dist)
+    }
 }
 
-#[derive(Default)]
-pub struct BarnesHutSimulation {
-    pos_upper_bound: Vec2,
-    pos_lower_bound: Vec2,
-    pub center: Vec2,
-    tree: Node,
-    roi: f64,
-    pub bodies: Vec<Body>,
-    particle_num: usize,
+// spreads the lower 21 bits of v over every third bit of the result
+fn spread_bits(v: u32) -> u64 {
+    let mut x = (v & 0x1f_ffff) as u64;
+    x = (x | (x << 32)) & 0x001f_0000_0000_ffff;
+    x = (x | (x << 16)) & 0x001f_0000_ff00_00ff;
+    x = (x | (x << 8)) & 0x100f_00f0_0f00_f00f;
+    x = (x | (x << 4)) & 0x10c3_0c30_c30c_30c3;
+    x = (x | (x << 2)) & 0x1249_2492_4924_9249;
+    x
 }
 
-impl BarnesHutSimulation {
-    pub fn new(bodies: Vec<Body>) -> Self {
-        let mut obj: Self = Default::default();
-        obj.particle_num = bodies.len();
-        obj.pos_upper_bound = Vec2::new(std::f64::MIN, std::f64::MIN);
-        obj.pos_lower_bound = Vec2::new(std::f64::MAX, std::f64::MAX);
-        obj.bodies = bodies;
-        obj.init();
-        obj
-    }
+// three key bits per level, x in the lowest one, z in the highest
+fn octant_offset(octant: usize) -> Vec3 {
+    Vec3::new((octant & 1) as f64, ((octant >> 1) & 1) as f64, (octant >> 2) as f64)
+}
+
+pub(crate) fn is_finite(pos: &Vec3) -> bool {
+    pos.iter().all(|v| v.is_finite())
+}
 
-    fn build_quadrant_tree(&mut self) {
-        self.tree = Node::default();
-        self.tree.pos_upper_bound = self.center.add_scalar(self.roi);
-        self.tree.pos_lower_bound = self.center.add_scalar(-self.roi);
-        self.tree.center = (self.tree.pos_upper_bound + self.tree.pos_lower_bound) / 2.0;
+// quadrupole of point masses around center
+fn quadrupole_of(particles: &[Particle], center: Vec3) -> Mat3 {
+    particles.iter().fold(Mat3::zeros(), |q, p| {
+        let d = p.pos - center;
+        q + p.mass * (3.0 * d * d.transpose() - d.dot(&d) * Mat3::identity())
+    })
+}
 
-        for body in &self.bodies {
-            self.tree.insert_particle(*body);
+#[derive(Default)]
+pub(crate) struct Octree {
+    pub(crate) nodes: Vec<Node>, // root at index 0, stored level by level
+    pub(crate) levels: Vec<std::ops::Range<usize>>, // nodes of every depth
+    pub(crate) particles: Vec<Particle>,
+    pub(crate) keys: Vec<(u64, u32)>, // morton key and body index, sorted
+}
+
+impl Octree {
+    /*
+     * Builds the tree inside the cube given by lower and upper, buffers are reused between calls.
+     * Nodes are split until they hold at most leaf_size bodies.
+     * Construction runs in parallel, one level at a time. Every node is computed only from its own
+     * particles or children, so the tree doesn't depend on the number of threads.
+     */
+    pub(crate) fn build(
+        &mut self,
+        bodies: &[Body],
+        lower: Vec3,
+        upper: Vec3,
+        leaf_size: usize,
+        quadrupole: bool,
+    ) -> TreeStats {
+        let mut stats = TreeStats::default();
+        let scale = 2f64.powi(MAX_DEPTH as i32) / (upper - lower).max();
+
+        self.keys.clear();
+        self.keys.par_extend(
+            bodies
+                .par_iter()
+                .enumerate()
+                .filter(|(_, body)| is_finite(&body.pos))
+                .map(|(index, body)| {
+                    // float to int casts saturate, bodies on the upper bound are clamped to the last
+                    // cell
+                    let last = (1 << MAX_DEPTH) - 1;
+                    let x = (((body.pos.x - lower.x) * scale) as u32).min(last);
+                    let y = (((body.pos.y - lower.y) * scale) as u32).min(last);
+                    let z = (((body.pos.z - lower.z) * scale) as u32).min(last);
+                    (spread_bits(x) | (spread_bits(y) << 1) | (spread_bits(z) << 2), index as u32)
+                }),
+        );
+        // body indices are unique, so the order is the same for any sorting algorithm
+        self.keys.par_sort_unstable();
+        stats.out_of_bounds = bodies.len() - self.keys.len();
+
+        self.particles.clear();
+        self.particles.par_extend(self.keys.par_iter().map(|&(_, index)| {
+            let body = &bodies[index as usize];
+            Particle {
+                pos: body.pos,
+                mass: body.mass,
+                softening2: body.softening * body.softening,
+            }
+        }));
+
+        self.nodes.clear();
+        self.nodes.push(Node::new(lower, upper, 0, self.particles.len()));
+        self.levels.clear();
+        let mut level = 0..1;
+        for depth in 0..MAX_DEPTH {
+            if level.is_empty() {
+                break;
+            }
+            let keys = &self.keys;
+            let splits: Vec<_> = self.nodes[level.clone()]
+                .par_iter()
+                .map(|node| node.split(&keys[node.particle_range()], depth, leaf_size))
+                .collect();
+
+            let next = self.nodes.len();
+            for (index, (children, count)) in level.clone().zip(splits) {
+                self.nodes[index].first_child = self.nodes.len() as u32;
+                self.nodes[index].children = count as u32;
+                self.nodes.extend_from_slice(&children[..count]);
+            }
+            self.levels.push(level);
+            level = next..self.nodes.len();
+        }
+        // leaves at the maximum depth may hold several bodies
+        stats.coincident = self.nodes[level.clone()]
+            .iter()
+            .map(|node| (node.particles as usize).saturating_sub(leaf_size))
+            .sum();
+        self.levels.push(level);
+
+        // deepest level first, children are always complete before their parents
+        for level in self.levels.iter().rev() {
+            let (head, tail) = self.nodes.split_at_mut(level.end);
+            let particles = &self.particles;
+            head[level.clone()].par_iter_mut().for_each(|node| {
+                // nodes of the deepest level were never split, their first_child is not set
+                let children = match node.child_range() {
+                    range if range.is_empty() => &[][..],
+                    range => &tail[range.start - level.end..range.end - level.end],
+                };
+                node.compute_mass_distribution(children, &particles[node.particle_range()], quadrupole);
+            });
         }
 
-        self.center = self.tree.compute_mass_distribution();
+        stats.inserted = self.particles.len() - stats.coincident;
+        stats.depth = self.levels.iter().filter(|level| !level.is_empty()).count() - 1;
+        stats.nodes = self.nodes.len();
+        stats.leaves = self.nodes.par_iter().filter(|node| node.children == 0).count();
+        stats
     }
 
-    pub fn eval(&mut self) {
-        self.build_quadrant_tree();
+    /*
+     * Indices of the bodies in leaves that may lie within reach of pos, the caller checks the exact
+     * distance. Reach is radius, or node_radius[node] if that is larger, so searches can cover
+     * particles whose own search radius is longer than the one of the searching particle. An empty
+     * node_radius searches within radius only.
+     */
+    pub(crate) fn neighbours(&self, pos: Vec3, radius: f64, node_radius: &[f64], found: &mut Vec<usize>) {
+        found.clear();
+        if !self.nodes.is_empty() {
+            self.collect_neighbours(0, pos, radius, node_radius, found);
+        }
+    }
 
-        for i in 0..self.bodies.len() {
-            self.bodies[i].acc = self.tree.calculate_force(self.bodies[i]);
+    fn collect_neighbours(&self, index: usize, pos: Vec3, radius: f64, node_radius: &[f64], found: &mut Vec<usize>) {
+        let node = &self.nodes[index];
+        let reach = node_radius.get(index).map_or(radius, |&node| radius.max(node));
+        // distance to the closest point of the node, zero when pos is inside
+        let outside = (node.pos_lower_bound - pos).sup(&(pos - node.pos_upper_bound)).sup(&Vec3::zeros());
+        if outside.norm_squared() > reach * reach {
+            return;
+        }
+        if node.children == 0 {
+            found.extend(self.keys[node.particle_range()].iter().map(|&(_, body)| body as usize));
+            return;
+        }
+        for child in node.child_range() {
+            self.collect_neighbours(child, pos, radius, node_radius, found);
         }
     }
 
-    fn init(&mut self) {
-        for body in &self.bodies {
-            self.pos_upper_bound.x = self.pos_upper_bound.x.max(body.pos.x);
-            self.pos_upper_bound.y = self.pos_upper_bound.y.max(body.pos.y);
+    fn mass_center(&self) -> Vec3 {
+        self.nodes.first().map_or(Vec3::new(0.0, 0.0, 0.0), |root| root.mass_center)
+    }
 
-            self.pos_lower_bound.x = self.pos_lower_bound.x.min(body.pos.x);
-            self.pos_lower_bound.y = self.pos_lower_bound.y.min(body.pos.y);
+    // Compute the force acting from the node and it's children to a particle p
+    fn calculate_force(&self, index: usize, body: &Body, interaction: &Interaction) -> Vec3 {
+        let node = &self.nodes[index];
+        if node.children == 0 {
+            // leaves interact directly, same position means same body
+            let mut acc = Vec3::new(0.0, 0.0, 0.0);
+            for particle in &self.particles[node.particle_range()] {
+                if particle.pos == body.pos {
+                    continue;
+                }
+                let d = particle.pos - body.pos;
+                acc += interaction.gravitational_constant
+                    * particle.mass
+                    * interaction.softening.force_factor(
+                        d.dot(&d),
+                        body.softening * body.softening,
+                        particle.softening2,
+                    )
+                    * d;
+            }
+            return acc;
         }
 
-        // The Barnes Hut algorithm needs square shaped quadrants.
-        // calculate the height of the square including all particles (and a bit more space)
-        let l = 1.05
-            * (self.pos_upper_bound.x - self.pos_lower_bound.x)
-                .max(self.pos_upper_bound.y - self.pos_lower_bound.y);
-        self.roi = l * 1.5;
-
-        // compute the center of the region including all particles
-        let c = Vec2::new(
-            (self.pos_lower_bound.x + self.pos_upper_bound.x) / 2.0,
-            (self.pos_lower_bound.y + self.pos_upper_bound.y) / 2.0,
-        );
+        let r = (body.pos - node.mass_center).norm();
+        if node.is_far_enough(body, r, interaction) {
+            // THE HEART OF THE ALGORITHM
+            let k = interaction.gravitational_constant
+                * node.mass
+                * interaction.softening.force_factor(
+                    r * r,
+                    body.softening * body.softening,
+                    node.softening2,
+                );
+            let monopole = k * (node.mass_center - body.pos);
+            if interaction.quadrupole {
+                monopole
+                    + node.quadrupole_acceleration(body.pos - node.mass_center, r)
+                        * interaction.gravitational_constant
+            } else {
+                monopole
+            }
+        } else {
+            node.child_range()
+                .map(|child| self.calculate_force(child, body, interaction))
+                .sum()
+        }
+    }
 
-        self.pos_upper_bound.x = c.x + l / 2.0;
-        self.pos_lower_bound.x = c.x - l / 2.0;
-        self.pos_upper_bound.y = c.y + l / 2.0;
-        self.pos_lower_bound.y = c.y - l / 2.0;
+    // Compute the potential per unit mass at particle p from the node and it's children, same
+    // approximation as calculate_force
+    fn calculate_potential(&self, index: usize, body: &Body, interaction: &Interaction) -> f64 {
+        let node = &self.nodes[index];
+        if node.children == 0 {
+            return self.particles[node.particle_range()]
+                .iter()
+                .filter(|particle| particle.pos != body.pos)
+                .map(|particle| {
+                    let d = particle.pos - body.pos;
+                    -interaction.gravitational_constant
+                        * particle.mass
+                        * interaction.softening.potential_factor(
+                            d.dot(&d),
+                            body.softening * body.softening,
+                            particle.softening2,
+                        )
+                })
+                .sum();
+        }
 
-        //println!("initial particle distribution");
-        //println!("min={:?} max={:?}", self.pos_lower_bound, self.pos_upper_bound);
-        //println!("l={:?} roi={:?}", l, self.roi);
+        let r = (body.pos - node.mass_center).norm();
+        if node.is_far_enough(body, r, interaction) {
+            let monopole = -interaction.gravitational_constant
+                * node.mass
+                * interaction.softening.potential_factor(
+                    r * r,
+                    body.softening * body.softening,
+                    node.softening2,
+                );
+            if interaction.quadrupole {
+                monopole
+                    + node.quadrupole_potential(body.pos - node.mass_center, r)
+                        * interaction.gravitational_constant
+            } else {
+                monopole
+            }
+        } else {
+            node.child_range()
+                .map(|child| self.calculate_potential(child, body, interaction))
+                .sum()
+        }
     }
 }
 
-// taken from paper 'MATHEMATICAL MODEL FOR THE 0.5 BILLION YEARS AGED SUN'
-const APPROXIMATIONS_ADB6: [f64; 6] = [
-    4277.0 / 1440.0,
-    -7923.0 / 1440.0,
-    9982.0 / 1440.0,
-    -7298.0 / 1440.0,
-    2877.0 / 1440.0,
-    -475.0 / 1440.0,
-];
-
-// six step adams-bashforth integration method
-// https://web.mit.edu/10.001/Web/Course_Notes/Differential_Equations_Notes/node6.html
-pub struct IntegratorADB6 {
-    dimensions: usize,
-    timestep: f64,
-    data: [Vec<Body>; 6],
+// how the bodies were placed in the tree during the last build
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct TreeStats {
+    pub inserted: usize,
+    pub coincident: usize,    // share a leaf with another body (same position), interact with it directly
+    pub out_of_bounds: usize, // only bodies with non finite positions, they don't interact at all
+    pub depth: usize,         // of the deepest leaf, the root is at depth 0
+    pub nodes: usize,
+    pub leaves: usize,
 }
 
-impl IntegratorADB6 {
-    pub fn new(dimensions: usize, timestep: f64) -> Self {
-        Self {
-            dimensions: dimensions,
-            timestep: timestep,
-            data: [
-                vec![Body::new(Mass(0.0), Radius(0.0)); dimensions],
-                vec![Body::new(Mass(0.0), Radius(0.0)); dimensions],
-                vec![Body::new(Mass(0.0), Radius(0.0)); dimensions],
-                vec![Body::new(Mass(0.0), Radius(0.0)); dimensions],
-                vec![Body::new(Mass(0.0), Radius(0.0)); dimensions],
-                vec![Body::new(Mass(0.0), Radius(0.0)); dimensions],
-            ],
-        }
+#[derive(Default)]
+pub struct BarnesHutSimulation {
+    pos_upper_bound: Vec3,
+    pos_lower_bound: Vec3,
+    pub center: Vec3,
+    tree: Octree,
+    accelerations: Vec<Vec3>, // in morton order, reused between evaluations
+    stats: TreeStats,
+    gravitational_constant: f64,
+    softening: Softening,
+    quadrupole: bool,
+    criterion: OpeningCriterion,
+}
+
+impl BarnesHutSimulation {
+    pub fn new(gravitational_constant: f64) -> Self {
+        Self::with_softening(gravitational_constant, Softening::default())
     }
 
-    // TODO make integrator independent of barnes hut simulation? in original implementation we
-    // were just processing raw numeric data in doubles
-    pub fn integrate(&mut self, dt: f64, model: &mut BarnesHutSimulation) {
-        for i in 0..self.dimensions {
-            model.bodies[i].pos += dt
-                * (APPROXIMATIONS_ADB6[0] * self.data[5][i].pos
-                    + APPROXIMATIONS_ADB6[1] * self.data[4][i].pos
-                    + APPROXIMATIONS_ADB6[2] * self.data[3][i].pos
-                    + APPROXIMATIONS_ADB6[3] * self.data[2][i].pos
-                    + APPROXIMATIONS_ADB6[4] * self.data[1][i].pos
-                    + APPROXIMATIONS_ADB6[5] * self.data[0][i].pos);
-            model.bodies[i].vel += dt
-                * (APPROXIMATIONS_ADB6[0] * self.data[5][i].vel
-                    + APPROXIMATIONS_ADB6[1] * self.data[4][i].vel
-                    + APPROXIMATIONS_ADB6[2] * self.data[3][i].vel
-                    + APPROXIMATIONS_ADB6[3] * self.data[2][i].vel
-                    + APPROXIMATIONS_ADB6[4] * self.data[1][i].vel
-                    + APPROXIMATIONS_ADB6[5] * self.data[0][i].vel);
-            for j in 0..5 {
-                self.data[j][i] = self.data[j + 1][i];
-            }
-        }
-        for i in 0..self.dimensions {
-            self.data[5][i].pos = model.bodies[i].vel;
-            self.data[5][i].vel = model.bodies[i].acc;
-        }
+    pub fn with_softening(gravitational_constant: f64, softening: Softening) -> Self {
+        let mut obj: Self = Default::default();
+        obj.gravitational_constant = gravitational_constant;
+        obj.softening = softening;
+        obj
     }
 
-    pub fn set_initial_state(&mut self, model: &mut BarnesHutSimulation) {
-        assert!(model.bodies.len() == self.dimensions);
-        let initial = model.bodies.clone();
+    // nodes carry quadrupole moments in addition to mass and mass center, more accurate forces
+    // for the same opening angle at the cost of a slightly more expensive tree
+    pub fn set_quadrupole(&mut self, enabled: bool) {
+        self.quadrupole = enabled;
+    }
 
-        let mut k1 = model.bodies.clone();
-        let mut k2 = model.bodies.clone();
-        let mut k3 = model.bodies.clone();
-        let mut k4 = model.bodies.clone();
+    pub fn set_opening_criterion(&mut self, criterion: OpeningCriterion) {
+        self.criterion = criterion;
+    }
 
-        for n in 0..5 {
-            // k1
-            model.eval();
+    pub fn stats(&self) -> TreeStats {
+        self.stats
+    }
 
-            for i in 0..self.dimensions {
-                k1[i].pos = model.bodies[i].vel;
-                k1[i].vel = model.bodies[i].acc;
-                model.bodies[i].pos = initial[i].pos + self.timestep * 0.5 * model.bodies[i].vel;
-                model.bodies[i].vel = initial[i].vel + self.timestep * 0.5 * model.bodies[i].acc;
-            }
+    // tree of the last evaluation, built from the bodies passed to it
+    pub(crate) fn tree(&self) -> &Octree {
+        &self.tree
+    }
 
-            // k2
-            model.eval();
-            for i in 0..self.dimensions {
-                k2[i].pos = model.bodies[i].vel;
-                k2[i].vel = model.bodies[i].acc;
-                model.bodies[i].pos = initial[i].pos + self.timestep * 0.5 * model.bodies[i].vel;
-                model.bodies[i].vel = initial[i].vel + self.timestep * 0.5 * model.bodies[i].acc;
-            }
+    fn interaction(&self) -> Interaction {
+        Interaction {
+            gravitational_constant: self.gravitational_constant,
+            softening: self.softening,
+            criterion: self.criterion,
+            quadrupole: self.quadrupole,
+        }
+    }
 
-            // k3
-            model.eval();
-            for i in 0..self.dimensions {
-                k3[i].pos = model.bodies[i].vel;
-                k3[i].vel = model.bodies[i].acc;
-                model.bodies[i].pos = initial[i].pos + self.timestep * 0.5 * model.bodies[i].vel;
-                model.bodies[i].vel = initial[i].vel + self.timestep * 0.5 * model.bodies[i].acc;
-            }
+    // bounds are recomputed on every build, so escaping bodies stay in the tree
+    fn build_octree(&mut self, bodies: &[Body]) {
+        let (lower, upper) = cube_bounds(bodies);
+        self.pos_lower_bound = lower;
+        self.pos_upper_bound = upper;
+        // a single body per leaf, leaves are summed directly
+        self.stats = self.tree.build(bodies, lower, upper, 1, self.quadrupole);
+        self.center = self.tree.mass_center();
+    }
+}
 
-            // k4
-            model.eval();
+// The Barnes Hut algorithm needs cube shaped octants. Smallest cube around all bodies with finite
+// positions (and a bit more space), as lower and upper corner
+pub(crate) fn cube_bounds(bodies: &[Body]) -> (Vec3, Vec3) {
+    // min and max don't round, the parallel reduction is exact
+    let (lower, upper) = bodies
+        .par_iter()
+        .filter(|body| is_finite(&body.pos))
+        .map(|body| (body.pos, body.pos))
+        .reduce(
+            || {
+                (
+                    Vec3::repeat(std::f64::MAX),
+                    Vec3::repeat(std::f64::MIN),
+                )
+            },
+            |a, b| (a.0.inf(&b.0), a.1.sup(&b.1)),
+        );
 
-            for i in 0..self.dimensions {
-                k4[i].pos = model.bodies[i].vel;
-                k4[i].vel = model.bodies[i].acc;
-                model.bodies[i].pos = initial[i].pos
-                    + self.timestep / 6.0 * (k1[i].pos + 2.0 * (k2[i].pos + k3[i].pos) + k4[i].pos);
-                model.bodies[i].vel = initial[i].vel
-                    + self.timestep / 6.0 * (k1[i].vel + 2.0 * (k2[i].vel + k3[i].vel) + k4[i].vel);
+    // calculate the edge of the cube including all particles (and a bit more space)
+    let l = 1.05 * (upper - lower).max().max(f64::MIN_POSITIVE);
 
-                self.data[n][i] = k1[i];
+    // compute the center of the region including all particles
+    let c = (lower + upper) / 2.0;
+    (c - Vec3::repeat(l / 2.0), c + Vec3::repeat(l / 2.0))
+}
+
+impl ForceSolver for BarnesHutSimulation {
+    // fills acc of every body with the force from the tree built out of the same bodies
+    fn eval(&mut self, bodies: &mut [Body]) {
+        self.build_octree(bodies);
+
+        // walking in morton order, neighbouring bodies open mostly the same nodes. Every force is
+        // summed by a single thread in a fixed order, so the number of threads doesn't matter
+        let interaction = self.interaction();
+        let tree = &self.tree;
+        let sources: &[Body] = bodies;
+        self.accelerations.clear();
+        self.accelerations.par_extend(
+            tree.keys
+                .par_iter()
+                .map(|&(_, index)| tree.calculate_force(0, &sources[index as usize], &interaction)),
+        );
+        for (&(_, index), acc) in tree.keys.iter().zip(self.accelerations.iter()) {
+            bodies[index as usize].acc = *acc;
+        }
+        if self.stats.out_of_bounds > 0 {
+            for body in bodies.iter_mut() {
+                if !is_finite(&body.pos) {
+                    body.acc = tree.calculate_force(0, body, &interaction);
+                }
             }
         }
+    }
 
-        model.eval();
-
-        for i in 0..self.dimensions {
-            self.data[5][i].pos = model.bodies[i].vel;
-            self.data[5][i].vel = model.bodies[i].acc;
+    // the tree is built from all bodies, only the active ones walk it
+    fn eval_active(&mut self, bodies: &mut [Body], active: &[usize]) {
+        self.build_octree(bodies);
+
+        let interaction = self.interaction();
+        let tree = &self.tree;
+        let sources: &[Body] = bodies;
+        let accelerations: Vec<Vec3> = active
+            .par_iter()
+            .map(|&index| tree.calculate_force(0, &sources[index], &interaction))
+            .collect();
+        for (&index, acc) in active.iter().zip(accelerations) {
+            bodies[index].acc = acc;
         }
     }
-}
 
+    // every pair is counted twice when summing potentials of all bodies
+    fn potential_energy(&mut self, bodies: &[Body]) -> f64 {
+        self.build_octree(bodies);
+
+        // summed sequentially, parallel reductions would depend on how the work was split
+        let interaction = self.interaction();
+        let tree = &self.tree;
+        let potentials: Vec<f64> = bodies
+            .par_iter()
+            .map(|body| body.mass * tree.calculate_potential(0, body, &interaction))
+            .collect();
+        0.5 * potentials.iter().sum::<f64>()
+    }
+}
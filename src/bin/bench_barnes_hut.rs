@@ -0,0 +1,191 @@
+use galaxyy::barnes_hut::BarnesHutSimulation;
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::solver::ForceSolver;
+use rand::{Rng, SeedableRng};
+use std::time::Instant;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+/*
+ * The octree as it was before the node arena: bodies are inserted one by one, every node is a Box of
+ * its own and leaves hold copies of the bodies. Same opening criterion (geometric, theta 0.9) and
+ * softening as BarnesHutSimulation::new, monopoles only, evaluated by a single thread.
+ */
+mod boxed {
+    use galaxyy::body::Body;
+    use galaxyy::softening::Softening;
+
+    type Vec3 = nalgebra::Vector3<f64>;
+
+    const THETA: f64 = 0.9;
+    // coincident bodies share a leaf at this depth instead of splitting forever
+    const MAX_DEPTH: u32 = 21;
+
+    #[derive(Default)]
+    struct Node {
+        mass: f64,
+        mass_center: Vec3,
+        lower: Vec3,
+        upper: Vec3,
+        bodies: Vec<Body>,
+        children: [Option<Box<Node>>; 8],
+        split: bool,
+    }
+
+    impl Node {
+        fn new(lower: Vec3, upper: Vec3) -> Box<Node> {
+            Box::new(Node {
+                lower,
+                upper,
+                ..Default::default()
+            })
+        }
+
+        fn insert(&mut self, body: Body, depth: u32) {
+            if !self.split {
+                if self.bodies.is_empty() || depth == MAX_DEPTH {
+                    self.bodies.push(body);
+                    return;
+                }
+                self.split = true;
+                let resident = self.bodies.pop().unwrap();
+                self.insert_into_child(resident, depth);
+            }
+            self.insert_into_child(body, depth);
+        }
+
+        fn insert_into_child(&mut self, body: Body, depth: u32) {
+            let center = (self.lower + self.upper) / 2.0;
+            let octant = (body.pos.x > center.x) as usize
+                | ((body.pos.y > center.y) as usize) << 1
+                | ((body.pos.z > center.z) as usize) << 2;
+            let (lower, upper) = (self.lower, self.upper);
+            let child = self.children[octant].get_or_insert_with(|| {
+                let half = (upper - lower) / 2.0;
+                let offset = Vec3::new((octant & 1) as f64, ((octant >> 1) & 1) as f64, (octant >> 2) as f64);
+                let child_lower = lower + half.component_mul(&offset);
+                Node::new(child_lower, child_lower + half)
+            });
+            child.insert(body, depth + 1);
+        }
+
+        fn compute_mass_distribution(&mut self) {
+            if self.split {
+                for child in self.children.iter_mut().flatten() {
+                    child.compute_mass_distribution();
+                    self.mass += child.mass;
+                    self.mass_center += child.mass_center * child.mass;
+                }
+            } else {
+                for body in &self.bodies {
+                    self.mass += body.mass;
+                    self.mass_center += body.pos * body.mass;
+                }
+            }
+            self.mass_center /= self.mass;
+        }
+
+        fn calculate_force(&self, body: &Body, gravitational_constant: f64, softening: &Softening) -> Vec3 {
+            if !self.split {
+                return self
+                    .bodies
+                    .iter()
+                    .filter(|other| other.pos != body.pos)
+                    .map(|other| body.compute_force(other, gravitational_constant, softening))
+                    .sum();
+            }
+            let d = self.mass_center - body.pos;
+            let r = d.norm();
+            if (self.upper - self.lower).max() <= THETA * r {
+                gravitational_constant * self.mass * softening.force_factor(r * r, 0.0, 0.0) * d
+            } else {
+                self.children
+                    .iter()
+                    .flatten()
+                    .map(|child| child.calculate_force(body, gravitational_constant, softening))
+                    .sum()
+            }
+        }
+    }
+
+    pub struct BoxedTree {
+        gravitational_constant: f64,
+        softening: Softening,
+    }
+
+    impl BoxedTree {
+        pub fn new(gravitational_constant: f64) -> Self {
+            Self {
+                gravitational_constant,
+                softening: Softening::default(),
+            }
+        }
+
+        // the tree is built from scratch and dropped again on every evaluation
+        pub fn eval(&mut self, bodies: &mut [Body]) {
+            let (mut lower, mut upper) = (Vec3::repeat(f64::MAX), Vec3::repeat(f64::MIN));
+            for body in bodies.iter() {
+                lower = lower.inf(&body.pos);
+                upper = upper.sup(&body.pos);
+            }
+            let size = 1.05 * (upper - lower).max();
+            let center = (lower + upper) / 2.0;
+            let mut root = Node::new(center - Vec3::repeat(size / 2.0), center + Vec3::repeat(size / 2.0));
+            for body in bodies.iter() {
+                root.insert(*body, 0);
+            }
+            root.compute_mass_distribution();
+
+            for body in bodies.iter_mut() {
+                body.acc = root.calculate_force(body, self.gravitational_constant, &self.softening);
+            }
+        }
+    }
+}
+
+// exponential disc, similar to a single galaxy of GalaxyCollisionBarnesHut
+fn get_disc(count: usize) -> Vec<Body> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1729);
+    (0..count)
+        .map(|_| {
+            let r = -2.0 * (1.0 - rng.gen_range(0.0..1.0f64)).ln();
+            let a = 2.0 * std::f64::consts::PI * rng.gen_range(0.0..1.0);
+            let mut body = Body::new(Mass(0.03 + 20.0 * rng.gen_range(0.0..1.0)), Radius(0.05));
+            body.pos = Vec3::new(r * a.cos(), r * a.sin(), 0.0);
+            body
+        })
+        .collect()
+}
+
+// best of several runs of eval in milliseconds, the machine is rarely idle
+fn best_of<F: FnMut(&mut [Body])>(bodies: &mut [Body], mut eval: F) -> f64 {
+    const EVALUATIONS: u32 = 10;
+    eval(bodies); // warm up, buffers are allocated here
+    let mut best = f64::MAX;
+    for _ in 0..EVALUATIONS {
+        let start = Instant::now();
+        eval(bodies);
+        best = best.min(start.elapsed().as_secs_f64() * 1000.0);
+    }
+    best
+}
+
+/*
+ * Time of one full force evaluation (tree build + walk) for growing particle counts, with the Box per
+ * node tree and the node arena on a single thread and the node arena on all threads of the rayon pool
+ * (RAYON_NUM_THREADS).
+ */
+fn main() {
+    let single = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+    println!("threads: {}", rayon::current_num_threads());
+    println!("{:>10} {:>16} {:>16} {:>16}", "bodies", "box, 1 thread", "arena, 1 thread", "arena, threads");
+    for &count in &[1000, 5000, 20000, 100000] {
+        let mut bodies = get_disc(count);
+        let mut boxed = boxed::BoxedTree::new(1.0);
+        let mut arena = BarnesHutSimulation::new(1.0);
+        let boxed_ms = single.install(|| best_of(&mut bodies, |bodies| boxed.eval(bodies)));
+        let single_ms = single.install(|| best_of(&mut bodies, |bodies| arena.eval(bodies)));
+        let parallel_ms = best_of(&mut bodies, |bodies| arena.eval(bodies));
+        println!("{:>10} {:>16.3} {:>16.3} {:>16.3}", count, boxed_ms, single_ms, parallel_ms);
+    }
+}
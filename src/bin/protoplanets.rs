@@ -0,0 +1,13 @@
+use galaxyy::scenario::{Scenario, Protoplanets};
+use galaxyy::screen::{TextRender, Zoom};
+
+fn main() {
+    let mut scenario = Protoplanets::new(400);
+    let mut renderer = TextRender::new(Zoom(5.0));
+    let dt = 1.0 / 100.0;
+
+    loop {
+        scenario.process(dt);
+        scenario.draw(&mut renderer);
+    }
+}
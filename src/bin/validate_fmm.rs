@@ -0,0 +1,113 @@
+use galaxyy::barnes_hut::{BarnesHutSimulation, GAMMA};
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::fmm::FastMultipole;
+use galaxyy::scenario::GalaxyCollisionBarnesHut;
+use galaxyy::softening::Softening;
+use galaxyy::solver::{DirectSolver, ForceSolver};
+use rand::{Rng, SeedableRng};
+use std::time::Instant;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// median and maximum relative error of accelerations against the reference
+fn errors(bodies: &[Body], reference: &[Body]) -> (f64, f64) {
+    let mut errors: Vec<f64> = bodies
+        .iter()
+        .zip(reference)
+        .map(|(body, exact)| (body.acc - exact.acc).norm() / exact.acc.norm())
+        .collect();
+    errors.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    (errors[errors.len() / 2], errors[errors.len() - 1])
+}
+
+// one evaluation of the solver, returns milliseconds, errors and potential energy
+fn measure(solver: &mut dyn ForceSolver, bodies: &[Body], reference: &[Body]) -> (f64, f64, f64, f64) {
+    let mut bodies = bodies.to_vec();
+    let start = Instant::now();
+    solver.eval(&mut bodies);
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+    let (median, max) = errors(&bodies, reference);
+    (elapsed, median, max, solver.potential_energy(&bodies))
+}
+
+// exponential disc, same as bench_barnes_hut
+fn get_disc(count: usize) -> Vec<Body> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1729);
+    (0..count)
+        .map(|_| {
+            let r = -2.0 * (1.0 - rng.gen_range(0.0..1.0f64)).ln();
+            let a = 2.0 * std::f64::consts::PI * rng.gen_range(0.0..1.0);
+            let mut body = Body::new(Mass(0.03 + 20.0 * rng.gen_range(0.0..1.0)), Radius(0.05));
+            body.pos = Vec3::new(r * a.cos(), r * a.sin(), 0.0);
+            body
+        })
+        .collect()
+}
+
+// accuracy of one softening kernel against direct summation
+fn compare(bodies: &[Body], softening: Softening) {
+    let mut reference = bodies.to_vec();
+    let mut direct = DirectSolver::with_softening(GAMMA, softening);
+    let start = Instant::now();
+    direct.eval(&mut reference);
+    let elapsed = start.elapsed().as_secs_f64() * 1000.0;
+    let energy = direct.potential_energy(&reference);
+
+    println!("{:?}", softening);
+    println!("{:>24} {:>10} {:>12} {:>12} {:>12}", "solver", "ms", "median err", "max err", "energy err");
+    println!("{:>24} {:>10.2} {:>12} {:>12} {:>12}", "direct", elapsed, "-", "-", "-");
+
+    let mut barnes_hut = BarnesHutSimulation::with_softening(GAMMA, softening);
+    barnes_hut.set_quadrupole(true);
+    let (ms, median, max, potential) = measure(&mut barnes_hut, bodies, &reference);
+    println!(
+        "{:>24} {:>10.2} {:>12.3e} {:>12.3e} {:>12.3e}",
+        "barnes-hut quadrupole",
+        ms,
+        median,
+        max,
+        (potential - energy).abs() / energy.abs()
+    );
+
+    for &order in &[2, 4, 6, 8, 12, 16] {
+        let mut fmm = FastMultipole::with_softening(GAMMA, softening);
+        fmm.set_order(order);
+        let (ms, median, max, potential) = measure(&mut fmm, bodies, &reference);
+        println!(
+            "{:>24} {:>10.2} {:>12.3e} {:>12.3e} {:>12.3e}",
+            format!("fmm order {}", order),
+            ms,
+            median,
+            max,
+            (potential - energy).abs() / energy.abs()
+        );
+    }
+    println!();
+}
+
+// fast multipole solver against direct summation on the initial conditions of the galaxy collision
+fn main() {
+    let bodies = GalaxyCollisionBarnesHut::new().bodies().to_vec();
+    println!("{} bodies, threads: {}", bodies.len(), rayon::current_num_threads());
+    println!();
+    // without softening the error only depends on the expansion order
+    for &softening in &[Softening::default(), Softening::Spline(0.1), Softening::None] {
+        compare(&bodies, softening);
+    }
+
+    println!("{:>10} {:>16} {:>16}", "bodies", "barnes-hut ms", "fmm ms");
+    for &count in &[20000, 100000] {
+        let mut bodies = get_disc(count);
+        let mut barnes_hut = BarnesHutSimulation::new(1.0);
+        barnes_hut.set_quadrupole(true);
+        let mut fmm = FastMultipole::new(1.0);
+        let mut timings = Vec::new();
+        for solver in [&mut barnes_hut as &mut dyn ForceSolver, &mut fmm].iter_mut() {
+            solver.eval(&mut bodies); // warm up
+            let start = Instant::now();
+            solver.eval(&mut bodies);
+            timings.push(start.elapsed().as_secs_f64() * 1000.0);
+        }
+        println!("{:>10} {:>16.2} {:>16.2}", count, timings[0], timings[1]);
+    }
+}
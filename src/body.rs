@@ -1,43 +1,95 @@
-type Vec2 = nalgebra::Vector2<f64>;
+use crate::softening::Softening;
+
+type Vec3 = nalgebra::Vector3<f64>;
 
 pub struct Mass(pub f64);
 pub struct Radius(pub f64);
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Body {
-    pub pos: Vec2,
-    pub vel: Vec2,
-    pub acc: Vec2,
+    pub pos: Vec3,
+    pub vel: Vec3,
+    pub acc: Vec3,
     pub mass: f64,
     pub radius: f64,
+    pub softening: f64, // epsilon used by Softening::PerBody
 }
 
 impl Body {
     pub fn new(m: Mass, r: Radius) -> Body {
         Body {
-            pos: Vec2::new(0.0, 0.0),
-            vel: Vec2::new(0.0, 0.0),
-            acc: Vec2::new(0.0, 0.0),
+            pos: Vec3::new(0.0, 0.0, 0.0),
+            vel: Vec3::new(0.0, 0.0, 0.0),
+            acc: Vec3::new(0.0, 0.0, 0.0),
             mass: m.0,
             radius: r.0,
+            softening: 0.0,
         }
     }
 
-    pub fn compute_force(&self, other: &Self, gravitational_constant: f64) -> Vec2 {
-        const S_SOFT: f64 = 0.1 * 0.1;
-        let dist = ((self.pos - other.pos).dot(&(self.pos - other.pos)) + S_SOFT).sqrt();
-        let acc =
-            gravitational_constant * other.mass * (other.pos - self.pos) / (dist * dist * dist);
+    pub fn compute_force(
+        &self,
+        other: &Self,
+        gravitational_constant: f64,
+        softening: &Softening,
+    ) -> Vec3 {
+        let dist2 = (self.pos - other.pos).dot(&(self.pos - other.pos));
+        let factor = softening.force_factor(
+            dist2,
+            self.softening * self.softening,
+            other.softening * other.softening,
+        );
+        let acc = gravitational_constant * other.mass * factor * (other.pos - self.pos);
         return acc;
     }
 
-    pub fn pull_by(&mut self, other: &Self, gravitational_constant: f64) {
-        self.acc += self.compute_force(other, gravitational_constant);
+    // time derivative of compute_force, both bodies moving with their velocities
+    pub fn compute_jerk(
+        &self,
+        other: &Self,
+        gravitational_constant: f64,
+        softening: &Softening,
+    ) -> Vec3 {
+        let d = other.pos - self.pos;
+        let v = other.vel - self.vel;
+        let dist2 = d.dot(&d);
+        let (eps2_1, eps2_2) = (self.softening * self.softening, other.softening * other.softening);
+        let factor = softening.force_factor(dist2, eps2_1, eps2_2);
+        let derivative = softening.force_factor_derivative(dist2, eps2_1, eps2_2);
+        gravitational_constant * other.mass * (factor * v + 2.0 * derivative * d.dot(&v) * d)
+    }
+
+    // potential per unit mass at this body caused by the other one, consistent with compute_force
+    pub fn compute_potential(
+        &self,
+        other: &Self,
+        gravitational_constant: f64,
+        softening: &Softening,
+    ) -> f64 {
+        let dist2 = (self.pos - other.pos).dot(&(self.pos - other.pos));
+        let factor = softening.potential_factor(
+            dist2,
+            self.softening * self.softening,
+            other.softening * other.softening,
+        );
+        -gravitational_constant * other.mass * factor
     }
 
-    pub fn process_forces(&mut self, dt: f64) {
-        self.vel += dt * self.acc;
-        self.pos += dt * self.vel;
-        self.acc = Vec2::new(0.0, 0.0);
+    pub fn pull_by(&mut self, other: &Self, gravitational_constant: f64, softening: &Softening) {
+        self.acc += self.compute_force(other, gravitational_constant, softening);
+    }
+}
+
+// planar solvers only see the x-y plane, a body off it would silently get the force of its
+// projection. Bodies which escaped to infinity aren't evaluated by any solver and don't count
+pub(crate) fn assert_planar(bodies: &[Body], solver: &str) {
+    if let Some(index) = bodies
+        .iter()
+        .position(|body| body.pos.z != 0.0 && body.pos.iter().all(|v| v.is_finite()))
+    {
+        panic!(
+            "{} is planar, body {} is at z = {}, use BarnesHutSimulation or DirectSolver for 3D",
+            solver, index, bodies[index].pos.z
+        );
     }
 }
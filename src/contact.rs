@@ -0,0 +1,21 @@
+use crate::body::Body;
+
+mod bounce;
+mod grid;
+mod merge;
+mod sink;
+
+pub use bounce::Bounce;
+pub use grid::SpatialGrid;
+pub use merge::{MergeEvent, Merger};
+pub use sink::{AccretionEvent, Sinks};
+
+/*
+ * Contact handlers resolve bodies whose spheres (Body::radius) overlap after an integration step.
+ * They may change the number of bodies, so they get the whole vector, and the current simulation time
+ * so events can be logged. `resolve` returns whether any body was touched, the integrator then has to
+ * be reset as the state it kept from the last step doesn't hold anymore (Integrator::reset).
+ */
+pub trait ContactHandler {
+    fn resolve(&mut self, bodies: &mut Vec<Body>, time: f64) -> bool;
+}
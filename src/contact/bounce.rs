@@ -0,0 +1,82 @@
+use crate::body::Body;
+use crate::contact::{ContactHandler, SpatialGrid};
+
+/*
+ * Hard sphere collisions with a coefficient of restitution, 1 is perfectly elastic and 0 makes the
+ * bodies stick to each other (without merging them). Approaching pairs exchange an impulse along the
+ * line of centres, which conserves momentum, then the overlap is removed by pushing the bodies apart
+ * in inverse proportion to their masses.
+ */
+#[derive(Default)]
+pub struct Bounce {
+    restitution: f64,
+    iterations: usize,
+    grid: SpatialGrid,
+    collisions: usize,
+}
+
+impl Bounce {
+    pub fn new(restitution: f64) -> Self {
+        Self::with_iterations(restitution, 1)
+    }
+
+    // dense piles need several passes, an impulse may push a body into its neighbour
+    pub fn with_iterations(restitution: f64, iterations: usize) -> Self {
+        Self {
+            restitution,
+            iterations,
+            ..Default::default()
+        }
+    }
+
+    // number of resolved impacts since creation
+    pub fn collisions(&self) -> usize {
+        self.collisions
+    }
+
+    // returns false if the pair couldn't be separated (both massless or on the same spot)
+    fn resolve_pair(&mut self, body1: &mut Body, body2: &mut Body) -> bool {
+        let inv_mass1 = if body1.mass > 0.0 { 1.0 / body1.mass } else { 0.0 };
+        let inv_mass2 = if body2.mass > 0.0 { 1.0 / body2.mass } else { 0.0 };
+        let inv_mass = inv_mass1 + inv_mass2;
+        let delta = body2.pos - body1.pos;
+        let dist = delta.norm();
+        if inv_mass == 0.0 || dist == 0.0 {
+            return false;
+        }
+        let normal = delta / dist;
+
+        let approach = (body2.vel - body1.vel).dot(&normal);
+        if approach < 0.0 {
+            let impulse = -(1.0 + self.restitution) * approach / inv_mass;
+            body1.vel -= impulse * inv_mass1 * normal;
+            body2.vel += impulse * inv_mass2 * normal;
+            self.collisions += 1;
+        }
+
+        let penetration = body1.radius + body2.radius - dist;
+        if penetration > 0.0 {
+            body1.pos -= penetration * inv_mass1 / inv_mass * normal;
+            body2.pos += penetration * inv_mass2 / inv_mass * normal;
+        }
+        true
+    }
+}
+
+impl ContactHandler for Bounce {
+    fn resolve(&mut self, bodies: &mut Vec<Body>, _time: f64) -> bool {
+        let mut any = false;
+        for _ in 0..self.iterations {
+            self.grid.build(bodies);
+            let pairs = self.grid.overlapping_pairs(bodies);
+            if pairs.is_empty() {
+                break;
+            }
+            for (i, j) in pairs {
+                let (left, right) = bodies.split_at_mut(j);
+                any |= self.resolve_pair(&mut left[i], &mut right[0]);
+            }
+        }
+        any
+    }
+}
@@ -0,0 +1,126 @@
+use crate::body::Body;
+use std::collections::HashMap;
+
+// bodies with a radius above this many mean radii are kept out of the grid
+const LARGE: f64 = 4.0;
+
+// the 13 of the 26 neighbouring cells that come after a cell, (dx, dy, dz) > (0, 0, 0)
+const FORWARD: [(i64, i64, i64); 13] = [
+    (0, 0, 1),
+    (0, 1, -1),
+    (0, 1, 0),
+    (0, 1, 1),
+    (1, -1, -1),
+    (1, -1, 0),
+    (1, -1, 1),
+    (1, 0, -1),
+    (1, 0, 0),
+    (1, 0, 1),
+    (1, 1, -1),
+    (1, 1, 0),
+    (1, 1, 1),
+];
+
+/*
+ * Uniform grid for finding overlapping spheres in O(N) instead of checking all pairs.
+ * Every body is inserted into the cell of its centre, the cell size is the largest diameter so
+ * overlapping bodies are at most one cell apart. Bodies much larger than the mean (e.g. galaxy
+ * centres among stars) would blow up the cells, they are kept out of the grid and checked against
+ * every body instead.
+ */
+#[derive(Default)]
+pub struct SpatialGrid {
+    cell_size: f64,
+    large_radius: f64, // bodies above it are in `large`
+    cells: HashMap<(i64, i64, i64), Vec<usize>>,
+    large: Vec<usize>,
+}
+
+impl SpatialGrid {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn build(&mut self, bodies: &[Body]) {
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        self.large.clear();
+        if bodies.is_empty() {
+            return;
+        }
+
+        let mean_radius = bodies.iter().map(|b| b.radius).sum::<f64>() / bodies.len() as f64;
+        self.large_radius = LARGE * mean_radius;
+        let max_radius = bodies
+            .iter()
+            .map(|b| b.radius)
+            .filter(|&radius| radius <= self.large_radius)
+            .fold(0.0, f64::max);
+        self.cell_size = 2.0 * max_radius;
+        if self.cell_size <= 0.0 {
+            self.cell_size = 1.0;
+        }
+
+        for (i, body) in bodies.iter().enumerate() {
+            if body.radius > self.large_radius {
+                self.large.push(i);
+            } else {
+                self.cells.entry(self.cell(body)).or_default().push(i);
+            }
+        }
+        // drop cells emptied since the previous build
+        self.cells.retain(|_, cell| !cell.is_empty());
+    }
+
+    fn cell(&self, body: &Body) -> (i64, i64, i64) {
+        let cell = |v: f64| (v / self.cell_size).floor() as i64;
+        (cell(body.pos.x), cell(body.pos.y), cell(body.pos.z))
+    }
+
+    // pairs (i, j), i < j of bodies that overlap, grid has to be built from the same bodies
+    pub fn overlapping_pairs(&mut self, bodies: &[Body]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+        // every pair of neighbouring cells once, from the cell with the lower key
+        for (&(x, y, z), cell) in &self.cells {
+            for (n, &i) in cell.iter().enumerate() {
+                for &j in &cell[n + 1..] {
+                    if overlap(&bodies[i], &bodies[j]) {
+                        pairs.push((i.min(j), i.max(j)));
+                    }
+                }
+            }
+            for &(dx, dy, dz) in &FORWARD {
+                if let Some(other) = self.cells.get(&(x + dx, y + dy, z + dz)) {
+                    for &i in cell {
+                        for &j in other {
+                            if overlap(&bodies[i], &bodies[j]) {
+                                pairs.push((i.min(j), i.max(j)));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // large bodies against all others, a pair of two large ones is found from the first of them
+        for &i in &self.large {
+            for (j, other) in bodies.iter().enumerate() {
+                if j == i || (j < i && other.radius > self.large_radius) {
+                    continue;
+                }
+                if overlap(&bodies[i], other) {
+                    pairs.push((i.min(j), i.max(j)));
+                }
+            }
+        }
+        // ordered by the first body, then the second
+        pairs.sort_unstable();
+        pairs
+    }
+}
+
+pub fn overlap(body1: &Body, body2: &Body) -> bool {
+    let reach = body1.radius + body2.radius;
+    (body1.pos - body2.pos).dot(&(body1.pos - body2.pos)) < reach * reach
+}
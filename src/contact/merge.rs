@@ -0,0 +1,119 @@
+use crate::body::Body;
+use crate::contact::grid::overlap;
+use crate::contact::{ContactHandler, SpatialGrid};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MergeEvent {
+    pub time: f64,
+    // indices in the body list before the merge, the survivor keeps its index minus removed bodies in
+    // front of it
+    pub survivor: usize,
+    pub absorbed: usize,
+    pub mass: f64, // mass after the merge
+    pub pos: Vec3,
+}
+
+/*
+ * Perfectly inelastic collisions, overlapping bodies are combined into one.
+ * Mass and momentum are conserved, the new centre is the centre of mass and the radius is computed from
+ * the sum of volumes (constant density). The body with the lower index survives, so bodies stored in
+ * front of the list (e.g. galaxy centres) keep their positions.
+ * Removed bodies shift the indices of all bodies behind them. Solvers that refer to bodies by index
+ * (RestrictedSolver sources, Centre::Body) only stay valid if the bodies they refer to are protected:
+ * the first `protected` bodies never merge with each other, so none of them is ever removed.
+ */
+#[derive(Default)]
+pub struct Merger {
+    grid: SpatialGrid,
+    log: Vec<MergeEvent>,
+    protected: usize,
+}
+
+impl Merger {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // the first `protected` bodies keep their indices, they absorb others but not each other
+    pub fn with_protected(protected: usize) -> Self {
+        Self {
+            protected,
+            ..Default::default()
+        }
+    }
+
+    pub fn log(&self) -> &[MergeEvent] {
+        &self.log
+    }
+
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+
+    // one pass over the pairs that overlapped at its start. A survivor may absorb several bodies, it moves
+    // and grows with each of them, so every pair is checked again against the merged bodies. Pairs that
+    // don't touch anymore or lost a body are left to the next pass, returns false if nothing merged
+    fn merge_overlapping(&mut self, bodies: &mut Vec<Body>, time: f64) -> bool {
+        self.grid.build(bodies);
+        let pairs = self.grid.overlapping_pairs(bodies);
+
+        let mut removed = vec![false; bodies.len()];
+        let mut any = false;
+        for (i, j) in pairs {
+            if removed[i] || removed[j] || j < self.protected || !overlap(&bodies[i], &bodies[j]) {
+                continue;
+            }
+            any = true;
+            bodies[i] = merge(&bodies[i], &bodies[j]);
+            removed[j] = true;
+            self.log.push(MergeEvent {
+                time,
+                survivor: i,
+                absorbed: j,
+                mass: bodies[i].mass,
+                pos: bodies[i].pos,
+            });
+        }
+        if !any {
+            return false;
+        }
+
+        let mut idx = 0;
+        bodies.retain(|_| {
+            idx += 1;
+            !removed[idx - 1]
+        });
+        true
+    }
+}
+
+impl ContactHandler for Merger {
+    // merged bodies grow and may touch others, repeat until nothing overlaps
+    fn resolve(&mut self, bodies: &mut Vec<Body>, time: f64) -> bool {
+        let mut any = false;
+        while self.merge_overlapping(bodies, time) {
+            any = true;
+        }
+        any
+    }
+}
+
+// mass weighted means, two massless bodies (test particles) are averaged with equal weights
+pub fn merge(body1: &Body, body2: &Body) -> Body {
+    let mass = body1.mass + body2.mass;
+    let (w1, w2) = if mass > 0.0 {
+        (body1.mass / mass, body2.mass / mass)
+    } else {
+        (0.5, 0.5)
+    };
+    let mut merged = *body1;
+    merged.mass = mass;
+    merged.pos = w1 * body1.pos + w2 * body2.pos;
+    merged.vel = w1 * body1.vel + w2 * body2.vel;
+    merged.acc = w1 * body1.acc + w2 * body2.acc;
+    merged.radius = (body1.radius.powi(3) + body2.radius.powi(3)).cbrt();
+    merged.softening = (w1 * body1.softening.powi(2) + w2 * body2.softening.powi(2)).sqrt();
+    merged
+}
@@ -0,0 +1,136 @@
+use crate::body::Body;
+use crate::contact::merge::merge;
+use crate::contact::ContactHandler;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AccretionEvent {
+    pub time: f64,
+    // indices in the body list before the accretion, sinks keep theirs unless a sink was absorbed
+    // in front of them
+    pub sink: usize,
+    pub absorbed: usize,
+    pub accreted: f64, // mass of the absorbed body
+    pub mass: f64,     // mass of the sink after the accretion
+    pub pos: Vec3,     // where the absorbed body was
+    pub vel: Vec3,     // velocity of the absorbed body relative to the sink
+}
+
+/*
+ * Sink particles (Bate, Bonnell & Price 1995) for black holes and stars that bodies fall into. The
+ * first bodies are sinks, every body that comes closer to a sink than its accretion radius is absorbed.
+ * Mass and momentum are conserved, the sink moves to the centre of mass and keeps its radius and
+ * softening. Without sinks a body passing a softened point mass within a step or two gets slingshot.
+ * Bodies are absorbed on their closest approach during the step, not only on their end positions: the
+ * relative motion since the previous call is taken as a straight line back from the end positions, so a
+ * body that crosses the accretion radius within one step is absorbed as well. The first call only sees
+ * the end positions.
+ * A sink inside the accretion radius of an earlier sink is absorbed as well, the radius of the
+ * survivor stays. Every accretion is logged, summing `accreted` per sink gives its growth history.
+ * An absorbed sink shifts the indices of all bodies behind it. Solvers that refer to bodies by index
+ * (RestrictedSolver sources, the relativistic bodies of PostNewtonian, Centre::Body) only stay valid if
+ * those sinks are protected: the first `protected` sinks never absorb each other.
+ */
+#[derive(Default)]
+pub struct Sinks {
+    radii: Vec<f64>,
+    log: Vec<AccretionEvent>,
+    protected: usize,
+    last_time: Option<f64>,
+}
+
+impl Sinks {
+    // accretion radii of the first bodies, one per sink
+    pub fn new(radii: Vec<f64>) -> Self {
+        Self::with_protected(radii, 0)
+    }
+
+    // the first `protected` sinks keep their indices, they accrete bodies and later sinks but not each other
+    pub fn with_protected(radii: Vec<f64>, protected: usize) -> Self {
+        Self {
+            radii,
+            log: Vec::new(),
+            protected,
+            last_time: None,
+        }
+    }
+
+    // accretion radii of the sinks that are left
+    pub fn radii(&self) -> &[f64] {
+        &self.radii
+    }
+
+    pub fn log(&self) -> &[AccretionEvent] {
+        &self.log
+    }
+
+    pub fn clear_log(&mut self) {
+        self.log.clear();
+    }
+}
+
+impl ContactHandler for Sinks {
+    // single pass, a body goes to the first sink that reaches it
+    fn resolve(&mut self, bodies: &mut Vec<Body>, time: f64) -> bool {
+        let dt = self.last_time.map_or(0.0, |last| (time - last).max(0.0));
+        self.last_time = Some(time);
+        let sinks = self.radii.len().min(bodies.len());
+        let mut removed = vec![false; bodies.len()];
+        let mut any = false;
+        for j in 0..bodies.len() {
+            if j < self.protected {
+                continue;
+            }
+            for i in 0..sinks.min(j) {
+                let radius = self.radii[i];
+                if removed[i] || closest_approach(&bodies[i], &bodies[j], dt) >= radius * radius {
+                    continue;
+                }
+                let mut sink = merge(&bodies[i], &bodies[j]);
+                sink.radius = bodies[i].radius;
+                sink.softening = bodies[i].softening;
+                self.log.push(AccretionEvent {
+                    time,
+                    sink: i,
+                    absorbed: j,
+                    accreted: bodies[j].mass,
+                    mass: sink.mass,
+                    pos: bodies[j].pos,
+                    vel: bodies[j].vel - bodies[i].vel,
+                });
+                bodies[i] = sink;
+                removed[j] = true;
+                any = true;
+                break;
+            }
+        }
+        if !any {
+            return false;
+        }
+
+        let mut idx = 0;
+        self.radii.retain(|_| {
+            idx += 1;
+            !removed.get(idx - 1).copied().unwrap_or(false)
+        });
+        let mut idx = 0;
+        bodies.retain(|_| {
+            idx += 1;
+            !removed[idx - 1]
+        });
+        true
+    }
+}
+
+// smallest squared distance of two bodies over the last dt, moving in straight lines back from where they are
+fn closest_approach(a: &Body, b: &Body, dt: f64) -> f64 {
+    let separation = b.pos - a.pos;
+    let velocity = b.vel - a.vel;
+    let speed2 = velocity.norm_squared();
+    if speed2 == 0.0 {
+        return separation.norm_squared();
+    }
+    let back = (separation.dot(&velocity) / speed2).max(0.0).min(dt);
+    (separation - back * velocity).norm_squared()
+}
@@ -0,0 +1,142 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+/*
+ * Conserved quantities of a particle set. In an isolated system total energy, linear momentum and
+ * angular momentum stay constant, so their drift from the initial state tells how much error the
+ * integrator, the timestep and the force approximation introduce.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Diagnostics {
+    pub kinetic_energy: f64,
+    pub potential_energy: f64,
+    pub momentum: Vec3,
+    pub angular_momentum: Vec3, // taken around the origin
+    // sums of magnitudes, used to normalize momentum drift when the totals are close to zero
+    momentum_scale: f64,
+    angular_momentum_scale: f64,
+}
+
+impl Diagnostics {
+    // potential energy is taken from the solver so it matches the forces used for integration
+    pub fn compute(bodies: &[Body], solver: &mut dyn ForceSolver) -> Self {
+        Self {
+            kinetic_energy: kinetic_energy(bodies),
+            potential_energy: solver.potential_energy(bodies),
+            momentum: momentum(bodies),
+            angular_momentum: angular_momentum(bodies),
+            momentum_scale: bodies.iter().map(|b| b.mass * b.vel.norm()).sum(),
+            angular_momentum_scale: bodies
+                .iter()
+                .map(|b| b.mass * b.pos.cross(&b.vel).norm())
+                .sum(),
+        }
+    }
+
+    pub fn total_energy(&self) -> f64 {
+        self.kinetic_energy + self.potential_energy
+    }
+
+    // 2K/|W|, equals one for a system in virial equilibrium
+    pub fn virial_ratio(&self) -> f64 {
+        2.0 * self.kinetic_energy / self.potential_energy.abs()
+    }
+}
+
+pub fn kinetic_energy(bodies: &[Body]) -> f64 {
+    bodies
+        .iter()
+        .map(|b| 0.5 * b.mass * b.vel.dot(&b.vel))
+        .sum()
+}
+
+pub fn momentum(bodies: &[Body]) -> Vec3 {
+    bodies
+        .iter()
+        .fold(Vec3::new(0.0, 0.0, 0.0), |acc, b| acc + b.mass * b.vel)
+}
+
+pub fn angular_momentum(bodies: &[Body]) -> Vec3 {
+    bodies
+        .iter()
+        .fold(Vec3::new(0.0, 0.0, 0.0), |acc, b| acc + b.mass * b.pos.cross(&b.vel))
+}
+
+pub fn center_of_mass(bodies: &[Body]) -> Vec3 {
+    let mass: f64 = bodies.iter().map(|b| b.mass).sum();
+    bodies
+        .iter()
+        .fold(Vec3::new(0.0, 0.0, 0.0), |acc, b| acc + b.mass * b.pos)
+        / mass
+}
+
+// relative change of conserved quantities since the first recorded state
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Drift {
+    pub energy: f64,
+    pub momentum: f64,
+    pub angular_momentum: f64,
+}
+
+#[derive(Default)]
+pub struct DiagnosticsTracker {
+    initial: Option<Diagnostics>,
+    current: Option<Diagnostics>,
+}
+
+impl DiagnosticsTracker {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    // the first recorded state is taken as t=0
+    pub fn record(&mut self, diagnostics: Diagnostics) {
+        if self.initial.is_none() {
+            self.initial = Some(diagnostics);
+        }
+        self.current = Some(diagnostics);
+    }
+
+    pub fn reset(&mut self) {
+        self.initial = None;
+        self.current = None;
+    }
+
+    pub fn initial(&self) -> Option<Diagnostics> {
+        self.initial
+    }
+
+    pub fn current(&self) -> Option<Diagnostics> {
+        self.current
+    }
+
+    pub fn drift(&self) -> Drift {
+        match (self.initial, self.current) {
+            (Some(initial), Some(current)) => Drift {
+                energy: relative_change(
+                    current.total_energy() - initial.total_energy(),
+                    initial.total_energy().abs(),
+                ),
+                momentum: relative_change(
+                    (current.momentum - initial.momentum).norm(),
+                    initial.momentum_scale,
+                ),
+                angular_momentum: relative_change(
+                    (current.angular_momentum - initial.angular_momentum).norm(),
+                    initial.angular_momentum_scale,
+                ),
+            },
+            _ => Drift::default(),
+        }
+    }
+}
+
+fn relative_change(change: f64, scale: f64) -> f64 {
+    if scale == 0.0 {
+        change.abs()
+    } else {
+        change.abs() / scale
+    }
+}
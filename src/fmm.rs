@@ -0,0 +1,526 @@
+use crate::barnes_hut::{cube_bounds, Node, Octree, Particle};
+use crate::body::{assert_planar, Body};
+use crate::softening::Softening;
+use crate::solver::ForceSolver;
+use rayon::prelude::*;
+
+type Vec2 = nalgebra::Vector2<f64>;
+type Vec3 = nalgebra::Vector3<f64>;
+type Complex = nalgebra::Complex<f64>;
+
+/*
+ * Fast multipole method (Greengard & Rokhlin) on the Barnes-Hut tree, O(N) evaluation.
+ * The solver is planar: all bodies have to stay at z = 0 and accelerations have no z component,
+ * so it only suits flat systems. Evaluating a body off the plane panics.
+ * The potential is still the 3D one, -G * m / |z|, so the logarithmic
+ * complex expansions of 2D gravity don't apply. With w = z - c and d = source - c
+ *     1 / |w - d| = |w|^-1 (1 - d / w)^-1/2 (1 - conj(d / w))^-1/2
+ *                 = sum over k, l of C_k C_l d^k conj(d)^l w^(-k-1/2) conj(w)^(-l-1/2)
+ * with C_k = binomial(2k, k) / 4^k, so a node is described by the moments
+ *     M_kl = sum of m * d^k * conj(d)^l
+ * and the field around a node by a local expansion in u = z - c
+ *     potential(u) = -G * sum of L_kl u^k conj(u)^l
+ * Both are truncated to k + l <= order. Shifting multipoles and locals is exact, converting a
+ * multipole into a local expansion (M2L) expands (d + u)^(-a-1/2) binomially in u / d. Errors
+ * shrink like SEPARATION^(order + 1).
+ * Acceleration is the gradient of the real potential, a_x + i a_y = 2 G d/d(conj u) of the sum.
+ * Expansions are newtonian. Leaves next to each other interact directly with softening, separated
+ * nodes inside the reach of the spline kernel add their softened monopole per body (M2P).
+ */
+
+// bodies per leaf, leaves next to each other interact directly
+const LEAF_SIZE: usize = 64;
+// nodes interact through expansions when (r_a + r_b) <= SEPARATION * distance of their centers,
+// r being the half diagonal of a node
+const SEPARATION: f64 = 0.7;
+// spline kernel is exactly newtonian beyond 2.8 epsilons
+const SPLINE_RANGE: f64 = 2.8;
+
+pub const DEFAULT_ORDER: usize = 6;
+pub const MAX_ORDER: usize = 20;
+
+const N: usize = MAX_ORDER + 1;
+
+// coefficients which depend only on the order
+struct Tables {
+    order: usize,
+    binomial: [[f64; N]; N], // binomial(n, k)
+    // C_a * binomial(-a - 1/2, k), expansion of a multipole term around a distant point
+    shifted: [[f64; N]; N],
+}
+
+impl Tables {
+    fn new(order: usize) -> Self {
+        let mut binomial = [[0.0; N]; N];
+        for n in 0..N {
+            binomial[n][0] = 1.0;
+            for k in 1..=n {
+                binomial[n][k] = binomial[n - 1][k - 1] + if k < n { binomial[n - 1][k] } else { 0.0 };
+            }
+        }
+
+        let mut shifted = [[0.0; N]; N];
+        let mut c = 1.0;
+        for (a, row) in shifted.iter_mut().enumerate() {
+            let mut b = 1.0;
+            for (k, value) in row.iter_mut().enumerate() {
+                *value = c * b;
+                b *= (-(a as f64) - 0.5 - k as f64) / (k as f64 + 1.0);
+            }
+            c *= (2.0 * a as f64 + 1.0) / (2.0 * a as f64 + 2.0);
+        }
+
+        Self {
+            order,
+            binomial,
+            shifted,
+        }
+    }
+
+    fn stride(&self) -> usize {
+        self.order + 1
+    }
+
+    // powers z^0 ... z^count-1
+    fn powers(z: Complex, count: usize) -> [Complex; 2 * N] {
+        let mut powers = [Complex::new(0.0, 0.0); 2 * N];
+        powers[0] = Complex::new(1.0, 0.0);
+        for n in 1..count {
+            powers[n] = powers[n - 1] * z;
+        }
+        powers
+    }
+
+    // P2M, moments of particles around center
+    fn particles_to_multipole(&self, particles: &[Particle], center: Vec2, out: &mut [Complex]) {
+        let (p, n) = (self.order, self.stride());
+        for particle in particles {
+            let d = planar(&particle.pos) - center;
+            let powers = Self::powers(Complex::new(d.x, d.y), p + 1);
+            for k in 0..=p {
+                let mk = powers[k] * particle.mass;
+                for l in 0..=p - k {
+                    out[k * n + l] += mk * powers[l].conj();
+                }
+            }
+        }
+    }
+
+    // M2M, moments around the child center shifted to the parent center, s = child - parent
+    fn multipole_to_multipole(&self, child: &[Complex], s: Complex, out: &mut [Complex]) {
+        let (p, n) = (self.order, self.stride());
+        let powers = Self::powers(s, p + 1);
+        for k in 0..=p {
+            for l in 0..=p - k {
+                let mut sum = Complex::new(0.0, 0.0);
+                for a in 0..=k {
+                    let sa = powers[k - a] * self.binomial[k][a];
+                    for b in 0..=l {
+                        sum += child[a * n + b] * sa * powers[l - b].conj() * self.binomial[l][b];
+                    }
+                }
+                out[k * n + l] += sum;
+            }
+        }
+    }
+
+    // M2L, d = local center - multipole center
+    fn multipole_to_local(&self, multipole: &[Complex], d: Complex, out: &mut [Complex]) {
+        let (p, n) = (self.order, self.stride());
+        let inverse = Self::powers(1.0 / d, 2 * p + 1);
+        let scale = 1.0 / d.norm_sqr().sqrt();
+
+        // T[k][a] = C_a binomial(-a - 1/2, k) d^(-a-k)
+        let mut t = [[Complex::new(0.0, 0.0); N]; N];
+        for k in 0..=p {
+            for a in 0..=p {
+                t[k][a] = inverse[a + k] * self.shifted[a][k];
+            }
+        }
+        // X[a][l] = sum over b of M_ab conj(T[l][b])
+        let mut x = [[Complex::new(0.0, 0.0); N]; N];
+        for a in 0..=p {
+            for l in 0..=p {
+                let mut sum = Complex::new(0.0, 0.0);
+                for b in 0..=p - a {
+                    sum += multipole[a * n + b] * t[l][b].conj();
+                }
+                x[a][l] = sum;
+            }
+        }
+        // the potential is real, L_lk = conj(L_kl)
+        for k in 0..=p / 2 {
+            for l in k..=p - k {
+                let mut sum = Complex::new(0.0, 0.0);
+                for a in 0..=p {
+                    sum += t[k][a] * x[a][l];
+                }
+                out[k * n + l] += sum * scale;
+                if l != k {
+                    out[l * n + k] += sum.conj() * scale;
+                }
+            }
+        }
+    }
+
+    // L2L, local expansion of the parent moved to the child center, s = child - parent
+    fn local_to_local(&self, parent: &[Complex], s: Complex, out: &mut [Complex]) {
+        let (p, n) = (self.order, self.stride());
+        let powers = Self::powers(s, p + 1);
+        for a in 0..=p {
+            for b in 0..=p - a {
+                let mut sum = Complex::new(0.0, 0.0);
+                for k in a..=p {
+                    let sk = powers[k - a] * self.binomial[k][a];
+                    for l in b..=p - k {
+                        sum += parent[k * n + l] * sk * powers[l - b].conj() * self.binomial[l][b];
+                    }
+                }
+                out[a * n + b] += sum;
+            }
+        }
+    }
+
+    // M2P without the monopole, sum of the multipole expansion at w and its derivative by conj(w)
+    fn evaluate_multipole(&self, multipole: &[Complex], w: Vec2) -> (f64, Complex) {
+        let (p, n) = (self.order, self.stride());
+        let inverse = Self::powers(1.0 / complex(w), p + 2);
+        let scale = 1.0 / w.norm();
+        let mut value = Complex::new(0.0, 0.0);
+        let mut derivative = Complex::new(0.0, 0.0);
+        for k in 0..=p {
+            for l in 0..=p - k {
+                if k + l == 0 {
+                    continue;
+                }
+                let term = multipole[k * n + l] * inverse[k] * self.shifted[k][0] * self.shifted[l][0];
+                value += term * inverse[l].conj();
+                derivative -= term * inverse[l + 1].conj() * (l as f64 + 0.5);
+            }
+        }
+        (value.re * scale, derivative * scale)
+    }
+
+    // L2P, sum of the local expansion and its derivative by conj(u)
+    fn evaluate_local(&self, local: &[Complex], u: Vec2) -> (f64, Complex) {
+        let (p, n) = (self.order, self.stride());
+        let powers = Self::powers(Complex::new(u.x, u.y), p + 1);
+        let mut value = Complex::new(0.0, 0.0);
+        let mut derivative = Complex::new(0.0, 0.0);
+        for k in 0..=p {
+            for l in 0..=p - k {
+                let term = local[k * n + l] * powers[k];
+                value += term * powers[l].conj();
+                if l > 0 {
+                    derivative += term * powers[l - 1].conj() * l as f64;
+                }
+            }
+        }
+        (value.re, derivative)
+    }
+}
+
+fn center(node: &Node) -> Vec2 {
+    planar(&((node.pos_lower_bound + node.pos_upper_bound) / 2.0))
+}
+
+fn radius(node: &Node) -> f64 {
+    (node.pos_upper_bound - node.pos_lower_bound).max() * std::f64::consts::FRAC_1_SQRT_2
+}
+
+fn complex(v: Vec2) -> Complex {
+    Complex::new(v.x, v.y)
+}
+
+fn planar(v: &Vec3) -> Vec2 {
+    Vec2::new(v.x, v.y)
+}
+
+// sources of every node, indexed by the node
+#[derive(Default)]
+struct InteractionLists {
+    m2l: Vec<Vec<u32>>, // far away nodes, through the local expansion
+    // well separated nodes still within reach of the softening, their multipoles are evaluated
+    // at every particle below with a softened monopole
+    m2p: Vec<Vec<u32>>,
+    p2p: Vec<Vec<u32>>, // neighbouring leaves, particle by particle
+}
+
+impl InteractionLists {
+    fn reset(&mut self, nodes: usize) {
+        for list in self.m2l.iter_mut().chain(self.m2p.iter_mut()).chain(self.p2p.iter_mut()) {
+            list.clear();
+        }
+        self.m2l.resize(nodes, Vec::new());
+        self.m2p.resize(nodes, Vec::new());
+        self.p2p.resize(nodes, Vec::new());
+    }
+}
+
+// interaction lists of every node, built by a dual tree walk splitting the larger node
+fn collect_interactions(
+    nodes: &[Node],
+    a: usize,
+    b: usize,
+    near_range: f64,
+    lists: &mut InteractionLists,
+) {
+    let (node_a, node_b) = (&nodes[a], &nodes[b]);
+    let (ra, rb) = (radius(node_a), radius(node_b));
+    let distance = (center(node_a) - center(node_b)).norm();
+    if a != b && ra + rb <= SEPARATION * distance {
+        if distance - ra - rb >= near_range {
+            lists.m2l[a].push(b as u32);
+        } else {
+            lists.m2p[a].push(b as u32);
+        }
+        return;
+    }
+
+    let (leaf_a, leaf_b) = (node_a.children == 0, node_b.children == 0);
+    if leaf_a && leaf_b {
+        lists.p2p[a].push(b as u32);
+    } else if leaf_b || (!leaf_a && ra >= rb) {
+        for child in node_a.child_range() {
+            collect_interactions(nodes, child, b, near_range, lists);
+        }
+    } else {
+        for child in node_b.child_range() {
+            collect_interactions(nodes, a, child, near_range, lists);
+        }
+    }
+}
+
+pub struct FastMultipole {
+    gravitational_constant: f64,
+    softening: Softening,
+    tables: Tables,
+    tree: Octree,
+    parents: Vec<u32>,
+    leaves: Vec<u32>, // leaf of every particle
+    lists: InteractionLists,
+    multipoles: Vec<Complex>, // (order + 1)^2 per node
+    locals: Vec<Complex>,
+    accelerations: Vec<Vec3>, // in morton order, reused between evaluations
+}
+
+impl FastMultipole {
+    // planar only: eval and potential_energy panic on any body with z != 0, before touching the bodies
+    pub fn new(gravitational_constant: f64) -> Self {
+        Self::with_softening(gravitational_constant, Softening::default())
+    }
+
+    // planar only: eval and potential_energy panic on any body with z != 0, before touching the bodies
+    pub fn with_softening(gravitational_constant: f64, softening: Softening) -> Self {
+        Self {
+            gravitational_constant,
+            softening,
+            tables: Tables::new(DEFAULT_ORDER),
+            tree: Octree::default(),
+            parents: Vec::new(),
+            leaves: Vec::new(),
+            lists: InteractionLists::default(),
+            multipoles: Vec::new(),
+            locals: Vec::new(),
+            accelerations: Vec::new(),
+        }
+    }
+
+    // highest k + l kept in the expansions, higher orders are more accurate and more expensive
+    pub fn set_order(&mut self, order: usize) {
+        assert!(
+            (1..=MAX_ORDER).contains(&order),
+            "expansion order has to be between 1 and {}, got {}",
+            MAX_ORDER,
+            order
+        );
+        self.tables = Tables::new(order);
+    }
+
+    // expansions are only used between nodes this far apart
+    fn near_range(&self) -> f64 {
+        match self.softening {
+            Softening::Spline(eps) => SPLINE_RANGE * eps,
+            // plummer never becomes newtonian, evaluating its monopole per body costs more than
+            // the 1.5 * eps^2 / r^2 it corrects, so separated nodes ignore it like barnes-hut does
+            Softening::None | Softening::Plummer(_) | Softening::PerBody => 0.0,
+        }
+    }
+
+    // tree, interaction lists and local expansions of every node
+    fn solve(&mut self, bodies: &[Body]) {
+        // all bodies at z = 0, the octree only ever splits them into four children like a quadtree
+        let (lower, upper) = cube_bounds(bodies);
+        self.tree.build(bodies, lower, upper, LEAF_SIZE, false);
+
+        let nodes = &self.tree.nodes;
+        self.parents.clear();
+        self.parents.resize(nodes.len(), 0);
+        self.leaves.clear();
+        self.leaves.resize(self.tree.particles.len(), 0);
+        for (index, node) in nodes.iter().enumerate() {
+            for child in node.child_range() {
+                self.parents[child] = index as u32;
+            }
+            if node.children == 0 {
+                for particle in node.particle_range() {
+                    self.leaves[particle] = index as u32;
+                }
+            }
+        }
+
+        let near_range = self.near_range();
+        self.lists.reset(nodes.len());
+        collect_interactions(nodes, 0, 0, near_range, &mut self.lists);
+
+        let tables = &self.tables;
+        let stride = tables.stride() * tables.stride();
+        let particles = &self.tree.particles;
+
+        // upward pass, deepest level first
+        self.multipoles.clear();
+        self.multipoles.resize(nodes.len() * stride, Complex::new(0.0, 0.0));
+        for level in self.tree.levels.iter().rev() {
+            let (head, tail) = self.multipoles.split_at_mut(level.end * stride);
+            head[level.start * stride..]
+                .par_chunks_mut(stride)
+                .zip(level.clone().into_par_iter())
+                .for_each(|(out, index)| {
+                    let node = &nodes[index];
+                    if node.children == 0 {
+                        tables.particles_to_multipole(&particles[node.particle_range()], center(node), out);
+                    }
+                    for child in node.child_range() {
+                        let moments = &tail[(child - level.end) * stride..][..stride];
+                        tables.multipole_to_multipole(moments, complex(center(&nodes[child]) - center(node)), out);
+                    }
+                });
+        }
+
+        // every node converts the multipoles of its interaction list on its own
+        let multipoles = &self.multipoles;
+        let m2l = &self.lists.m2l;
+        self.locals.clear();
+        self.locals.resize(nodes.len() * stride, Complex::new(0.0, 0.0));
+        self.locals
+            .par_chunks_mut(stride)
+            .enumerate()
+            .for_each(|(index, out)| {
+                for &source in &m2l[index] {
+                    let source = source as usize;
+                    let d = complex(center(&nodes[index]) - center(&nodes[source]));
+                    tables.multipole_to_local(&multipoles[source * stride..][..stride], d, out);
+                }
+            });
+
+        // downward pass, parents are always on the level above
+        let parents = &self.parents;
+        for level in self.tree.levels.iter().skip(1) {
+            let (head, tail) = self.locals.split_at_mut(level.start * stride);
+            tail[..level.len() * stride]
+                .par_chunks_mut(stride)
+                .zip(level.clone().into_par_iter())
+                .for_each(|(out, index)| {
+                    let parent = parents[index] as usize;
+                    let s = complex(center(&nodes[index]) - center(&nodes[parent]));
+                    tables.local_to_local(&head[parent * stride..][..stride], s, out);
+                });
+        }
+    }
+
+    // acceleration and potential per unit mass of a particle, from the local expansion of its
+    // leaf and the particles of neighbouring leaves
+    fn particle_field(&self, index: usize) -> (Vec3, f64) {
+        let particles = &self.tree.particles;
+        let particle = &particles[index];
+        let leaf = self.leaves[index] as usize;
+        let node = &self.tree.nodes[leaf];
+        let stride = self.tables.stride() * self.tables.stride();
+        let g = self.gravitational_constant;
+
+        let (value, derivative) =
+            self.tables.evaluate_local(&self.locals[leaf * stride..][..stride], planar(&particle.pos) - center(node));
+        let mut acc = Vec3::new(derivative.re, derivative.im, 0.0) * (2.0 * g);
+        let mut potential = -g * value;
+
+        for &neighbour in &self.lists.p2p[leaf] {
+            for other in &particles[self.tree.nodes[neighbour as usize].particle_range()] {
+                if other.pos == particle.pos {
+                    continue; // same body
+                }
+                let d = other.pos - particle.pos;
+                let dist2 = d.dot(&d);
+                acc += g * other.mass * self.softening.force_factor(dist2, particle.softening2, other.softening2) * d;
+                potential -= g * other.mass * self.softening.potential_factor(dist2, particle.softening2, other.softening2);
+            }
+        }
+
+        // softened sources of the leaf and of all its parents
+        let mut node = leaf;
+        loop {
+            for &source in &self.lists.m2p[node] {
+                let source = source as usize;
+                let (source_acc, source_potential) = self.multipole_field(source, particle);
+                acc += source_acc;
+                potential += source_potential;
+            }
+            if node == 0 {
+                break;
+            }
+            node = self.parents[node] as usize;
+        }
+        (acc, potential)
+    }
+
+    // M2P, multipole of a node evaluated at a particle with the monopole softened like in
+    // Barnes-Hut, the higher moments stay newtonian
+    fn multipole_field(&self, index: usize, particle: &Particle) -> (Vec3, f64) {
+        let node = &self.tree.nodes[index];
+        let stride = self.tables.stride() * self.tables.stride();
+        let g = self.gravitational_constant;
+        let w = planar(&particle.pos) - center(node);
+        let (value, derivative) = self
+            .tables
+            .evaluate_multipole(&self.multipoles[index * stride..][..stride], w);
+
+        let dist2 = w.dot(&w);
+        let monopole = self.softening.force_factor(dist2, particle.softening2, node.softening2);
+        let acc = (Vec2::new(derivative.re, derivative.im) * (2.0 * g) - g * node.mass * monopole * w).push(0.0);
+        let potential = -g * (value + node.mass * self.softening.potential_factor(dist2, particle.softening2, node.softening2));
+        (acc, potential)
+    }
+}
+
+impl ForceSolver for FastMultipole {
+    // bodies with non finite positions are not in the tree, their acc is zero
+    fn eval(&mut self, bodies: &mut [Body]) {
+        assert_planar(bodies, "FastMultipole");
+        self.solve(bodies);
+
+        let mut accelerations = std::mem::take(&mut self.accelerations);
+        let this = &*self;
+        accelerations.clear();
+        accelerations.par_extend((0..this.tree.particles.len()).into_par_iter().map(|index| this.particle_field(index).0));
+
+        for body in bodies.iter_mut() {
+            body.acc = Vec3::new(0.0, 0.0, 0.0);
+        }
+        for (&(_, index), acc) in self.tree.keys.iter().zip(accelerations.iter()) {
+            bodies[index as usize].acc = *acc;
+        }
+        self.accelerations = accelerations;
+    }
+
+    fn potential_energy(&mut self, bodies: &[Body]) -> f64 {
+        assert_planar(bodies, "FastMultipole");
+        self.solve(bodies);
+
+        // summed sequentially, parallel reductions would depend on how the work was split
+        let this = &*self;
+        let energies: Vec<f64> = (0..this.tree.particles.len())
+            .into_par_iter()
+            .map(|index| this.tree.particles[index].mass * this.particle_field(index).1)
+            .collect();
+        0.5 * energies.iter().sum::<f64>()
+    }
+}
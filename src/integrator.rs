@@ -0,0 +1,42 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+
+mod adams_bashforth_moulton;
+mod adaptive;
+mod block;
+mod bulirsch_stoer;
+mod euler;
+mod hermite;
+mod ias15;
+mod kepler;
+mod leapfrog;
+mod runge_kutta;
+mod wisdom_holman;
+mod yoshida;
+
+pub use adams_bashforth_moulton::IntegratorABM6;
+pub use adaptive::{IntegratorAdaptiveLeapfrog, TimestepCriterion};
+pub use block::IntegratorBlockLeapfrog;
+pub use bulirsch_stoer::IntegratorBulirschStoer;
+pub use euler::IntegratorEuler;
+pub use hermite::IntegratorHermite;
+pub use ias15::IntegratorIAS15;
+pub use leapfrog::IntegratorLeapfrog;
+pub use runge_kutta::IntegratorRK4;
+pub use wisdom_holman::IntegratorWisdomHolman;
+pub use yoshida::IntegratorYoshida4;
+
+/*
+ * An integrator advances positions and velocities of a particle set by one step.
+ * It doesn't know anything about gravity, whenever it needs accelerations for the current positions
+ * it asks the force solver to fill `Body::acc` of every body in the slice.
+ * Integrators may keep state between steps (accelerations from the end of the last step, force
+ * histories, timesteps), which only holds as long as nobody else changes the bodies. Whoever moves,
+ * kicks, adds or removes bodies between two steps, e.g. a contact handler, calls `reset` afterwards.
+ */
+pub trait Integrator {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver);
+
+    // forget everything kept from earlier steps, the next step starts from the bodies alone
+    fn reset(&mut self) {}
+}
@@ -0,0 +1,111 @@
+use std::collections::VecDeque;
+
+use crate::body::Body;
+use crate::integrator::{Integrator, IntegratorRK4};
+use crate::solver::ForceSolver;
+
+// six step adams-bashforth predictor, newest derivative first
+// taken from paper 'MATHEMATICAL MODEL FOR THE 0.5 BILLION YEARS AGED SUN'
+const PREDICTOR: [f64; 6] = [
+    4277.0 / 1440.0,
+    -7923.0 / 1440.0,
+    9982.0 / 1440.0,
+    -7298.0 / 1440.0,
+    2877.0 / 1440.0,
+    -475.0 / 1440.0,
+];
+
+// sixth order adams-moulton corrector, the first weight is for the derivative at the predicted state
+const CORRECTOR: [f64; 6] = [
+    475.0 / 1440.0,
+    1427.0 / 1440.0,
+    -798.0 / 1440.0,
+    482.0 / 1440.0,
+    -173.0 / 1440.0,
+    27.0 / 1440.0,
+];
+
+/*
+ * Sixth order adams-bashforth-moulton predictor-corrector in PECE mode: predict from the last six
+ * derivatives, evaluate there, correct, evaluate again for the history. Two force evaluations per step.
+ * https://en.wikipedia.org/wiki/Linear_multistep_method#Adams%E2%80%93Moulton_methods
+ * This is a fixed step method: the history only holds for equal steps of the same bodies, when dt or
+ * the number of bodies changes it is dropped and refilled by five runge-kutta steps (four evaluations
+ * each). The history is not rescaled to the new step, so frequent changes of dt make it a slow RK4.
+ */
+#[derive(Default)]
+pub struct IntegratorABM6 {
+    step: f64,
+    history: VecDeque<Vec<Body>>, // derivatives at the start of the last steps, newest first
+    initial: Vec<Body>,
+    predicted: Vec<Body>,
+    starter: IntegratorRK4,
+}
+
+impl IntegratorABM6 {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+// derivatives are stored as bodies: pos holds dx/dt and vel holds dv/dt
+fn derivatives(bodies: &[Body], mut buffer: Vec<Body>) -> Vec<Body> {
+    buffer.clear();
+    buffer.extend(bodies.iter().map(|body| {
+        let mut derivative = *body;
+        derivative.pos = body.vel;
+        derivative.vel = body.acc;
+        derivative
+    }));
+    buffer
+}
+
+impl Integrator for IntegratorABM6 {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        let restart = dt != self.step || self.history.front().map(Vec::len) != Some(bodies.len());
+        if restart {
+            self.history.clear();
+            self.step = dt;
+            solver.eval(bodies);
+        }
+
+        let buffer = if self.history.len() == PREDICTOR.len() {
+            self.history.pop_back().unwrap()
+        } else {
+            Vec::new()
+        };
+        self.history.push_front(derivatives(bodies, buffer));
+
+        if self.history.len() < PREDICTOR.len() {
+            self.starter.integrate(dt, bodies, solver);
+            solver.eval(bodies);
+            return;
+        }
+
+        self.initial.clear();
+        self.initial.extend_from_slice(bodies);
+        for (i, body) in bodies.iter_mut().enumerate() {
+            for (weight, derivative) in PREDICTOR.iter().zip(&self.history) {
+                body.pos += dt * weight * derivative[i].pos;
+                body.vel += dt * weight * derivative[i].vel;
+            }
+        }
+
+        solver.eval(bodies);
+        self.predicted = derivatives(bodies, std::mem::take(&mut self.predicted));
+
+        for (i, body) in bodies.iter_mut().enumerate() {
+            body.pos = self.initial[i].pos + dt * CORRECTOR[0] * self.predicted[i].pos;
+            body.vel = self.initial[i].vel + dt * CORRECTOR[0] * self.predicted[i].vel;
+            for (weight, derivative) in CORRECTOR[1..].iter().zip(&self.history) {
+                body.pos += dt * weight * derivative[i].pos;
+                body.vel += dt * weight * derivative[i].vel;
+            }
+        }
+        solver.eval(bodies);
+    }
+
+    fn reset(&mut self) {
+        self.history.clear();
+    }
+}
@@ -0,0 +1,131 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use crate::integrator::Integrator;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// steps are never shorter than dt / 2^MAX_LEVEL, so a close encounter can't stall the simulation
+pub(super) const MAX_LEVEL: u32 = 20;
+
+/*
+ * Timestep a body asks for. Both are Aarseth-style, a step resolves a fraction eta of the time
+ * in which the acceleration changes:
+ *     Acceleration: dt = eta * sqrt(length / |a|), length is usually the softening (GADGET)
+ *     Jerk:         dt = eta * |a| / |da/dt| (Aarseth 1985)
+ * The jerk is estimated from the accelerations at the start and the end of the previous step, so it
+ * costs no extra force evaluations.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TimestepCriterion {
+    Acceleration { eta: f64, length: f64 },
+    Jerk(f64),
+}
+
+impl TimestepCriterion {
+    // infinite when nothing limits the step
+    pub fn timestep(&self, acc: &Vec3, jerk: &Vec3) -> f64 {
+        match *self {
+            TimestepCriterion::Acceleration { eta, length } => eta * (length / acc.norm()).sqrt(),
+            TimestepCriterion::Jerk(eta) => eta * acc.norm() / jerk.norm(),
+        }
+    }
+
+    fn needs_jerk(&self) -> bool {
+        matches!(self, TimestepCriterion::Jerk(_))
+    }
+}
+
+/*
+ * Jerk of every body before the first step, from one extra evaluation at positions drifted by h.
+ * Accelerations of bodies have to be valid, they are kept.
+ */
+pub(super) fn initial_jerks(
+    criterion: &TimestepCriterion,
+    h: f64,
+    bodies: &[Body],
+    solver: &mut dyn ForceSolver,
+) -> Vec<Vec3> {
+    if !criterion.needs_jerk() {
+        return vec![Vec3::new(0.0, 0.0, 0.0); bodies.len()];
+    }
+    let mut drifted = bodies.to_vec();
+    for body in drifted.iter_mut() {
+        body.pos += h * body.vel;
+    }
+    solver.eval(&mut drifted);
+    drifted
+        .iter()
+        .zip(bodies)
+        .map(|(drifted, body)| (drifted.acc - body.acc) / h)
+        .collect()
+}
+
+/*
+ * Kick-drift-kick leapfrog with one adaptive step shared by all bodies, the shortest one any body
+ * asks for. `integrate(dt, ..)` always advances by dt, taking as many substeps as needed, so dt of
+ * a scenario is the longest step. Not symplectic anymore, but close encounters stay resolved.
+ */
+pub struct IntegratorAdaptiveLeapfrog {
+    criterion: TimestepCriterion,
+    steps: Vec<f64>, // step asked for by every body, from the end of the previous substep
+    substeps: usize,
+}
+
+impl IntegratorAdaptiveLeapfrog {
+    pub fn new(criterion: TimestepCriterion) -> Self {
+        Self {
+            criterion,
+            steps: Vec::new(),
+            substeps: 0,
+        }
+    }
+
+    // substeps taken by the last call of integrate
+    pub fn substeps(&self) -> usize {
+        self.substeps
+    }
+}
+
+impl Integrator for IntegratorAdaptiveLeapfrog {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        let min_step = dt / 2f64.powi(MAX_LEVEL as i32);
+        if self.steps.len() != bodies.len() {
+            solver.eval(bodies);
+            let jerks = initial_jerks(&self.criterion, min_step, bodies, solver);
+            let criterion = self.criterion;
+            self.steps = bodies
+                .iter()
+                .zip(&jerks)
+                .map(|(body, jerk)| criterion.timestep(&body.acc, jerk))
+                .collect();
+        }
+
+        let mut accelerations: Vec<Vec3> = Vec::with_capacity(bodies.len());
+        let mut remaining = dt;
+        self.substeps = 0;
+        while remaining > 0.0 {
+            let wanted = self.steps.iter().cloned().fold(dt, f64::min).max(min_step);
+            let h = if wanted >= remaining { remaining } else { wanted };
+
+            accelerations.clear();
+            for body in bodies.iter_mut() {
+                accelerations.push(body.acc);
+                body.vel += 0.5 * h * body.acc;
+                body.pos += h * body.vel;
+            }
+
+            solver.eval(bodies);
+
+            for ((body, acc), step) in bodies.iter_mut().zip(&accelerations).zip(self.steps.iter_mut()) {
+                body.vel += 0.5 * h * body.acc;
+                *step = self.criterion.timestep(&body.acc, &((body.acc - acc) / h));
+            }
+            remaining -= h;
+            self.substeps += 1;
+        }
+    }
+
+    fn reset(&mut self) {
+        self.steps.clear();
+    }
+}
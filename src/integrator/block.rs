@@ -0,0 +1,129 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use crate::integrator::adaptive::{initial_jerks, MAX_LEVEL};
+use crate::integrator::{Integrator, TimestepCriterion};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+/*
+ * Hierarchical block timesteps (Aarseth, GADGET-2). Every body moves with its own step dt / 2^level,
+ * so a body on level k is synchronised with all bodies on lower levels at the end of its step.
+ * Kick-drift-kick per body: a body is half kicked at the start and at the end of its own step,
+ * between them all bodies drift together, so positions of inactive bodies are always current.
+ * Only bodies finishing a step get new accelerations, through ForceSolver::eval_active.
+ * A body may move to a longer step only where that step would start on the block grid.
+ */
+pub struct IntegratorBlockLeapfrog {
+    criterion: TimestepCriterion,
+    steps: Vec<f64>,   // step asked for by every body
+    levels: Vec<u32>,  // current level of every body
+    starts: Vec<Vec3>, // acceleration at the start of the current step of every body
+    active: Vec<usize>,
+    evaluations: usize,
+}
+
+impl IntegratorBlockLeapfrog {
+    pub fn new(criterion: TimestepCriterion) -> Self {
+        Self {
+            criterion,
+            steps: Vec::new(),
+            levels: Vec::new(),
+            starts: Vec::new(),
+            active: Vec::new(),
+            evaluations: 0,
+        }
+    }
+
+    // level of every body during the last step, 0 is the full dt
+    pub fn levels(&self) -> &[u32] {
+        &self.levels
+    }
+
+    // force evaluations of active bodies in the last call of integrate, dt costs one per body
+    // without block timesteps
+    pub fn evaluations(&self) -> usize {
+        self.evaluations
+    }
+}
+
+// first level whose step doesn't exceed the wanted one
+pub(super) fn level_of(step: f64, dt: f64) -> u32 {
+    let mut level = 0;
+    while level < MAX_LEVEL && dt / 2f64.powi(level as i32) > step {
+        level += 1;
+    }
+    level
+}
+
+impl Integrator for IntegratorBlockLeapfrog {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        // time is counted in ticks of the shortest possible step, exact in integers
+        let ticks: u64 = 1 << MAX_LEVEL;
+        let tick = dt / ticks as f64;
+        if self.steps.len() != bodies.len() {
+            solver.eval(bodies);
+            let jerks = initial_jerks(&self.criterion, tick, bodies, solver);
+            let criterion = self.criterion;
+            self.steps = bodies
+                .iter()
+                .zip(&jerks)
+                .map(|(body, jerk)| criterion.timestep(&body.acc, jerk))
+                .collect();
+        }
+        // all bodies are synchronised between calls, any level is allowed and dt may change
+        self.levels = self.steps.iter().map(|&step| level_of(step, dt)).collect();
+        self.starts = bodies.iter().map(|body| body.acc).collect();
+        self.evaluations = 0;
+
+        let length = |level: u32| ticks >> level;
+        let mut time = 0;
+        while time < ticks {
+            // opening half kicks of bodies starting a step
+            for (body, &level) in bodies.iter_mut().zip(&self.levels) {
+                if time % length(level) == 0 {
+                    body.vel += 0.5 * tick * length(level) as f64 * body.acc;
+                }
+            }
+
+            // the next end of any step
+            let next = self
+                .levels
+                .iter()
+                .map(|&level| (time / length(level) + 1) * length(level))
+                .min()
+                .unwrap_or(ticks);
+            let h = tick * (next - time) as f64;
+            for body in bodies.iter_mut() {
+                body.pos += h * body.vel;
+            }
+            time = next;
+
+            let levels = &self.levels;
+            self.active.clear();
+            self.active
+                .extend((0..bodies.len()).filter(|&i| time % length(levels[i]) == 0));
+            solver.eval_active(bodies, &self.active);
+            self.evaluations += self.active.len();
+
+            // closing half kicks, then the next step of every active body
+            for &i in &self.active {
+                let body = &mut bodies[i];
+                let step = tick * length(self.levels[i]) as f64;
+                body.vel += 0.5 * step * body.acc;
+                let jerk = (body.acc - self.starts[i]) / step;
+                self.starts[i] = body.acc;
+                self.steps[i] = self.criterion.timestep(&body.acc, &jerk);
+
+                let mut level = level_of(self.steps[i], dt);
+                while time % length(level) != 0 {
+                    level += 1;
+                }
+                self.levels[i] = level;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.steps.clear();
+    }
+}
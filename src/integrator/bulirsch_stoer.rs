@@ -0,0 +1,188 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use crate::integrator::adaptive::MAX_LEVEL;
+use crate::integrator::Integrator;
+
+// substeps of the modified midpoint rule in the rows of the extrapolation table (Deuflhard)
+const SEQUENCE: [usize; 8] = [2, 4, 6, 8, 10, 12, 14, 16];
+const DEFAULT_TOLERANCE: f64 = 1e-12;
+// new steps are a safe fraction of the one the error estimate allows, and change by at most these factors
+const SAFETY: f64 = 0.94;
+const MIN_FACTOR: f64 = 0.02;
+const MAX_FACTOR: f64 = 4.0;
+
+/*
+ * Bulirsch-Stoer (Hairer, Norsett & Wanner, Solving ODEs I, II.9), for chaotic few body encounters
+ * that need a given accuracy rather than a fixed order.
+ * A step H is integrated by the modified midpoint rule with n = 2, 4, 6 .. substeps, whose error is a
+ * series in even powers of H / n, and the results are extrapolated to H / n = 0 (Richardson). Every
+ * row of the table is one order of H^2 better, a step is accepted once the difference of the last two
+ * extrapolations is below tolerance, relative to |x| + H |v| for positions and |v| + H |a| for
+ * velocities. The next step is the one with the least force evaluations per time among the columns
+ * tried, a step that doesn't converge in eight rows is repeated shorter.
+ * `integrate(dt, ..)` always advances by dt, `errors()` reports the error of every step it took.
+ */
+pub struct IntegratorBulirschStoer {
+    tolerance: f64,
+    step: f64, // next step to try, 0 before the first one
+    initial: Vec<Body>,
+    previous: Vec<Body>,  // state before the last midpoint substep
+    table: Vec<Vec<Body>>, // last row of the extrapolation table, positions and velocities
+    row: Vec<Vec<Body>>,
+    errors: Vec<f64>,
+}
+
+impl IntegratorBulirschStoer {
+    pub fn new() -> Self {
+        Self::with_tolerance(DEFAULT_TOLERANCE)
+    }
+
+    pub fn with_tolerance(tolerance: f64) -> Self {
+        Self {
+            tolerance,
+            step: 0.0,
+            initial: Vec::new(),
+            previous: Vec::new(),
+            table: Vec::new(),
+            row: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    // relative error estimate of every step taken by the last call of integrate
+    pub fn errors(&self) -> &[f64] {
+        &self.errors
+    }
+
+    // modified midpoint rule with n substeps over h, accelerations of bodies have to be valid
+    fn midpoint(&mut self, n: usize, h: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        let substep = h / n as f64;
+        self.previous.clear();
+        self.previous.extend_from_slice(&self.initial);
+        for body in bodies.iter_mut() {
+            body.pos += substep * body.vel;
+            body.vel += substep * body.acc;
+        }
+        for _ in 1..n {
+            solver.eval(bodies);
+            for (body, previous) in bodies.iter_mut().zip(self.previous.iter_mut()) {
+                let pos = previous.pos + 2.0 * substep * body.vel;
+                let vel = previous.vel + 2.0 * substep * body.acc;
+                previous.pos = body.pos;
+                previous.vel = body.vel;
+                body.pos = pos;
+                body.vel = vel;
+            }
+        }
+        solver.eval(bodies);
+        for (body, previous) in bodies.iter_mut().zip(&self.previous) {
+            let pos = 0.5 * (body.pos + previous.pos + substep * body.vel);
+            let vel = 0.5 * (body.vel + previous.vel + substep * body.acc);
+            body.pos = pos;
+            body.vel = vel;
+        }
+    }
+
+    // largest difference of two extrapolations relative to the tolerance
+    fn error(&self, h: f64, a: &[Body], b: &[Body]) -> f64 {
+        let mut error: f64 = 0.0;
+        for ((initial, a), b) in self.initial.iter().zip(a).zip(b) {
+            let pos_scale = initial.pos.norm() + h * initial.vel.norm() + f64::MIN_POSITIVE;
+            let vel_scale = initial.vel.norm() + h * initial.acc.norm() + f64::MIN_POSITIVE;
+            error = error
+                .max((a.pos - b.pos).norm() / pos_scale)
+                .max((a.vel - b.vel).norm() / vel_scale);
+        }
+        error / self.tolerance
+    }
+
+    // one step of at most h, returns the accepted length and the step asked for next
+    fn try_step(&mut self, mut h: f64, min_step: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) -> (f64, f64) {
+        self.initial.clear();
+        self.initial.extend_from_slice(bodies);
+        loop {
+            let mut evaluations = 1;
+            let mut best_work = f64::INFINITY;
+            let mut best_step = h;
+            for (j, &n) in SEQUENCE.iter().enumerate() {
+                bodies.copy_from_slice(&self.initial);
+                self.midpoint(n, h, bodies, solver);
+                evaluations += n;
+
+                // T[j][k] = T[j][k - 1] + (T[j][k - 1] - T[j - 1][k - 1]) / ((n_j / n_(j - k))^2 - 1)
+                self.row.resize_with(j + 1, Vec::new);
+                self.row[0].clear();
+                self.row[0].extend_from_slice(bodies);
+                for k in 1..=j {
+                    let ratio = n as f64 / SEQUENCE[j - k] as f64;
+                    let denominator = ratio * ratio - 1.0;
+                    let (lower, upper) = self.row.split_at_mut(k);
+                    upper[0].clear();
+                    upper[0].extend(lower[k - 1].iter().zip(&self.table[k - 1]).map(|(current, last)| {
+                        let mut extrapolated = *current;
+                        extrapolated.pos += (current.pos - last.pos) / denominator;
+                        extrapolated.vel += (current.vel - last.vel) / denominator;
+                        extrapolated
+                    }));
+                }
+                std::mem::swap(&mut self.table, &mut self.row);
+                if j == 0 {
+                    continue;
+                }
+
+                let error = self.error(h, &self.table[j], &self.table[j - 1]);
+                let factor = if error > 0.0 {
+                    (SAFETY * (1.0 / error).powf(1.0 / (2 * j + 1) as f64)).clamp(MIN_FACTOR, MAX_FACTOR)
+                } else {
+                    MAX_FACTOR
+                };
+                let work = evaluations as f64 / (h * factor);
+                if work < best_work {
+                    best_work = work;
+                    best_step = h * factor;
+                }
+                if error <= 1.0 || h <= min_step {
+                    for (body, extrapolated) in bodies.iter_mut().zip(&self.table[j]) {
+                        body.pos = extrapolated.pos;
+                        body.vel = extrapolated.vel;
+                    }
+                    self.errors.push(error * self.tolerance);
+                    return (h, best_step.max(min_step));
+                }
+                if j == SEQUENCE.len() - 1 {
+                    // too long even for the last row
+                    h = (h * factor).max(min_step);
+                }
+            }
+        }
+    }
+}
+
+impl Default for IntegratorBulirschStoer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Integrator for IntegratorBulirschStoer {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        let min_step = dt / 2f64.powi(MAX_LEVEL as i32);
+        if self.step == 0.0 {
+            self.step = dt;
+        }
+
+        solver.eval(bodies);
+        self.errors.clear();
+        let mut remaining = dt;
+        while remaining > 0.0 {
+            let h = if self.step >= remaining { remaining } else { self.step };
+            let (done, next) = self.try_step(h, min_step, bodies, solver);
+            remaining -= done;
+            // a step cut short by the end of dt says nothing about longer ones
+            if done == self.step || done < h {
+                self.step = next;
+            }
+            solver.eval(bodies);
+        }
+    }
+}
@@ -0,0 +1,23 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use crate::integrator::Integrator;
+
+// semi-implicit (symplectic) euler, velocity is updated first and then used to move the body
+#[derive(Default)]
+pub struct IntegratorEuler;
+
+impl IntegratorEuler {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Integrator for IntegratorEuler {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        solver.eval(bodies);
+        for body in bodies.iter_mut() {
+            body.vel += dt * body.acc;
+            body.pos += dt * body.vel;
+        }
+    }
+}
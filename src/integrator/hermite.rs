@@ -0,0 +1,217 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use crate::integrator::adaptive::MAX_LEVEL;
+use crate::integrator::block::level_of;
+use crate::integrator::Integrator;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// accuracy parameter of the Aarseth timestep criterion, 0.01 - 0.02 is usual
+const DEFAULT_ETA: f64 = 0.02;
+// first step of a body, eta * |a| / |j| with a smaller eta as there are no higher derivatives yet
+const STARTING_ETA: f64 = 0.01;
+// offset of the numerical jerk in steps of the active bodies, the error of the central difference
+// is about its square
+const DIFFERENCE: f64 = 1e-3;
+
+// state of a body at the start of its current step
+#[derive(Debug, Copy, Clone)]
+struct Base {
+    pos: Vec3,
+    vel: Vec3,
+    acc: Vec3,
+    jerk: Vec3,
+    time: u64, // in ticks
+}
+
+/*
+ * Fourth order Hermite predictor-corrector with individual block timesteps (Makino & Aarseth 1992),
+ * the standard for collisional few body and star cluster work.
+ * All bodies are predicted to the current time by a Taylor series in acceleration and jerk, the
+ * bodies finishing their step get new accelerations and jerks from the predicted positions and
+ * velocities, and their corrector uses the third order interpolation of the acceleration between
+ * both ends of the step. The step of every body follows the Aarseth criterion
+ *     dt = sqrt(eta * (|a| |a''| + |a'|^2) / (|a'| |a'''| + |a''|^2))
+ * rounded down to dt / 2^level. Jerks come from ForceSolver::eval_jerk (DirectSolver). Other solvers
+ * don't provide them, their accelerations are differentiated numerically at the cost of two more
+ * evaluations of the active bodies.
+ */
+pub struct IntegratorHermite {
+    eta: f64,
+    bases: Vec<Base>,
+    steps: Vec<f64>, // step asked for by every body
+    levels: Vec<u32>,
+    active: Vec<usize>,
+    evaluations: usize,
+}
+
+impl IntegratorHermite {
+    pub fn new() -> Self {
+        Self::with_eta(DEFAULT_ETA)
+    }
+
+    pub fn with_eta(eta: f64) -> Self {
+        Self {
+            eta,
+            bases: Vec::new(),
+            steps: Vec::new(),
+            levels: Vec::new(),
+            active: Vec::new(),
+            evaluations: 0,
+        }
+    }
+
+    // level of every body at the end of the last call of integrate, 0 is the full dt
+    pub fn levels(&self) -> &[u32] {
+        &self.levels
+    }
+
+    // force evaluations of active bodies in the last call of integrate
+    pub fn evaluations(&self) -> usize {
+        self.evaluations
+    }
+}
+
+impl Default for IntegratorHermite {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/*
+ * Accelerations of the active bodies and their jerks, for solvers without jerks the central
+ * difference along the motion of all bodies
+ *     a' = (a(x + v d, v + a d) - a(x - v d, v - a d)) / 2d
+ * with d a small fraction of the step. The accelerations of the bodies as they are come last, so
+ * solvers keeping state (SPH) end up with the one of the real positions.
+ */
+fn eval_jerk(solver: &mut dyn ForceSolver, bodies: &mut [Body], active: &[usize], step: f64) -> Vec<Vec3> {
+    if let Some(jerks) = solver.eval_jerk(bodies, active) {
+        return jerks;
+    }
+    let d = DIFFERENCE * step;
+    let mut shifted = bodies.to_vec();
+    let mut shifted_acc = |sign: f64| {
+        for (shifted, body) in shifted.iter_mut().zip(bodies.iter()) {
+            shifted.pos = body.pos + sign * d * body.vel;
+            shifted.vel = body.vel + sign * d * body.acc;
+        }
+        solver.eval_active(&mut shifted, active);
+        active.iter().map(|&i| shifted[i].acc).collect::<Vec<Vec3>>()
+    };
+    let ahead = shifted_acc(1.0);
+    let behind = shifted_acc(-1.0);
+    solver.eval_active(bodies, active);
+    ahead.iter().zip(behind).map(|(ahead, behind)| (ahead - behind) / (2.0 * d)).collect()
+}
+
+// third order Taylor series of position and velocity
+fn predict(base: &Base, h: f64) -> (Vec3, Vec3) {
+    let pos = base.pos + h * (base.vel + h / 2.0 * (base.acc + h / 3.0 * base.jerk));
+    let vel = base.vel + h * (base.acc + h / 2.0 * base.jerk);
+    (pos, vel)
+}
+
+impl Integrator for IntegratorHermite {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        let ticks: u64 = 1 << MAX_LEVEL;
+        let tick = dt / ticks as f64;
+        let length = |level: u32| ticks >> level;
+
+        // bodies are synchronised between calls, they only need new derivatives when added
+        if self.bases.len() != bodies.len() {
+            // nothing is known about the orbits yet, differences start at the shortest step
+            let all: Vec<usize> = (0..bodies.len()).collect();
+            let jerks = eval_jerk(solver, bodies, &all, tick);
+            self.bases = bodies
+                .iter()
+                .zip(jerks)
+                .map(|(body, jerk)| Base {
+                    pos: body.pos,
+                    vel: body.vel,
+                    acc: body.acc,
+                    jerk,
+                    time: 0,
+                })
+                .collect();
+            self.steps = self
+                .bases
+                .iter()
+                .map(|base| STARTING_ETA * base.acc.norm() / base.jerk.norm())
+                // bodies at rest have no jerk, they start on the shortest step and grow from there
+                .map(|step| if step.is_finite() { step } else { 0.0 })
+                .collect();
+        }
+        // dt may change between calls, contact handlers may have moved the bodies
+        self.levels = self.steps.iter().map(|&step| level_of(step, dt)).collect();
+        for (base, body) in self.bases.iter_mut().zip(bodies.iter()) {
+            base.pos = body.pos;
+            base.vel = body.vel;
+            base.time = 0;
+        }
+        self.evaluations = 0;
+
+        let mut time = 0;
+        while time < ticks {
+            time = self
+                .levels
+                .iter()
+                .zip(&self.bases)
+                .map(|(&level, base)| base.time + length(level))
+                .min()
+                .unwrap_or(ticks);
+
+            for (body, base) in bodies.iter_mut().zip(&self.bases) {
+                let (pos, vel) = predict(base, tick * (time - base.time) as f64);
+                body.pos = pos;
+                body.vel = vel;
+            }
+
+            let (bases, levels) = (&self.bases, &self.levels);
+            self.active.clear();
+            self.active
+                .extend((0..bodies.len()).filter(|&i| bases[i].time + length(levels[i]) == time));
+            let shortest = self.active.iter().map(|&i| self.levels[i]).max().unwrap_or(0);
+            let jerks = eval_jerk(solver, bodies, &self.active, tick * length(shortest) as f64);
+            self.evaluations += self.active.len();
+
+            for (&i, jerk1) in self.active.iter().zip(jerks) {
+                let body = &mut bodies[i];
+                let base = &mut self.bases[i];
+                let h = tick * length(self.levels[i]) as f64;
+                let (acc0, jerk0, acc1) = (base.acc, base.jerk, body.acc);
+
+                // second and third derivative of the acceleration at the start of the step
+                let snap = (-6.0 * (acc0 - acc1) - h * (4.0 * jerk0 + 2.0 * jerk1)) / (h * h);
+                let crackle = (12.0 * (acc0 - acc1) + 6.0 * h * (jerk0 + jerk1)) / (h * h * h);
+                let h2 = h * h;
+                body.pos += h2 * h2 * (snap / 24.0 + h * crackle / 120.0);
+                body.vel += h2 * h * (snap / 6.0 + h * crackle / 24.0);
+
+                let snap = snap + h * crackle;
+                self.steps[i] = (self.eta
+                    * (acc1.norm() * snap.norm() + jerk1.norm_squared())
+                    / (jerk1.norm() * crackle.norm() + snap.norm_squared()))
+                .sqrt();
+                *base = Base {
+                    pos: body.pos,
+                    vel: body.vel,
+                    acc: acc1,
+                    jerk: jerk1,
+                    time,
+                };
+
+                // steps at most double and longer steps have to start on the block grid
+                let mut level = level_of(self.steps[i], dt).max(self.levels[i].saturating_sub(1));
+                while time % length(level) != 0 {
+                    level += 1;
+                }
+                self.levels[i] = level;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.bases.clear();
+    }
+}
@@ -0,0 +1,307 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use crate::integrator::adaptive::MAX_LEVEL;
+use crate::integrator::Integrator;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// Gauss-Radau spacings, the substeps as fractions of the step
+const SPACINGS: [f64; 8] = [
+    0.0,
+    0.05626256053692215,
+    0.18024069173689236,
+    0.3526247171131696,
+    0.5471536263305554,
+    0.7342101772154105,
+    0.8853209468390958,
+    0.9775206135612875,
+];
+
+// last term of the series relative to the acceleration, 1e-9 keeps the truncation error below round-off
+const DEFAULT_EPSILON: f64 = 1e-9;
+// a step asking for a successor shorter than this fraction of itself is repeated, steps grow by at
+// most the inverse
+const SAFETY: f64 = 0.25;
+// the predictor-corrector usually converges in two or three iterations
+const MAX_ITERATIONS: usize = 12;
+const CONVERGED: f64 = 1e-16;
+
+/*
+ * IAS15 (Rein & Spiegel 2015), 15th order Gauss-Radau integrator with adaptive steps, for few body
+ * systems that have to be integrated to round-off over long times.
+ * Within a step the acceleration is a polynomial of degree seven in time, a(t) = a0 + b0 t + .. + b6 t^7,
+ * and the coefficients are found by a predictor-corrector iteration through forces at the seven
+ * Gauss-Radau spacings. The size of b6 relative to the acceleration decides the next step,
+ *     dt' = dt * (epsilon / (|b6| / |a|))^(1/7)
+ * and a step asking for less than a quarter of itself is repeated. Positions and velocities are
+ * summed with compensation (Kahan), so round-off doesn't grow with the number of steps.
+ * `integrate(dt, ..)` always advances by dt, taking as many steps as needed.
+ */
+pub struct IntegratorIAS15 {
+    epsilon: f64,
+    step: f64,      // next step to try, 0 before the first one
+    last_step: f64, // last accepted step
+    pos: Vec<Vec3>,
+    vel: Vec<Vec3>,
+    pos_compensation: Vec<Vec3>,
+    vel_compensation: Vec<Vec3>,
+    acc: Vec<Vec3>,     // at the start of the step
+    b: Vec<[Vec3; 7]>,  // coefficients of the acceleration polynomial of every body
+    g: Vec<[Vec3; 7]>,  // the same polynomial in divided differences
+    e: Vec<[Vec3; 7]>,  // prediction of b at the start of the step
+    accepted_b: Vec<[Vec3; 7]>,
+    accepted_e: Vec<[Vec3; 7]>,
+    c: [[f64; 7]; 7], // b from g
+    d: [[f64; 7]; 7], // g from b
+    steps: usize,
+}
+
+impl IntegratorIAS15 {
+    pub fn new() -> Self {
+        Self::with_epsilon(DEFAULT_EPSILON)
+    }
+
+    pub fn with_epsilon(epsilon: f64) -> Self {
+        // b_k = sum of c[j][k] g_j and g_k = sum of d[j][k] b_j over j >= k
+        let mut c = [[0.0; 7]; 7];
+        let mut d = [[0.0; 7]; 7];
+        for j in 0..7 {
+            c[j][j] = 1.0;
+            d[j][j] = 1.0;
+            for k in 0..j {
+                let c_lower = if k > 0 { c[j - 1][k - 1] } else { 0.0 };
+                let d_lower = if k > 0 { d[j - 1][k - 1] } else { 0.0 };
+                c[j][k] = c_lower - SPACINGS[j] * c[j - 1][k];
+                d[j][k] = d_lower + SPACINGS[k + 1] * d[j - 1][k];
+            }
+        }
+        Self {
+            epsilon,
+            step: 0.0,
+            last_step: 0.0,
+            pos: Vec::new(),
+            vel: Vec::new(),
+            pos_compensation: Vec::new(),
+            vel_compensation: Vec::new(),
+            acc: Vec::new(),
+            b: Vec::new(),
+            g: Vec::new(),
+            e: Vec::new(),
+            accepted_b: Vec::new(),
+            accepted_e: Vec::new(),
+            c,
+            d,
+            steps: 0,
+        }
+    }
+
+    // accepted steps of the last call of integrate
+    pub fn steps(&self) -> usize {
+        self.steps
+    }
+
+    fn restart(&mut self, bodies: &[Body]) {
+        let zero = [Vec3::new(0.0, 0.0, 0.0); 7];
+        self.step = 0.0;
+        self.last_step = 0.0;
+        self.pos = bodies.iter().map(|body| body.pos).collect();
+        self.vel = bodies.iter().map(|body| body.vel).collect();
+        self.pos_compensation = vec![Vec3::new(0.0, 0.0, 0.0); bodies.len()];
+        self.vel_compensation = vec![Vec3::new(0.0, 0.0, 0.0); bodies.len()];
+        self.b = vec![zero; bodies.len()];
+        self.g = vec![zero; bodies.len()];
+        self.e = vec![zero; bodies.len()];
+        self.accepted_b = vec![zero; bodies.len()];
+        self.accepted_e = vec![zero; bodies.len()];
+    }
+
+    // positions and velocities at fraction h of the step dt
+    fn substep(&self, h: f64, dt: f64, bodies: &mut [Body]) {
+        for (i, body) in bodies.iter_mut().enumerate() {
+            let b = &self.b[i];
+            let mut dx = Vec3::new(0.0, 0.0, 0.0);
+            let mut dv = Vec3::new(0.0, 0.0, 0.0);
+            for k in (0..7).rev() {
+                dx = dx * h + b[k] / ((k + 2) * (k + 3)) as f64;
+                dv = dv * h + b[k] / (k + 2) as f64;
+            }
+            let t = h * dt;
+            body.pos = self.pos[i]
+                + (t * (self.vel[i] + t * (0.5 * self.acc[i] + h * dx)) - self.pos_compensation[i]);
+            body.vel = self.vel[i] + (t * (self.acc[i] + h * dv) - self.vel_compensation[i]);
+        }
+    }
+
+    // one step of at most dt, returns the accepted length and the step asked for next
+    fn try_step(&mut self, mut dt: f64, min_step: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) -> (f64, f64) {
+        loop {
+            let d = &self.d;
+            for (g, b) in self.g.iter_mut().zip(&self.b) {
+                for k in 0..7 {
+                    g[k] = (k..7).map(|j| d[j][k] * b[j]).sum();
+                }
+            }
+
+            let mut error = f64::INFINITY;
+            let mut previous = 2.0;
+            for iteration in 0..MAX_ITERATIONS {
+                if error < CONVERGED || (iteration > 2 && previous <= error) {
+                    break;
+                }
+                previous = error;
+
+                let mut max_change: f64 = 0.0;
+                let mut max_acc: f64 = 0.0;
+                for (n, &spacing) in SPACINGS.iter().enumerate().skip(1) {
+                    self.substep(spacing, dt, bodies);
+                    solver.eval(bodies);
+                    let j = n - 1;
+                    for (i, body) in bodies.iter().enumerate() {
+                        let (g, b) = (&mut self.g[i], &mut self.b[i]);
+                        let mut value = (body.acc - self.acc[i]) / spacing;
+                        for k in 1..n {
+                            value = (value - g[k - 1]) / (spacing - SPACINGS[k]);
+                        }
+                        let change = value - g[j];
+                        g[j] = value;
+                        for (b, c) in b.iter_mut().zip(&self.c[j][..j]) {
+                            *b += *c * change;
+                        }
+                        b[j] += change;
+                        if n == 7 {
+                            max_change = max_change.max(change.norm());
+                            max_acc = max_acc.max(body.acc.norm());
+                        }
+                    }
+                }
+                error = max_change / max_acc;
+            }
+
+            let max_b6 = self.b.iter().map(|b| b[6].norm()).fold(0.0, f64::max);
+            let max_acc = bodies.iter().map(|body| body.acc.norm()).fold(0.0, f64::max);
+            let relative = max_b6 / max_acc;
+            let wanted = if relative.is_normal() {
+                (dt * (self.epsilon / relative).powf(1.0 / 7.0)).max(min_step)
+            } else {
+                dt / SAFETY
+            };
+
+            if wanted < SAFETY * dt {
+                // much too long, repeat with the polynomial extrapolated from the last accepted step
+                if self.last_step != 0.0 {
+                    let ratio = wanted / self.last_step;
+                    predict(ratio, &self.accepted_b, &self.accepted_e, &mut self.b, &mut self.e);
+                }
+                dt = wanted;
+                continue;
+            }
+
+            for (i, body) in bodies.iter_mut().enumerate() {
+                let b = &self.b[i];
+                let mut dx = Vec3::new(0.0, 0.0, 0.0);
+                let mut dv = Vec3::new(0.0, 0.0, 0.0);
+                for (k, b) in b.iter().enumerate() {
+                    dx += b / ((k + 2) * (k + 3)) as f64;
+                    dv += b / (k + 2) as f64;
+                }
+                compensated_add(
+                    &mut self.pos[i],
+                    &mut self.pos_compensation[i],
+                    dt * (self.vel[i] + dt * (0.5 * self.acc[i] + dx)),
+                );
+                compensated_add(&mut self.vel[i], &mut self.vel_compensation[i], dt * (self.acc[i] + dv));
+                body.pos = self.pos[i];
+                body.vel = self.vel[i];
+            }
+            self.last_step = dt;
+            self.accepted_b.clone_from(&self.b);
+            self.accepted_e.clone_from(&self.e);
+            return (dt, wanted);
+        }
+    }
+}
+
+impl Default for IntegratorIAS15 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Kahan summation, sum + x with the lost low bits kept in compensation
+fn compensated_add(sum: &mut Vec3, compensation: &mut Vec3, x: Vec3) {
+    let y = x - *compensation;
+    let t = *sum + y;
+    *compensation = (t - *sum) - y;
+    *sum = t;
+}
+
+/*
+ * b of the next step from b of a step and its prediction e, for a next step ratio times as long.
+ * The polynomial is shifted to the end of the step, the error of the previous prediction is kept.
+ */
+fn predict(ratio: f64, b: &[[Vec3; 7]], e: &[[Vec3; 7]], next_b: &mut [[Vec3; 7]], next_e: &mut [[Vec3; 7]]) {
+    if ratio > 20.0 {
+        // too far for the polynomial to be of any use
+        let zero = [Vec3::new(0.0, 0.0, 0.0); 7];
+        next_b.iter_mut().for_each(|b| *b = zero);
+        next_e.iter_mut().for_each(|e| *e = zero);
+        return;
+    }
+    for i in 0..b.len() {
+        let mut q = 1.0;
+        for k in 0..7 {
+            q *= ratio;
+            // binomial coefficients (j + 1 over k + 1)
+            let mut binomial = 1.0;
+            let mut shifted = Vec3::new(0.0, 0.0, 0.0);
+            for (j, b) in b[i].iter().enumerate().skip(k) {
+                shifted += binomial * b;
+                binomial = binomial * (j + 2) as f64 / (j + 1 - k) as f64;
+            }
+            next_e[i][k] = q * shifted;
+            next_b[i][k] = next_e[i][k] + (b[i][k] - e[i][k]);
+        }
+    }
+}
+
+impl Integrator for IntegratorIAS15 {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        let min_step = dt / 2f64.powi(MAX_LEVEL as i32);
+        if self.b.len() != bodies.len() {
+            self.restart(bodies);
+        }
+        // contact handlers may have moved bodies, their compensation doesn't apply anymore
+        for (i, body) in bodies.iter().enumerate() {
+            if body.pos != self.pos[i] || body.vel != self.vel[i] {
+                self.pos[i] = body.pos;
+                self.vel[i] = body.vel;
+                self.pos_compensation[i] = Vec3::new(0.0, 0.0, 0.0);
+                self.vel_compensation[i] = Vec3::new(0.0, 0.0, 0.0);
+            }
+        }
+        if self.step == 0.0 {
+            self.step = dt;
+        }
+
+        solver.eval(bodies);
+        self.steps = 0;
+        let mut remaining = dt;
+        while remaining > 0.0 {
+            self.acc = bodies.iter().map(|body| body.acc).collect();
+            let h = if self.step >= remaining { remaining } else { self.step };
+            let (done, wanted) = self.try_step(h, min_step, bodies, solver);
+            remaining -= done;
+            self.steps += 1;
+
+            // a step cut short by the end of dt or by rejections keeps the step size unless it was too long
+            let next = if done < self.step { wanted.min(self.step) } else { wanted.min(done / SAFETY) };
+            predict(next / done, &self.accepted_b, &self.accepted_e, &mut self.b, &mut self.e);
+            self.step = next;
+            solver.eval(bodies);
+        }
+    }
+
+    fn reset(&mut self) {
+        self.b.clear();
+    }
+}
@@ -0,0 +1,99 @@
+type Vec3 = nalgebra::Vector3<f64>;
+
+// Laguerre iterations, converged steps take three or four, bisections of wide brackets a few more
+const MAX_ITERATIONS: usize = 50;
+
+/*
+ * Stumpff functions c0 .. c3 of x, e.g. c0 = cos(sqrt(x)) and c1 = sin(sqrt(x)) / sqrt(x) for x > 0.
+ * The argument is quartered until the series converge fast, then the results are doubled back
+ * (Danby, Fundamentals of Celestial Mechanics, 6.9).
+ */
+fn stumpff(mut x: f64) -> [f64; 4] {
+    let mut quarterings = 0;
+    while x.abs() > 0.1 {
+        x /= 4.0;
+        quarterings += 1;
+    }
+    // c2 = 1/2! - x/4! + x^2/6! .., c3 = 1/3! - x/5! + x^2/7! ..
+    let c2 = (1.0 - x / 12.0 * (1.0 - x / 30.0 * (1.0 - x / 56.0 * (1.0 - x / 90.0 * (1.0 - x / 132.0))))) / 2.0;
+    let c3 = (1.0 - x / 20.0 * (1.0 - x / 42.0 * (1.0 - x / 72.0 * (1.0 - x / 110.0 * (1.0 - x / 156.0))))) / 6.0;
+    let mut c = [1.0 - x * c2, 1.0 - x * c3, c2, c3];
+    for _ in 0..quarterings {
+        c = [
+            2.0 * c[0] * c[0] - 1.0,
+            c[0] * c[1],
+            0.5 * c[1] * c[1],
+            0.25 * (c[2] + c[0] * c[3]),
+        ];
+    }
+    c
+}
+
+/*
+ * Moves a body along its Kepler orbit around a fixed mass for a time dt, mu is G times the mass.
+ * Universal variables work the same for elliptic, parabolic and hyperbolic orbits, the universal
+ * anomaly s solves
+ *     dt = r0 s c1(beta s^2) + (r0 . v0) s^2 c2(beta s^2) + mu s^3 c3(beta s^2),  beta = 2 mu / r0 - v0^2
+ * by Laguerre-Conway iteration, then the position and velocity follow from the f and g functions.
+ * The right side grows with s at the rate r, so s is bracketed and iterations leaving the bracket
+ * bisect it instead: elliptic steps are reduced to less than a period, which s covers within
+ * 2 pi / sqrt(beta), and other orbits never come closer than the pericentre q, so |s| <= |dt| / q.
+ */
+pub(super) fn drift(mu: f64, pos: &Vec3, vel: &Vec3, mut dt: f64) -> (Vec3, Vec3) {
+    let r0 = pos.norm();
+    let eta = pos.dot(vel);
+    let beta = 2.0 * mu / r0 - vel.norm_squared();
+    let zeta = mu - beta * r0;
+
+    let bound = if beta > 0.0 {
+        let period = 2.0 * std::f64::consts::PI * mu / (beta * beta.sqrt());
+        dt %= period;
+        2.0 * std::f64::consts::PI / beta.sqrt()
+    } else {
+        let eccentricity = ((vel.norm_squared() - mu / r0) * pos - eta * vel).norm() / mu;
+        let q = pos.cross(vel).norm_squared() / (mu * (1.0 + eccentricity));
+        dt.abs() / q
+    };
+    let (mut lower, mut upper) = if dt < 0.0 { (-bound, 0.0) } else { (0.0, bound) };
+
+    // the step is usually a small part of the orbit, s is about dt / r0, or else grows with the mean motion
+    let mut s = dt / r0;
+    if !(s > lower && s < upper) {
+        s = dt * beta.max(0.0) / mu;
+    }
+    // bisection takes over from iterations that leave the bracket or slow down, like rtsafe in
+    // Numerical Recipes, so the exponential growth of hyperbolic orbits can't stall them
+    let (mut step, mut before) = (upper - lower, upper - lower);
+    for _ in 0..MAX_ITERATIONS {
+        let c = stumpff(beta * s * s);
+        let f = r0 * s * c[1] + eta * s * s * c[2] + mu * s * s * s * c[3] - dt;
+        let df = r0 * c[0] + eta * s * c[1] + mu * s * s * c[2];
+        let ddf = eta * c[0] + zeta * s * c[1];
+        if f < 0.0 {
+            lower = s;
+        } else {
+            upper = s;
+        }
+        // degree of the Laguerre-Conway iteration
+        let n = 5.0;
+        let root = ((n - 1.0) * (n - 1.0) * df * df - n * (n - 1.0) * f * ddf).abs().sqrt();
+        let mut next = s - n * f / (df + df.signum() * root);
+        if !(next > lower && next < upper) || 2.0 * (next - s).abs() > before {
+            next = 0.5 * (lower + upper);
+        }
+        before = step;
+        step = (next - s).abs();
+        s = next;
+        if step <= 1e-15 * s.abs() || s == lower || s == upper {
+            break;
+        }
+    }
+
+    let c = stumpff(beta * s * s);
+    let r = r0 * c[0] + eta * s * c[1] + mu * s * s * c[2];
+    let f = 1.0 - mu * s * s * c[2] / r0;
+    let g = dt - mu * s * s * s * c[3];
+    let df = -mu * s * c[1] / (r * r0);
+    let dg = 1.0 - mu * s * s * c[2] / r;
+    (f * pos + g * vel, df * pos + dg * vel)
+}
@@ -0,0 +1,42 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use crate::integrator::Integrator;
+
+// velocity verlet in kick-drift-kick form
+// https://en.wikipedia.org/wiki/Leapfrog_integration
+// accelerations from the end of a step are reused at the beginning of the next one, so after the
+// first step it costs one force evaluation per step
+#[derive(Default)]
+pub struct IntegratorLeapfrog {
+    primed: Option<usize>, // number of bodies for which `acc` is already valid
+}
+
+impl IntegratorLeapfrog {
+    pub fn new() -> Self {
+        Self { primed: None }
+    }
+}
+
+impl Integrator for IntegratorLeapfrog {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        if self.primed != Some(bodies.len()) {
+            solver.eval(bodies);
+        }
+
+        for body in bodies.iter_mut() {
+            body.vel += 0.5 * dt * body.acc;
+            body.pos += dt * body.vel;
+        }
+
+        solver.eval(bodies);
+
+        for body in bodies.iter_mut() {
+            body.vel += 0.5 * dt * body.acc;
+        }
+        self.primed = Some(bodies.len());
+    }
+
+    fn reset(&mut self) {
+        self.primed = None;
+    }
+}
@@ -0,0 +1,53 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use crate::integrator::Integrator;
+
+// classic fourth order runge-kutta, four force evaluations per step
+#[derive(Default)]
+pub struct IntegratorRK4 {
+    initial: Vec<Body>,
+    k1: Vec<Body>,
+    k2: Vec<Body>,
+    k3: Vec<Body>,
+}
+
+impl IntegratorRK4 {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+// derivatives are stored as bodies: pos holds dx/dt and vel holds dv/dt
+// bodies are moved to the state used by the next stage: initial + h * k
+fn stage(initial: &[Body], k: &mut Vec<Body>, bodies: &mut [Body], h: f64) {
+    k.clear();
+    k.extend_from_slice(bodies);
+    for (i, body) in bodies.iter_mut().enumerate() {
+        k[i].pos = body.vel;
+        k[i].vel = body.acc;
+        body.pos = initial[i].pos + h * k[i].pos;
+        body.vel = initial[i].vel + h * k[i].vel;
+    }
+}
+
+impl Integrator for IntegratorRK4 {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        self.initial.clear();
+        self.initial.extend_from_slice(bodies);
+
+        solver.eval(bodies);
+        stage(&self.initial, &mut self.k1, bodies, 0.5 * dt);
+        solver.eval(bodies);
+        stage(&self.initial, &mut self.k2, bodies, 0.5 * dt);
+        solver.eval(bodies);
+        stage(&self.initial, &mut self.k3, bodies, dt);
+
+        solver.eval(bodies);
+        for (i, body) in bodies.iter_mut().enumerate() {
+            body.pos = self.initial[i].pos
+                + dt / 6.0 * (self.k1[i].pos + 2.0 * (self.k2[i].pos + self.k3[i].pos) + body.vel);
+            body.vel = self.initial[i].vel
+                + dt / 6.0 * (self.k1[i].vel + 2.0 * (self.k2[i].vel + self.k3[i].vel) + body.acc);
+        }
+    }
+}
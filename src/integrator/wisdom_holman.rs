@@ -0,0 +1,128 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use crate::integrator::kepler;
+use crate::integrator::Integrator;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+/*
+ * Wisdom-Holman mapping in democratic heliocentric coordinates (Duncan, Levison & Lee 1998), for
+ * planetary systems where one body holds most of the mass.
+ * Positions are taken relative to the central body, the most massive one, and velocities relative to
+ * the center of mass. The Hamiltonian splits into Kepler orbits around the central body, which are
+ * followed exactly, the pulls between the other bodies, and a jump of all of them by the momentum
+ * of the central body:
+ *     kick dt/2, jump dt/2, kepler dt, jump dt/2, kick dt/2
+ * Symplectic, and the error scales with the mass ratio of the planets to the central body instead
+ * of the orbit, so steps can be a tenth of the shortest orbit.
+ * The kicks take the accelerations of the solver minus the pull of the central body, so the solver
+ * should not soften it, and the Kepler orbits need the gravitational constant of the solver.
+ * Accelerations from the end of a step are reused at the start of the next one, as in leapfrog.
+ */
+pub struct IntegratorWisdomHolman {
+    gravitational_constant: f64,
+    primed: Option<usize>, // number of bodies for which `acc` is already valid
+}
+
+impl IntegratorWisdomHolman {
+    pub fn new(gravitational_constant: f64) -> Self {
+        Self {
+            gravitational_constant,
+            primed: None,
+        }
+    }
+}
+
+// pulls between the bodies other than the central one, the velocities are barycentric
+fn kick(h: f64, mu: f64, central: usize, bodies: &mut [Body], velocities: &mut [Vec3]) {
+    let center = bodies[central].pos;
+    for (i, (body, vel)) in bodies.iter().zip(velocities.iter_mut()).enumerate() {
+        if i != central {
+            let d = body.pos - center;
+            *vel += h * (body.acc + mu * d / (d.norm_squared() * d.norm()));
+        }
+    }
+}
+
+// moves all bodies other than the central one by the momentum of the central body
+fn jump(h: f64, central: usize, bodies: &mut [Body], velocities: &[Vec3]) {
+    let mut momentum = Vec3::new(0.0, 0.0, 0.0);
+    for (i, (body, vel)) in bodies.iter().zip(velocities).enumerate() {
+        if i != central {
+            momentum += body.mass * vel;
+        }
+    }
+    let shift = h * momentum / bodies[central].mass;
+    for (i, body) in bodies.iter_mut().enumerate() {
+        if i != central {
+            body.pos += shift;
+        }
+    }
+}
+
+impl Integrator for IntegratorWisdomHolman {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        if bodies.is_empty() {
+            return;
+        }
+        if self.primed != Some(bodies.len()) {
+            solver.eval(bodies);
+        }
+
+        let central = (0..bodies.len())
+            .max_by(|&i, &j| bodies[i].mass.total_cmp(&bodies[j].mass))
+            .unwrap();
+        let mu = self.gravitational_constant * bodies[central].mass;
+        let total_mass: f64 = bodies.iter().map(|body| body.mass).sum();
+        let momentum: Vec3 = bodies.iter().map(|body| body.mass * body.vel).sum();
+        let mut center_of_mass: Vec3 = bodies.iter().map(|body| body.mass * body.pos).sum::<Vec3>() / total_mass;
+        let com_vel = momentum / total_mass;
+        let mut velocities: Vec<Vec3> = bodies.iter().map(|body| body.vel - com_vel).collect();
+
+        kick(0.5 * dt, mu, central, bodies, &mut velocities);
+        jump(0.5 * dt, central, bodies, &velocities);
+        let center = bodies[central].pos;
+        for (i, (body, vel)) in bodies.iter_mut().zip(velocities.iter_mut()).enumerate() {
+            if i != central {
+                let (pos, drifted) = kepler::drift(mu, &(body.pos - center), vel, dt);
+                body.pos = center + pos;
+                *vel = drifted;
+            }
+        }
+        jump(0.5 * dt, central, bodies, &velocities);
+
+        // the center of mass moves on uniformly, the central body follows from it
+        center_of_mass += dt * com_vel;
+        let mut offset = Vec3::new(0.0, 0.0, 0.0);
+        for (i, body) in bodies.iter().enumerate() {
+            if i != central {
+                offset += body.mass * (body.pos - center);
+            }
+        }
+        let central_pos = center_of_mass - offset / total_mass;
+        for (i, body) in bodies.iter_mut().enumerate() {
+            if i != central {
+                body.pos += central_pos - center;
+            }
+        }
+        bodies[central].pos = central_pos;
+
+        solver.eval(bodies);
+        kick(0.5 * dt, mu, central, bodies, &mut velocities);
+
+        // barycentric velocities sum to no momentum, that gives the one of the central body
+        let mut central_momentum = Vec3::new(0.0, 0.0, 0.0);
+        for (i, (body, vel)) in bodies.iter_mut().zip(&velocities).enumerate() {
+            if i != central {
+                body.vel = com_vel + vel;
+                central_momentum -= body.mass * vel;
+            }
+        }
+        bodies[central].vel = com_vel + central_momentum / bodies[central].mass;
+        self.primed = Some(bodies.len());
+    }
+
+    fn reset(&mut self) {
+        self.primed = None;
+    }
+}
@@ -0,0 +1,37 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use crate::integrator::Integrator;
+
+// fourth order symplectic integrator obtained by composing three leapfrog steps
+// https://en.wikipedia.org/wiki/Leapfrog_integration#Yoshida_algorithms
+const CBRT_2: f64 = 1.259_921_049_894_873_2;
+const W1: f64 = 1.0 / (2.0 - CBRT_2);
+const W0: f64 = -CBRT_2 / (2.0 - CBRT_2);
+const DRIFTS: [f64; 4] = [W1 / 2.0, (W0 + W1) / 2.0, (W0 + W1) / 2.0, W1 / 2.0];
+const KICKS: [f64; 3] = [W1, W0, W1];
+
+#[derive(Default)]
+pub struct IntegratorYoshida4;
+
+impl IntegratorYoshida4 {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Integrator for IntegratorYoshida4 {
+    fn integrate(&mut self, dt: f64, bodies: &mut [Body], solver: &mut dyn ForceSolver) {
+        for (i, kick) in KICKS.iter().enumerate() {
+            for body in bodies.iter_mut() {
+                body.pos += DRIFTS[i] * dt * body.vel;
+            }
+            solver.eval(bodies);
+            for body in bodies.iter_mut() {
+                body.vel += kick * dt * body.acc;
+            }
+        }
+        for body in bodies.iter_mut() {
+            body.pos += DRIFTS[3] * dt * body.vel;
+        }
+    }
+}
@@ -2,3 +2,12 @@ pub mod body;
 pub mod screen;
 pub mod scenario;
 pub mod barnes_hut;
+pub mod integrator;
+pub mod solver;
+pub mod diagnostics;
+pub mod softening;
+pub mod potential;
+pub mod sph;
+pub mod contact;
+pub mod particle_mesh;
+pub mod fmm;
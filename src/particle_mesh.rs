@@ -0,0 +1,371 @@
+use crate::body::{assert_planar, Body};
+use crate::softening::Softening;
+use crate::solver::ForceSolver;
+use rayon::prelude::*;
+
+pub mod fft;
+
+use fft::Fft;
+
+type Vec2 = nalgebra::Vector2<f64>;
+type Vec3 = nalgebra::Vector3<f64>;
+type Complex = nalgebra::Complex<f64>;
+
+// cells kept free around the bodies of an isolated mesh, assignment reaches one cell further and
+// the finite difference gradient two more
+const MARGIN: usize = 3;
+
+/*
+ * Particle-mesh gravity (Hockney & Eastwood). Masses are assigned to a square mesh, convolved with
+ * the Green's function of the softened 1/r potential using FFTs and the potential is differentiated
+ * on the mesh. Accelerations are interpolated back with the same assignment scheme, so the force
+ * of a body on itself vanishes and momentum is conserved.
+ * The mesh is planar: all bodies have to stay at z = 0 and accelerations have no z component,
+ * evaluating a body off the plane panics. Gravity is still the 3D one, potential -G * m / r as for
+ * all other solvers. Structure smaller than a couple of cells is not resolved, the cost is
+ * O(N + M log M) for M mesh cells.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Boundary {
+    // no images. The mesh follows the bodies and is zero padded to twice its size before the
+    // convolution, so the periodic FFT doesn't wrap around
+    Isolated,
+    // square box [lower, lower + size) repeated in both directions, positions are wrapped into it.
+    // The mean density doesn't attract, as usual for periodic gravity
+    Periodic { lower: Vec2, size: f64 },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum Assignment {
+    // cloud in cell, bilinear over 2x2 cells
+    #[default]
+    CloudInCell,
+    // triangular shaped cloud, quadratic over 3x3 cells, smoother and more isotropic forces
+    TriangularShapedCloud,
+}
+
+// cells touched by a body along one axis and their weights, indices can be outside of the mesh
+struct Stencil {
+    first: isize,
+    weights: [f64; 3],
+    count: usize,
+}
+
+impl Assignment {
+    // u is the position in cells from the mesh origin, cell centers are at i + 0.5
+    fn stencil(&self, u: f64) -> Stencil {
+        match *self {
+            Assignment::CloudInCell => {
+                let u = u - 0.5;
+                let first = u.floor();
+                let f = u - first;
+                Stencil {
+                    first: first as isize,
+                    weights: [1.0 - f, f, 0.0],
+                    count: 2,
+                }
+            }
+            Assignment::TriangularShapedCloud => {
+                let nearest = (u - 0.5).round();
+                let d = u - 0.5 - nearest;
+                Stencil {
+                    first: nearest as isize - 1,
+                    weights: [
+                        0.5 * (0.5 - d) * (0.5 - d),
+                        0.75 - d * d,
+                        0.5 * (0.5 + d) * (0.5 + d),
+                    ],
+                    count: 3,
+                }
+            }
+        }
+    }
+}
+
+pub struct ParticleMesh {
+    cells: usize, // per axis, mesh covering the bodies
+    gravitational_constant: f64,
+    softening: Softening,
+    boundary: Boundary,
+    assignment: Assignment,
+    origin: Vec2, // lower corner of the mesh used by the last solve
+    cell_size: f64,
+    fft: Fft,
+    kernel: Vec<Complex>, // green's function in fourier space
+    kernel_real: Vec<f64>,
+    kernel_cell_size: f64, // cell size the kernel was built for, zero when it has to be rebuilt
+    mesh: Vec<Complex>,    // masses, after the convolution potentials
+    scratch: Vec<Complex>,
+    potential: Vec<f64>, // cells x cells
+    acc: Vec<Vec2>,      // cells x cells
+}
+
+impl ParticleMesh {
+    // planar only: eval and potential_energy panic on any body with z != 0, before touching the bodies
+    pub fn new(gravitational_constant: f64, cells: usize) -> Self {
+        Self::with_softening(gravitational_constant, cells, Softening::default())
+    }
+
+    // planar only: eval and potential_energy panic on any body with z != 0, before touching the bodies
+    pub fn with_softening(gravitational_constant: f64, cells: usize, softening: Softening) -> Self {
+        assert!(
+            cells.is_power_of_two() && cells > 2 * MARGIN,
+            "mesh needs a power of two cells per axis, more than {}, got {}",
+            2 * MARGIN,
+            cells
+        );
+        assert!(
+            softening != Softening::PerBody,
+            "per body softening needs pairs of bodies, the mesh only knows cells"
+        );
+        Self {
+            cells,
+            gravitational_constant,
+            softening,
+            boundary: Boundary::Isolated,
+            assignment: Assignment::default(),
+            origin: Vec2::new(0.0, 0.0),
+            cell_size: 0.0,
+            fft: Fft::new(2 * cells),
+            kernel: Vec::new(),
+            kernel_real: Vec::new(),
+            kernel_cell_size: 0.0,
+            mesh: Vec::new(),
+            scratch: Vec::new(),
+            potential: Vec::new(),
+            acc: Vec::new(),
+        }
+    }
+
+    pub fn set_boundary(&mut self, boundary: Boundary) {
+        if let Boundary::Periodic { size, .. } = boundary {
+            assert!(size > 0.0, "periodic box needs a positive size, got {}", size);
+        }
+        self.boundary = boundary;
+        self.fft = Fft::new(self.mesh_size());
+        self.kernel_cell_size = 0.0;
+    }
+
+    pub fn set_assignment(&mut self, assignment: Assignment) {
+        self.assignment = assignment;
+    }
+
+    // cells per axis of the mesh the FFT runs on
+    fn mesh_size(&self) -> usize {
+        match self.boundary {
+            Boundary::Isolated => 2 * self.cells,
+            Boundary::Periodic { .. } => self.cells,
+        }
+    }
+
+    fn place_mesh(&mut self, bodies: &[Body]) {
+        match self.boundary {
+            Boundary::Periodic { lower, size } => {
+                self.origin = lower;
+                self.cell_size = size / self.cells as f64;
+            }
+            Boundary::Isolated => {
+                let (lower, upper) = bodies
+                    .par_iter()
+                    .filter(|body| body.pos.x.is_finite() && body.pos.y.is_finite())
+                    .map(|body| (planar(&body.pos), planar(&body.pos)))
+                    .reduce(
+                        || (Vec2::repeat(f64::MAX), Vec2::repeat(f64::MIN)),
+                        |a, b| (a.0.inf(&b.0), a.1.sup(&b.1)),
+                    );
+                // square mesh with a bit of space, like the Barnes-Hut root
+                let extent = 1.05 * (upper - lower).max().max(f64::MIN_POSITIVE);
+                self.cell_size = extent / (self.cells - 2 * MARGIN) as f64;
+                self.origin = (lower + upper) / 2.0 - Vec2::repeat(self.cell_size * self.cells as f64 / 2.0);
+            }
+        }
+    }
+
+    // potential factor of the softening sampled on the mesh, offsets wrap around
+    fn build_kernel(&mut self) {
+        let size = self.mesh_size();
+        let h = self.cell_size;
+        let signed = |i: usize| if i <= size / 2 { i as f64 } else { i as f64 - size as f64 };
+        self.kernel.clear();
+        self.kernel.resize(size * size, Complex::new(0.0, 0.0));
+
+        match self.boundary {
+            Boundary::Isolated => {
+                for (index, value) in self.kernel.iter_mut().enumerate() {
+                    let (x, y) = (index % size, index / size);
+                    if x == self.cells || y == self.cells {
+                        continue; // no pair of cells is that far apart
+                    }
+                    let dist2 = (signed(x) * signed(x) + signed(y) * signed(y)) * h * h;
+                    value.re = if dist2 == 0.0 && self.softening == Softening::None {
+                        // mean of 1/r over a square cell, the mass is spread over it
+                        4.0 * (1.0 + 2f64.sqrt()).ln() / h
+                    } else {
+                        self.softening.potential_factor(dist2, 0.0, 0.0)
+                    };
+                }
+                self.kernel_real = self.kernel.iter().map(|value| value.re).collect();
+                self.fft.transform_2d(&mut self.kernel, &mut self.scratch, false);
+            }
+            Boundary::Periodic { size: length, .. } => {
+                // 2D fourier transform of the plummer potential is 2 pi exp(-k eps) / k,
+                // the spline kernel is treated as plummer with the same epsilon
+                let eps = match self.softening {
+                    Softening::None => 0.0,
+                    Softening::Plummer(eps) | Softening::Spline(eps) => eps,
+                    Softening::PerBody => unreachable!(),
+                };
+                let tau = 2.0 * std::f64::consts::PI;
+                for (index, value) in self.kernel.iter_mut().enumerate() {
+                    let (x, y) = (index % size, index / size);
+                    let k = tau / length * (signed(x) * signed(x) + signed(y) * signed(y)).sqrt();
+                    if k > 0.0 {
+                        value.re = tau * (-k * eps).exp() / (k * h * h);
+                    }
+                }
+                let mut real = self.kernel.clone();
+                self.fft.transform_2d(&mut real, &mut self.scratch, true);
+                self.kernel_real = real.iter().map(|value| value.re).collect();
+            }
+        }
+        self.kernel_cell_size = h;
+    }
+
+    fn cell(&self, i: isize) -> usize {
+        match self.boundary {
+            Boundary::Periodic { .. } => i.rem_euclid(self.cells as isize) as usize,
+            // the margin keeps every stencil inside, clamping only guards against rounding
+            Boundary::Isolated => i.max(0).min(self.cells as isize - 1) as usize,
+        }
+    }
+
+    fn stencils(&self, pos: Vec2) -> (Stencil, Stencil) {
+        let u = (pos - self.origin) / self.cell_size;
+        (self.assignment.stencil(u.x), self.assignment.stencil(u.y))
+    }
+
+    // calls f with every cell the body at pos is assigned to and the weight of it
+    fn for_each_cell<F: FnMut(usize, usize, f64)>(&self, pos: Vec2, mut f: F) {
+        let (sx, sy) = self.stencils(pos);
+        for j in 0..sy.count {
+            let y = self.cell(sy.first + j as isize);
+            for i in 0..sx.count {
+                let x = self.cell(sx.first + i as isize);
+                f(x, y, sx.weights[i] * sy.weights[j]);
+            }
+        }
+    }
+
+    // fills potential with the mesh potential of all bodies
+    fn solve(&mut self, bodies: &[Body]) {
+        self.place_mesh(bodies);
+        if self.kernel_cell_size != self.cell_size {
+            self.build_kernel();
+        }
+
+        // deposition stays sequential, every cell sums its masses in the order of bodies
+        let size = self.mesh_size();
+        let mut mesh = std::mem::take(&mut self.mesh);
+        mesh.clear();
+        mesh.resize(size * size, Complex::new(0.0, 0.0));
+        for body in bodies {
+            if body.pos.x.is_finite() && body.pos.y.is_finite() {
+                self.for_each_cell(planar(&body.pos), |x, y, weight| mesh[y * size + x].re += body.mass * weight);
+            }
+        }
+
+        self.fft.transform_2d(&mut mesh, &mut self.scratch, false);
+        mesh.par_iter_mut()
+            .zip(self.kernel.par_iter())
+            .for_each(|(value, kernel)| *value *= kernel);
+        self.fft.transform_2d(&mut mesh, &mut self.scratch, true);
+
+        let cells = self.cells;
+        let gravitational_constant = self.gravitational_constant;
+        self.potential.clear();
+        self.potential.par_extend(
+            (0..cells * cells)
+                .into_par_iter()
+                .map(|index| -gravitational_constant * mesh[(index / cells) * size + index % cells].re),
+        );
+        self.mesh = mesh;
+    }
+
+    // four point central differences of the potential
+    fn compute_gradient(&mut self) {
+        let cells = self.cells as isize;
+        let h = self.cell_size;
+        let this = &*self;
+        let potential = |x: isize, y: isize| this.potential[this.cell(y) * this.cells + this.cell(x)];
+        let acc: Vec<Vec2> = (0..cells * cells)
+            .into_par_iter()
+            .map(|index| {
+                let (x, y) = (index % cells, index / cells);
+                let dx = 8.0 * (potential(x + 1, y) - potential(x - 1, y))
+                    - (potential(x + 2, y) - potential(x - 2, y));
+                let dy = 8.0 * (potential(x, y + 1) - potential(x, y - 1))
+                    - (potential(x, y + 2) - potential(x, y - 2));
+                -Vec2::new(dx, dy) / (12.0 * h)
+            })
+            .collect();
+        self.acc = acc;
+    }
+
+    // potential per unit mass the body feels from its own cloud, removed from the potential energy
+    fn self_potential(&self, body: &Body) -> f64 {
+        let size = self.mesh_size() as isize;
+        let mut cloud = Vec::with_capacity(9);
+        self.for_each_cell(planar(&body.pos), |x, y, weight| cloud.push((x as isize, y as isize, weight)));
+        let mut sum = 0.0;
+        for &(x1, y1, w1) in &cloud {
+            for &(x2, y2, w2) in &cloud {
+                let index = (y1 - y2).rem_euclid(size) * size + (x1 - x2).rem_euclid(size);
+                sum += w1 * w2 * self.kernel_real[index as usize];
+            }
+        }
+        -self.gravitational_constant * body.mass * sum
+    }
+}
+
+impl ForceSolver for ParticleMesh {
+    // bodies with non finite positions are not on the mesh, their acc is zero
+    fn eval(&mut self, bodies: &mut [Body]) {
+        assert_planar(bodies, "ParticleMesh");
+        self.solve(bodies);
+        self.compute_gradient();
+
+        let this = &*self;
+        bodies.par_iter_mut().for_each(|body| {
+            body.acc = Vec3::new(0.0, 0.0, 0.0);
+            if body.pos.x.is_finite() && body.pos.y.is_finite() {
+                let mut acc = Vec2::new(0.0, 0.0);
+                this.for_each_cell(planar(&body.pos), |x, y, weight| acc += this.acc[y * this.cells + x] * weight);
+                body.acc = acc.push(0.0);
+            }
+        });
+    }
+
+    fn potential_energy(&mut self, bodies: &[Body]) -> f64 {
+        assert_planar(bodies, "ParticleMesh");
+        self.solve(bodies);
+
+        // summed sequentially, parallel reductions would depend on how the work was split
+        let this = &*self;
+        let energies: Vec<f64> = bodies
+            .par_iter()
+            .filter(|body| body.pos.x.is_finite() && body.pos.y.is_finite())
+            .map(|body| {
+                let mut potential = 0.0;
+                this.for_each_cell(planar(&body.pos), |x, y, weight| {
+                    potential += this.potential[y * this.cells + x] * weight
+                });
+                body.mass * (potential - this.self_potential(body))
+            })
+            .collect();
+        0.5 * energies.iter().sum::<f64>()
+    }
+}
+
+fn planar(v: &Vec3) -> Vec2 {
+    Vec2::new(v.x, v.y)
+}
@@ -0,0 +1,90 @@
+use rayon::prelude::*;
+
+type Complex = nalgebra::Complex<f64>;
+
+/*
+ * Iterative radix-2 Cooley-Tukey transform for power of two lengths. Twiddle factors and the bit
+ * reversal permutation are computed once per length and reused for every row of a mesh.
+ * The inverse transform is normalised, inverse(forward(x)) == x.
+ */
+pub struct Fft {
+    len: usize,
+    twiddles: Vec<Complex>, // exp(-2 pi i k / len) for k < len / 2
+    reversed: Vec<usize>,
+}
+
+impl Fft {
+    pub fn new(len: usize) -> Self {
+        assert!(len.is_power_of_two(), "fft length has to be a power of two, got {}", len);
+        let bits = len.trailing_zeros();
+        let twiddles = (0..len / 2)
+            .map(|k| {
+                let angle = -2.0 * std::f64::consts::PI * k as f64 / len as f64;
+                Complex::new(angle.cos(), angle.sin())
+            })
+            .collect();
+        let reversed = (0..len)
+            .map(|i| if bits == 0 { 0 } else { i.reverse_bits() >> (usize::BITS - bits) })
+            .collect();
+        Self {
+            len,
+            twiddles,
+            reversed,
+        }
+    }
+
+    pub fn transform(&self, data: &mut [Complex], inverse: bool) {
+        assert_eq!(data.len(), self.len);
+        for i in 0..self.len {
+            let j = self.reversed[i];
+            if i < j {
+                data.swap(i, j);
+            }
+        }
+
+        let mut half = 1;
+        while half < self.len {
+            let stride = self.len / (2 * half);
+            for start in (0..self.len).step_by(2 * half) {
+                for k in 0..half {
+                    let mut twiddle = self.twiddles[k * stride];
+                    if inverse {
+                        twiddle = twiddle.conj();
+                    }
+                    let odd = data[start + k + half] * twiddle;
+                    data[start + k + half] = data[start + k] - odd;
+                    data[start + k] += odd;
+                }
+            }
+            half *= 2;
+        }
+
+        if inverse {
+            let scale = 1.0 / self.len as f64;
+            for value in data.iter_mut() {
+                *value *= scale;
+            }
+        }
+    }
+
+    // square len x len mesh stored by rows, rows are transformed in parallel and the mesh is
+    // transposed twice so columns are contiguous too
+    pub fn transform_2d(&self, data: &mut [Complex], scratch: &mut Vec<Complex>, inverse: bool) {
+        assert_eq!(data.len(), self.len * self.len);
+        for _ in 0..2 {
+            data.par_chunks_mut(self.len)
+                .for_each(|row| self.transform(row, inverse));
+            transpose(data, scratch, self.len);
+        }
+    }
+}
+
+fn transpose(data: &mut [Complex], scratch: &mut Vec<Complex>, len: usize) {
+    scratch.clear();
+    scratch.extend_from_slice(data);
+    data.par_chunks_mut(len).enumerate().for_each(|(y, row)| {
+        for (x, value) in row.iter_mut().enumerate() {
+            *value = scratch[x * len + y];
+        }
+    });
+}
@@ -0,0 +1,123 @@
+type Vec3 = nalgebra::Vector3<f64>;
+
+/*
+ * Static analytic potentials for the parts of a galaxy that aren't resolved by particles, given
+ * around their centre (offset = pos - centre). Disks lie in the x-y plane.
+ *     Nfw:           -G M ln(1 + r / rs) / r, M = 4 pi rho_0 rs^3 (Navarro, Frenk & White 1996)
+ *     Hernquist:     -G M / (r + a)  (Hernquist 1990)
+ *     MiyamotoNagai: -G M / sqrt(x^2 + y^2 + (a + sqrt(z^2 + b^2))^2)  (Miyamoto & Nagai 1975)
+ *     Logarithmic:   v0^2 / 2 * ln(rc^2 + x^2 + y^2 + z^2 / q^2), flat rotation curve at v0 (Binney & Tremaine 2.71)
+ * NFW and logarithmic potentials have infinite mass, they are meant to be used inside a few scale radii.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ExternalPotential {
+    Nfw { mass: f64, scale: f64 },
+    Hernquist { mass: f64, scale: f64 },
+    MiyamotoNagai { mass: f64, a: f64, b: f64 },
+    Logarithmic { speed: f64, core: f64, flattening: f64 },
+}
+
+// where a potential sits: at a fixed point or on a body, carried along as the body moves
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Centre {
+    Fixed(Vec3),
+    Body(usize),
+}
+
+impl ExternalPotential {
+    pub fn acceleration(&self, offset: Vec3, gravitational_constant: f64) -> Vec3 {
+        match *self {
+            ExternalPotential::Nfw { mass, scale } => {
+                let r = offset.norm();
+                if r == 0.0 {
+                    return Vec3::new(0.0, 0.0, 0.0);
+                }
+                let x = r / scale;
+                let enclosed = (1.0 + x).ln() - x / (1.0 + x);
+                -gravitational_constant * mass * enclosed / (r * r * r) * offset
+            }
+            ExternalPotential::Hernquist { mass, scale } => {
+                let r = offset.norm();
+                if r == 0.0 {
+                    return Vec3::new(0.0, 0.0, 0.0);
+                }
+                -gravitational_constant * mass / (r * (r + scale) * (r + scale)) * offset
+            }
+            ExternalPotential::MiyamotoNagai { mass, a, b } => {
+                let zeta = (offset.z * offset.z + b * b).sqrt();
+                let d2 = offset.x * offset.x + offset.y * offset.y + (a + zeta) * (a + zeta);
+                let factor = -gravitational_constant * mass / (d2 * d2.sqrt());
+                factor * Vec3::new(offset.x, offset.y, offset.z * (a + zeta) / zeta)
+            }
+            ExternalPotential::Logarithmic { speed, core, flattening } => {
+                let q2 = flattening * flattening;
+                let denominator = core * core + offset.x * offset.x + offset.y * offset.y + offset.z * offset.z / q2;
+                -speed * speed / denominator * Vec3::new(offset.x, offset.y, offset.z / q2)
+            }
+        }
+    }
+
+    // potential per unit mass, consistent with acceleration
+    pub fn potential(&self, offset: Vec3, gravitational_constant: f64) -> f64 {
+        match *self {
+            ExternalPotential::Nfw { mass, scale } => {
+                let r = offset.norm();
+                if r == 0.0 {
+                    return -gravitational_constant * mass / scale;
+                }
+                -gravitational_constant * mass * (1.0 + r / scale).ln() / r
+            }
+            ExternalPotential::Hernquist { mass, scale } => {
+                -gravitational_constant * mass / (offset.norm() + scale)
+            }
+            ExternalPotential::MiyamotoNagai { mass, a, b } => {
+                let zeta = (offset.z * offset.z + b * b).sqrt();
+                let d2 = offset.x * offset.x + offset.y * offset.y + (a + zeta) * (a + zeta);
+                -gravitational_constant * mass / d2.sqrt()
+            }
+            ExternalPotential::Logarithmic { speed, core, flattening } => {
+                let q2 = flattening * flattening;
+                let argument = core * core + offset.x * offset.x + offset.y * offset.y + offset.z * offset.z / q2;
+                0.5 * speed * speed * argument.ln()
+            }
+        }
+    }
+
+    // mass density that generates the potential (Poisson's equation)
+    pub fn density(&self, offset: Vec3, gravitational_constant: f64) -> f64 {
+        let pi = std::f64::consts::PI;
+        match *self {
+            ExternalPotential::Nfw { mass, scale } => {
+                let x = offset.norm() / scale;
+                mass / (4.0 * pi * scale * scale * scale * x * (1.0 + x) * (1.0 + x))
+            }
+            ExternalPotential::Hernquist { mass, scale } => {
+                let r = offset.norm();
+                mass * scale / (2.0 * pi * r * (r + scale) * (r + scale) * (r + scale))
+            }
+            ExternalPotential::MiyamotoNagai { mass, a, b } => {
+                let zeta = (offset.z * offset.z + b * b).sqrt();
+                let cylindrical2 = offset.x * offset.x + offset.y * offset.y;
+                let d2 = cylindrical2 + (a + zeta) * (a + zeta);
+                b * b * mass / (4.0 * pi)
+                    * (a * cylindrical2 + (a + 3.0 * zeta) * (a + zeta) * (a + zeta))
+                    / (d2 * d2 * d2.sqrt() * zeta * zeta * zeta)
+            }
+            ExternalPotential::Logarithmic { speed, core, flattening } => {
+                let q2 = flattening * flattening;
+                let cylindrical2 = offset.x * offset.x + offset.y * offset.y;
+                let z2 = offset.z * offset.z;
+                let argument = core * core + cylindrical2 + z2 / q2;
+                speed * speed / (4.0 * pi * gravitational_constant * q2)
+                    * ((2.0 * q2 + 1.0) * core * core + cylindrical2 + (2.0 - 1.0 / q2) * z2)
+                    / (argument * argument)
+            }
+        }
+    }
+
+    // speed of a circular orbit of given radius in the x-y plane
+    pub fn circular_speed(&self, radius: f64, gravitational_constant: f64) -> f64 {
+        let acc = self.acceleration(Vec3::new(radius, 0.0, 0.0), gravitational_constant);
+        (radius * acc.norm()).sqrt()
+    }
+}
@@ -1,13 +1,18 @@
+use crate::diagnostics::Diagnostics;
 use crate::screen::Screen;
 
 mod collision;
 mod sun_earth_moon;
 mod three_body;
 mod collision_barnes_hut;
+mod protoplanets;
+mod gas_disc;
 //mod barnes_hut;
 
 pub use collision::Collision;
 pub use collision_barnes_hut::GalaxyCollisionBarnesHut;
+pub use gas_disc::GasDisc;
+pub use protoplanets::Protoplanets;
 pub use sun_earth_moon::SunEarthMoon;
 pub use three_body::ThreeBody;
 
@@ -15,4 +20,5 @@ pub trait Scenario
 {
     fn process(&mut self, dt : f64);
     fn draw(&self, renderer : &mut dyn Screen);
+    fn diagnostics(&mut self) -> Diagnostics;
 }
@@ -1,15 +1,32 @@
 use crate::body::{Body, Mass, Radius};
+use crate::contact::{Bounce, ContactHandler};
+use crate::diagnostics::Diagnostics;
+use crate::integrator::{Integrator, IntegratorBlockLeapfrog, IntegratorEuler, TimestepCriterion};
+use crate::potential::{Centre, ExternalPotential};
+use crate::solver::{Background, DynamicalFriction, ExternalField, ForceSolver, RestrictedSolver};
 use crate::screen::Screen;
 use crate::scenario::Scenario;
 
-type Vec2 = nalgebra::Vector2<f64>;
+type Vec3 = nalgebra::Vector3<f64>;
+
+// both galaxy centres are stored in front of the stars
+const CENTRE1: usize = 0;
+const CENTRE2: usize = 1;
+const CENTRES: usize = 2;
+
+const G: f64 = 3.0;
+
+// NFW halo with 20000 within three scale radii, the mass parameter is divided by ln(4) - 3 / 4
+const HALO: ExternalPotential = ExternalPotential::Nfw { mass: 20000.0 / 0.636294361, scale: 10.0 };
+// ln(b_max / b_min), the halo scale over the size of a centre
+const COULOMB_LOGARITHM: f64 = 1.5;
 
 pub struct Collision {
-    Centre1: Body,
-    Bodies1: Vec<Body>,
-    Centre2: Body,
-    Bodies2: Vec<Body>,
-    G: f64,
+    bodies: Vec<Body>,
+    integrator: Box<dyn Integrator>,
+    solver: Box<dyn ForceSolver>,
+    contacts: Option<Box<dyn ContactHandler>>,
+    time: f64,
 }
 
 /*
@@ -18,17 +35,72 @@ pub struct Collision {
  */
 impl Collision {
     pub fn new(subobjects: usize) -> Collision {
+        Collision::with_integrator(subobjects, Box::new(IntegratorEuler::new()))
+    }
+
+    pub fn with_integrator(subobjects: usize, integrator: Box<dyn Integrator>) -> Collision {
+        let solver = Box::new(RestrictedSolver::new(CENTRES, G));
+        Collision::with_integrator_and_solver(subobjects, integrator, solver)
+    }
+
+    pub fn with_integrator_and_solver(
+        subobjects: usize,
+        integrator: Box<dyn Integrator>,
+        solver: Box<dyn ForceSolver>,
+    ) -> Collision {
+        Collision::with_halo(subobjects, integrator, solver, None)
+    }
+
+    /*
+     * Both centres carry a dark matter halo (NFW, ten times their mass within 30), stars start on
+     * circular orbits in the combined potential and the rotation curves stay nearly flat across the
+     * discs instead of falling off like around a point mass. The centres pass within a few softening
+     * lengths of each other, block timesteps resolve that without shortening the steps of the stars.
+     */
+    pub fn with_halos(subobjects: usize) -> Collision {
+        Collision::with_halo(subobjects, halo_integrator(), Box::new(halo_field()), Some(HALO))
+    }
+
+    // halos as in with_halos, each centre feels dynamical friction in the halo of the other one, so
+    // instead of passing through each other they sink together and merge
+    pub fn merging(subobjects: usize) -> Collision {
+        let mut solver = DynamicalFriction::new(Box::new(halo_field()), CENTRES, G, COULOMB_LOGARITHM);
+        solver.set_cutoff(5.0);
+        solver.add(Background::Profile(HALO), Centre::Body(CENTRE1));
+        solver.add(Background::Profile(HALO), Centre::Body(CENTRE2));
+        Collision::with_halo(subobjects, halo_integrator(), Box::new(solver), Some(HALO))
+    }
+
+    // stars bounce off each other and off the centres instead of passing through them
+    pub fn bouncing(subobjects: usize, restitution: f64) -> Collision {
+        let mut obj = Collision::new(subobjects);
+        obj.set_contact_handler(Box::new(Bounce::new(restitution)));
+        obj
+    }
+
+    fn with_halo(
+        subobjects: usize,
+        integrator: Box<dyn Integrator>,
+        solver: Box<dyn ForceSolver>,
+        halo: Option<ExternalPotential>,
+    ) -> Collision {
+        let circular_speed = |mass: f64, r: f64| {
+            let halo_speed = halo.map_or(0.0, |halo| halo.circular_speed(r, G));
+            (G * mass / r + halo_speed * halo_speed).sqrt()
+        };
         let mut obj = Collision {
-            Centre1: Body::new(Mass(2000.0), Radius(2.5)),
-            Bodies1: Vec::with_capacity(subobjects),
-            Centre2: Body::new(Mass(2000.0), Radius(2.5)),
-            Bodies2: Vec::with_capacity(subobjects),
-            G: 3.0,
+            bodies: Vec::with_capacity(CENTRES + 2 * subobjects),
+            integrator,
+            solver,
+            contacts: None,
+            time: 0.0,
         };
+        obj.bodies.push(Body::new(Mass(2000.0), Radius(2.5)));
+        obj.bodies.push(Body::new(Mass(2000.0), Radius(2.5)));
 
         // Initializing first galaxy
-        obj.Centre1.pos = Vec2::new(150.0, 20.0);
-        obj.Centre1.vel = Vec2::new(-5.0, 0.0);
+        obj.bodies[CENTRE1].pos = Vec3::new(150.0, 20.0, 0.0);
+        obj.bodies[CENTRE1].vel = Vec3::new(-5.0, 0.0, 0.0);
 
         for _ in 0..subobjects {
             let maxRadius = 30.0;
@@ -37,21 +109,21 @@ impl Collision {
             r = r * r / maxRadius;
 
             let mut body = Body::new(Mass(1.0), Radius(0.2));
-            body.pos = Vec2::new(r * theta.cos(), r * theta.sin());
-            body.pos += obj.Centre1.pos;
+            body.pos = Vec3::new(r * theta.cos(), r * theta.sin(), 0.0);
+            body.pos += obj.bodies[CENTRE1].pos;
 
-            let v = (obj.G * obj.Centre1.mass / r).sqrt();
-            body.vel = Vec2::new(v * theta.sin(), -v * theta.cos());
+            let v = circular_speed(obj.bodies[CENTRE1].mass, r);
+            body.vel = Vec3::new(v * theta.sin(), -v * theta.cos(), 0.0);
 
             let offset = 0.6;
-            body.vel += Vec2::new(random(-offset, offset), random(-offset, offset));
-            body.vel += obj.Centre1.vel;
+            body.vel += Vec3::new(random(-offset, offset), random(-offset, offset), 0.0);
+            body.vel += obj.bodies[CENTRE1].vel;
 
-            obj.Bodies1.push(body);
+            obj.bodies.push(body);
         }
 
-        obj.Centre2.pos = -obj.Centre1.pos;
-        obj.Centre2.vel = -obj.Centre1.vel;
+        obj.bodies[CENTRE2].pos = -obj.bodies[CENTRE1].pos;
+        obj.bodies[CENTRE2].vel = -obj.bodies[CENTRE1].vel;
 
         for _ in 0..subobjects {
             let maxRadius = 30.0;
@@ -59,82 +131,106 @@ impl Collision {
 
             let mut r = random(1.0, maxRadius);
             r = r * r / maxRadius;
-            r += 0.2 * obj.Centre2.radius;
+            r += 0.2 * obj.bodies[CENTRE2].radius;
 
             let mut body = Body::new(Mass(1.0), Radius(0.2));
-            body.pos = Vec2::new(r * theta.cos(), r * theta.sin());
-            body.pos += obj.Centre2.pos;
+            body.pos = Vec3::new(r * theta.cos(), r * theta.sin(), 0.0);
+            body.pos += obj.bodies[CENTRE2].pos;
 
-            let v = (obj.G * obj.Centre2.mass / r).sqrt();
-            body.vel = Vec2::new(v * theta.sin(), -v * theta.cos());
+            let v = circular_speed(obj.bodies[CENTRE2].mass, r);
+            body.vel = Vec3::new(v * theta.sin(), -v * theta.cos(), 0.0);
             // uncomment for opposite direction of rotation
             // body.vel = -body.vel;
 
             let offset = 0.6;
-            body.vel += Vec2::new(random(-offset, offset), random(-offset, offset));
-            body.vel += obj.Centre2.vel;
+            body.vel += Vec3::new(random(-offset, offset), random(-offset, offset), 0.0);
+            body.vel += obj.bodies[CENTRE2].vel;
 
-            obj.Bodies2.push(body);
+            obj.bodies.push(body);
         }
 
         obj
     }
 
+    // both centres first, then the stars
+    pub fn bodies(&self) -> &[Body] {
+        &self.bodies
+    }
+
+    // bodies pass through each other unless a contact handler is set. The solvers refer to the centres by
+    // index, so the handler must never remove them, e.g. Merger::with_protected(2). process panics if
+    // it does
+    pub fn set_contact_handler(&mut self, contacts: Box<dyn ContactHandler>) {
+        self.contacts = Some(contacts);
+    }
+
     fn plot_body(&self, renderer : &mut dyn Screen, body: Body) {
         // TODO how to get mutable reference to body here?
-        renderer.plot_circle(body.pos.x, body.pos.y, body.radius);
+        let (x, y) = renderer.project(&body.pos);
+        renderer.plot_circle(x, y, body.radius);
     }
 }
 
 impl Scenario for Collision {
     fn process(&mut self, dt: f64) {
-        // centres attract each other
-        self.Centre1.pull_by(&self.Centre2, self.G);
-        self.Centre2.pull_by(&self.Centre1, self.G);
-
-        // particles are attracted to centres
-        for i in 0..self.Bodies1.len() {
-            self.Bodies1[i].pull_by(&self.Centre1, self.G);
-            self.Bodies2[i].pull_by(&self.Centre1, self.G);
-            self.Bodies1[i].pull_by(&self.Centre2, self.G);
-            self.Bodies2[i].pull_by(&self.Centre2, self.G);
-        }
-
-        self.Centre1.process_forces(dt);
-        self.Centre2.process_forces(dt);
-
-        for i in 0..self.Bodies1.len() {
-            self.Bodies1[i].process_forces(dt);
-        }
-        for i in 0..self.Bodies2.len() {
-            self.Bodies2[i].process_forces(dt);
+        self.integrator.integrate(dt, &mut self.bodies, self.solver.as_mut());
+        self.time += dt;
+        if let Some(contacts) = self.contacts.as_mut() {
+            // a centre only gains mass, a star moved into its place would lose some
+            let masses = [self.bodies[CENTRE1].mass, self.bodies[CENTRE2].mass];
+            if contacts.resolve(&mut self.bodies, self.time) {
+                assert!(
+                    self.bodies.len() >= CENTRES
+                        && self.bodies[CENTRE1].mass >= masses[0]
+                        && self.bodies[CENTRE2].mass >= masses[1],
+                    "the contact handler removed a galaxy centre, protect the first {} bodies",
+                    CENTRES
+                );
+                self.integrator.reset();
+            }
         }
     }
 
     fn draw(&self, renderer : &mut dyn Screen) {
         renderer.clear();
-        self.plot_body(renderer, self.Centre1);
-        self.plot_body(renderer, self.Centre2);
-
-        for i in 0..self.Bodies1.len() {
-            self.plot_body(renderer, self.Bodies1[i]);
-        }
-        for i in 0..self.Bodies2.len() {
-            self.plot_body(renderer, self.Bodies2[i]);
+        for body in &self.bodies {
+            self.plot_body(renderer, *body);
         }
 
         // drawing
-        if (self.Centre1.pos - self.Centre2.pos).dot(&(self.Centre1.pos - self.Centre2.pos))
+        let centre1 = self.bodies[CENTRE1];
+        let centre2 = self.bodies[CENTRE2];
+        if (centre1.pos - centre2.pos).dot(&(centre1.pos - centre2.pos))
             < 90.0 * 90.0
         {
             renderer.set_zoom(5.0);
-        } else if (self.Centre1.pos - self.Centre2.pos).dot(&(self.Centre1.pos - self.Centre2.pos))
+        } else if (centre1.pos - centre2.pos).dot(&(centre1.pos - centre2.pos))
             > 110.0 * 110.0
         {
             renderer.set_zoom(2.0);
         }
         renderer.draw();
     }
+
+    fn diagnostics(&mut self) -> Diagnostics {
+        Diagnostics::compute(&self.bodies, self.solver.as_mut())
+    }
+}
+
+// restricted gravity of the centres plus a halo around each of them
+fn halo_field() -> ExternalField {
+    let mut solver = ExternalField::new(Box::new(RestrictedSolver::new(CENTRES, G)), G);
+    solver.add(HALO, Centre::Body(CENTRE1));
+    solver.add(HALO, Centre::Body(CENTRE2));
+    solver
+}
+
+// block timesteps, the centres pass close to each other
+fn halo_integrator() -> Box<dyn Integrator> {
+    Box::new(IntegratorBlockLeapfrog::new(TimestepCriterion::Acceleration {
+        eta: 0.01,
+        length: 0.1,
+    }))
 }
 
 fn random(low: f64, high: f64) -> f64 {
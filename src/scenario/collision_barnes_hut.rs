@@ -1,18 +1,26 @@
 use crate::scenario::Scenario;
 use crate::screen::Screen;
-use crate::barnes_hut::{GAMMA, BarnesHutSimulation, IntegratorADB6};
+use crate::barnes_hut::{GAMMA, BarnesHutSimulation};
 use crate::body::*;
+use crate::contact::{AccretionEvent, ContactHandler, Sinks};
+use crate::diagnostics::{center_of_mass, Diagnostics};
+use crate::integrator::{Integrator, IntegratorABM6};
+use crate::solver::{ForceSolver, PostNewtonian};
 use rand::Rng;
 
 
-type Vec2 = nalgebra::Vector2<f64>;
+type Vec3 = nalgebra::Vector3<f64>;
 
 pub struct GalaxyCollisionBarnesHut {
-    integrator: IntegratorADB6,
-    model: BarnesHutSimulation,
+    integrator: Box<dyn Integrator>,
+    solver: Box<dyn ForceSolver>,
+    bodies: Vec<Body>,
+    sinks: Option<Sinks>,
+    time: f64,
 }
 
-fn get_galaxies() -> Vec<Body> {
+// the disc of the second galaxy is tilted by inclination (radians) around the x axis
+fn get_galaxies(inclination: f64) -> Vec<Body> {
     let mut bodies = Vec::new();
 
     let create_body = |mass, position, velocity, radius| {
@@ -22,23 +30,23 @@ fn get_galaxies() -> Vec<Body> {
         return new_body;
     };
 
-    let get_orbital_velocity = |pos1: Vec2, pos2: Vec2, m1: f64| {
+    let get_orbital_velocity = |pos1: Vec3, pos2: Vec3, m1: f64| {
         let dist: f64 = (pos1 - pos2).dot(&(pos1 - pos2)).sqrt();
         let v = (GAMMA * m1 / dist).sqrt();
-        return Vec2::new((pos1.y - pos2.y) / dist * v, -(pos1.x - pos2.x) / dist * v);
+        return Vec3::new((pos1.y - pos2.y) / dist * v, -(pos1.x - pos2.x) / dist * v, 0.0);
     };
 
     // add black hole
     let black_hole1 = create_body(
         Mass(1000000.0),
-        Vec2::new(0.0, 0.0),
-        Vec2::new(0.0, 0.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 0.0, 0.0),
         Radius(0.5)
     );
 
     // second black hole
     let black_hole2 = (|| {
-        let pos = Vec2::new(10.0, 10.0);
+        let pos = Vec3::new(10.0, 10.0, 0.0);
         let vel = get_orbital_velocity(black_hole1.pos, pos, black_hole1.mass) * 0.9;
         return create_body(
             Mass(black_hole1.mass / 10.0),
@@ -55,23 +63,22 @@ fn get_galaxies() -> Vec<Body> {
         let r = 0.1 + 0.8 * (rad * rng.gen_range(0.0..1.0));
         let a = 2.0 * std::f64::consts::PI * rng.gen_range(0.0..1.0);
         let mass = Mass(0.03 + 20.0 * rng.gen_range(0.0..1.0));
-        let pos = Vec2::new(r * a.sin(), r * a.cos());
+        let pos = Vec3::new(r * a.sin(), r * a.cos(), 0.0);
         let vel = get_orbital_velocity(black_hole1.pos, pos, black_hole1.mass);
         return create_body(mass, pos, vel, Radius(0.05));
     }).collect::<Vec<Body>>();
 
     // add second galaxy
+    let tilt = nalgebra::Rotation3::from_axis_angle(&Vec3::x_axis(), inclination);
     let galaxy2 : Vec<Body> = (4001..5000).map(|_| { 
         let rad = 3.0;
         let r = 0.1 + 0.8 * (rad * rng.gen_range(0.0..1.0));
         let a = 2.0 * std::f64::consts::PI * rng.gen_range(0.0..1.0);
         let mass = Mass(0.03 + 20.0 * rng.gen_range(0.0..1.0));
-        let pos = Vec2::new(
-            black_hole2.pos.x + r * a.sin(),
-            black_hole2.pos.y + r * a.cos(),
-        );
-        let vel = get_orbital_velocity(black_hole2.pos, pos, black_hole2.mass)
-            + black_hole2.vel;
+        let offset = Vec3::new(r * a.sin(), r * a.cos(), 0.0);
+        let vel = get_orbital_velocity(Vec3::new(0.0, 0.0, 0.0), offset, black_hole2.mass);
+        let pos = black_hole2.pos + tilt * offset;
+        let vel = black_hole2.vel + tilt * vel;
         return create_body(mass, pos, vel, Radius(0.05));
     }).collect();
 
@@ -82,34 +89,94 @@ fn get_galaxies() -> Vec<Body> {
     return bodies;
 }
 
+fn barnes_hut() -> BarnesHutSimulation {
+    let mut solver = BarnesHutSimulation::new(GAMMA);
+    solver.set_quadrupole(true);
+    solver
+}
+
 impl GalaxyCollisionBarnesHut {
     pub fn new() -> Self {
-        let bodies = get_galaxies();
-        let mut obj = Self {
-            integrator: IntegratorADB6::new(bodies.len(), 100.0),
-            model: BarnesHutSimulation::new(bodies),
-        };
-        obj.integrator.set_initial_state(&mut obj.model);
-        obj
+        Self::inclined(0.0)
+    }
+
+    // encounter with the disc of the smaller galaxy tilted by inclination (radians)
+    pub fn inclined(inclination: f64) -> Self {
+        let bodies = get_galaxies(inclination);
+        let integrator = Box::new(IntegratorABM6::new());
+        Self::with_bodies(bodies, integrator, Box::new(barnes_hut()))
+    }
+
+    pub fn with_integrator(integrator: Box<dyn Integrator>) -> Self {
+        Self::with_bodies(get_galaxies(0.0), integrator, Box::new(barnes_hut()))
+    }
+
+    pub fn with_integrator_and_solver(
+        integrator: Box<dyn Integrator>,
+        solver: Box<dyn ForceSolver>,
+    ) -> Self {
+        Self::with_bodies(get_galaxies(0.0), integrator, solver)
+    }
+
+    /*
+     * The two black holes (first bodies) attract each other with 1PN and 2.5PN corrections, speed of
+     * light in parsec per year. With the real one (barnes_hut::SPEED_OF_LIGHT) the pair is too wide
+     * for any visible effect, a few times its orbital speed of 1e-5 shows precession and inspiral.
+     */
+    pub fn post_newtonian(speed_of_light: f64) -> Self {
+        let mut solver = PostNewtonian::new(Box::new(barnes_hut()), 2, GAMMA, speed_of_light);
+        solver.set_radiation_reaction(true);
+        Self::with_integrator_and_solver(Box::new(IntegratorABM6::new()), Box::new(solver))
+    }
+
+    // the black holes swallow stars that come closer than radius (parsec) instead of flinging them away,
+    // they don't swallow each other and stay the first two bodies
+    pub fn with_sinks(radius: f64) -> Self {
+        let mut scenario = Self::new();
+        scenario.sinks = Some(Sinks::with_protected(vec![radius, radius], 2));
+        scenario
+    }
+
+    pub fn bodies(&self) -> &[Body] {
+        &self.bodies
+    }
+
+    // stars swallowed by the black holes so far, empty without sinks
+    pub fn accretions(&self) -> &[AccretionEvent] {
+        self.sinks.as_ref().map_or(&[], |sinks| sinks.log())
+    }
+
+    fn with_bodies(bodies: Vec<Body>, integrator: Box<dyn Integrator>, solver: Box<dyn ForceSolver>) -> Self {
+        Self {
+            integrator,
+            solver,
+            bodies,
+            sinks: None,
+            time: 0.0,
+        }
     }
 
 }
 impl Scenario for GalaxyCollisionBarnesHut {
     fn process(&mut self, dt: f64) {
-        self.integrator.integrate(dt, &mut self.model);
-        self.model.eval();
+        self.integrator.integrate(dt, &mut self.bodies, self.solver.as_mut());
+        self.time += dt;
+        if let Some(sinks) = self.sinks.as_mut() {
+            if sinks.resolve(&mut self.bodies, self.time) {
+                self.integrator.reset();
+            }
+        }
     }
 
     fn draw(&self, renderer: &mut dyn Screen) {
         renderer.clear();
-        renderer.position(self.model.center.x, self.model.center.y);
-
-        for i in 0..self.model.bodies.len() {
-            renderer.plot_circle(
-                self.model.bodies[i].pos.x,
-                self.model.bodies[i].pos.y,
-                self.model.bodies[i].radius,
-            );
+        let center = center_of_mass(&self.bodies);
+        let (x, y) = renderer.project(&center);
+        renderer.position(x, y);
+
+        for i in 0..self.bodies.len() {
+            let (x, y) = renderer.project(&self.bodies[i].pos);
+            renderer.plot_circle(x, y, self.bodies[i].radius);
         }
 
         /*
@@ -125,4 +192,8 @@ impl Scenario for GalaxyCollisionBarnesHut {
         }*/
         renderer.draw();
     }
+
+    fn diagnostics(&mut self) -> Diagnostics {
+        Diagnostics::compute(&self.bodies, self.solver.as_mut())
+    }
 }
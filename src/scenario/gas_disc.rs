@@ -0,0 +1,92 @@
+use crate::barnes_hut::BarnesHutSimulation;
+use crate::body::{Body, Mass, Radius};
+use crate::diagnostics::Diagnostics;
+use crate::integrator::{Integrator, IntegratorLeapfrog};
+use crate::scenario::Scenario;
+use crate::screen::Screen;
+use crate::softening::Softening;
+use crate::sph::{EquationOfState, Sph};
+use rand::Rng;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+const G: f64 = 1.0;
+const STAR_MASS: f64 = 1000.0;
+const DISC_MASS: f64 = 20.0;
+// aspect ratio H / r = sound speed / orbital speed, at r = 40
+const SOUND_SPEED: f64 = 0.25;
+
+/*
+ * Isothermal protoplanetary gas disc around a star, same units as Protoplanets. Gas particles come
+ * first, the star is the last body. Pressure makes the gas orbit a bit slower than Kepler and keeps
+ * the disc at a scale height of H = c / Omega, viscosity spreads it slowly.
+ */
+pub struct GasDisc {
+    bodies: Vec<Body>,
+    integrator: Box<dyn Integrator>,
+    solver: Sph,
+    star: usize,
+}
+
+impl GasDisc {
+    pub fn new(particles: usize) -> GasDisc {
+        let mut bodies = Vec::with_capacity(particles + 1);
+        let mut rng = rand::thread_rng();
+        for _ in 0..particles {
+            // surface density falling off as 1 / r, uniform in radius
+            let r: f64 = rng.gen_range(20.0..60.0);
+            let a = 2.0 * std::f64::consts::PI * rng.gen_range(0.0..1.0);
+            let omega = (G * STAR_MASS / (r * r * r)).sqrt();
+            let height = SOUND_SPEED / omega;
+            // gaussian vertical profile from Box-Muller
+            let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+            let z = height * (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * rng.gen_range(0.0..1.0)).cos();
+
+            // pressure support, d ln(rho) / d ln(r) is about -2.5 in the midplane
+            let v = (G * STAR_MASS / r - 2.5 * SOUND_SPEED * SOUND_SPEED).sqrt();
+            let mut body = Body::new(Mass(DISC_MASS / particles as f64), Radius(0.2));
+            body.pos = Vec3::new(r * a.cos(), r * a.sin(), z);
+            body.vel = Vec3::new(-v * a.sin(), v * a.cos(), 0.0);
+            bodies.push(body);
+        }
+        bodies.push(Body::new(Mass(STAR_MASS), Radius(3.0)));
+
+        let gravity = BarnesHutSimulation::with_softening(G, Softening::Plummer(0.5));
+        let mut solver = Sph::new(gravity, particles, EquationOfState::Isothermal { sound_speed: SOUND_SPEED });
+        // the default viscosity is meant for shocks, in the shear of the disc it would drain it into
+        // the star within a few orbits
+        solver.set_viscosity(0.1, 0.2);
+        GasDisc {
+            bodies,
+            integrator: Box::new(IntegratorLeapfrog::new()),
+            solver,
+            star: particles,
+        }
+    }
+
+    pub fn bodies(&self) -> &[Body] {
+        &self.bodies
+    }
+}
+
+impl Scenario for GasDisc {
+    fn process(&mut self, dt: f64) {
+        self.integrator.integrate(dt, &mut self.bodies, &mut self.solver);
+        self.solver.advance_entropy(dt);
+    }
+
+    fn draw(&self, renderer: &mut dyn Screen) {
+        renderer.clear();
+        let (x, y) = renderer.project(&self.bodies[self.star].pos);
+        renderer.position(x, y);
+        for body in &self.bodies {
+            let (x, y) = renderer.project(&body.pos);
+            renderer.plot_circle(x, y, body.radius);
+        }
+        renderer.draw();
+    }
+
+    fn diagnostics(&mut self) -> Diagnostics {
+        Diagnostics::compute(&self.bodies, &mut self.solver)
+    }
+}
@@ -0,0 +1,96 @@
+use crate::body::{Body, Mass, Radius};
+use crate::contact::{ContactHandler, Merger};
+use crate::diagnostics::Diagnostics;
+use crate::integrator::{Integrator, IntegratorLeapfrog};
+use crate::solver::{DirectSolver, ForceSolver};
+use crate::screen::Screen;
+use crate::scenario::Scenario;
+use rand::Rng;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+const STAR: usize = 0;
+const G: f64 = 1.0;
+
+// disc of planetesimals around a star, touching planetesimals stick together and grow into planets
+pub struct Protoplanets {
+    bodies: Vec<Body>,
+    integrator: Box<dyn Integrator>,
+    solver: Box<dyn ForceSolver>,
+    contacts: Option<Box<dyn ContactHandler>>,
+    time: f64,
+}
+
+impl Protoplanets {
+    pub fn new(planetesimals: usize) -> Protoplanets {
+        Protoplanets::with_integrator_and_solver(
+            planetesimals,
+            Box::new(IntegratorLeapfrog::new()),
+            Box::new(DirectSolver::new(G)),
+        )
+    }
+
+    pub fn with_integrator_and_solver(
+        planetesimals: usize,
+        integrator: Box<dyn Integrator>,
+        solver: Box<dyn ForceSolver>,
+    ) -> Protoplanets {
+        let mut obj = Protoplanets {
+            bodies: Vec::with_capacity(planetesimals + 1),
+            integrator,
+            solver,
+            contacts: Some(Box::new(Merger::new())),
+            time: 0.0,
+        };
+        obj.bodies.push(Body::new(Mass(1000.0), Radius(3.0)));
+
+        let mut rng = rand::thread_rng();
+        for _ in 0..planetesimals {
+            let r = rng.gen_range(20.0..60.0);
+            let a = 2.0 * std::f64::consts::PI * rng.gen_range(0.0..1.0);
+            let mut body = Body::new(Mass(rng.gen_range(0.01..0.1)), Radius(rng.gen_range(0.3..0.6)));
+            body.pos = Vec3::new(r * a.cos(), r * a.sin(), 0.0);
+
+            // nearly circular orbits with a bit of random motion so the orbits cross
+            let v = (G * obj.bodies[STAR].mass / r).sqrt();
+            let offset = 0.05 * v;
+            body.vel = Vec3::new(-v * a.sin(), v * a.cos(), 0.0)
+                + Vec3::new(rng.gen_range(-offset..offset), rng.gen_range(-offset..offset), 0.0);
+            obj.bodies.push(body);
+        }
+
+        obj
+    }
+
+    // merging by default, pass another handler to compare
+    pub fn set_contact_handler(&mut self, contacts: Box<dyn ContactHandler>) {
+        self.contacts = Some(contacts);
+    }
+}
+
+impl Scenario for Protoplanets {
+    fn process(&mut self, dt: f64) {
+        self.integrator.integrate(dt, &mut self.bodies, self.solver.as_mut());
+        self.time += dt;
+        if let Some(contacts) = self.contacts.as_mut() {
+            if contacts.resolve(&mut self.bodies, self.time) {
+                self.integrator.reset();
+            }
+        }
+    }
+
+    fn draw(&self, renderer: &mut dyn Screen) {
+        renderer.clear();
+        let (x, y) = renderer.project(&self.bodies[STAR].pos);
+        renderer.position(x, y);
+        for body in &self.bodies {
+            let (x, y) = renderer.project(&body.pos);
+            renderer.plot_circle(x, y, body.radius);
+        }
+        renderer.draw();
+    }
+
+    fn diagnostics(&mut self) -> Diagnostics {
+        Diagnostics::compute(&self.bodies, self.solver.as_mut())
+    }
+}
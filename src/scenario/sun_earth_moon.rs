@@ -1,67 +1,94 @@
 use crate::body::{Body, Mass, Radius};
+use crate::diagnostics::Diagnostics;
+use crate::integrator::{Integrator, IntegratorEuler, IntegratorWisdomHolman};
+use crate::softening::Softening;
+use crate::solver::{DirectSolver, ForceSolver};
 use crate::screen::Screen;
 use crate::scenario::Scenario;
 
-type Vec2 = nalgebra::Vector2<f64>;
+type Vec3 = nalgebra::Vector3<f64>;
+
+const SUN: usize = 0;
+const EARTH: usize = 1;
+const MOON: usize = 2;
+
+const G: f64 = 1.0;
 
 pub struct SunEarthMoon {
-    Sun: Body,
-    Earth: Body,
-    Moon: Body,
-    G: f64,
+    bodies: [Body; 3],
+    integrator: Box<dyn Integrator>,
+    solver: Box<dyn ForceSolver>,
 }
 
 impl SunEarthMoon {
     pub fn new() -> SunEarthMoon {
+        SunEarthMoon::with_integrator(Box::new(IntegratorEuler::new()))
+    }
+
+    pub fn with_integrator(integrator: Box<dyn Integrator>) -> SunEarthMoon {
+        SunEarthMoon::with_integrator_and_solver(integrator, Box::new(DirectSolver::new(G)))
+    }
+
+    pub fn with_integrator_and_solver(
+        integrator: Box<dyn Integrator>,
+        solver: Box<dyn ForceSolver>,
+    ) -> SunEarthMoon {
         let mut obj = SunEarthMoon {
-            Sun: Body::new(Mass(10000.0), Radius(7.0)),
-            Earth: Body::new(Mass(1000.0), Radius(2.0)),
-            Moon: Body::new(Mass(1.0), Radius(1.2)),
-            G: 1.0,
+            bodies: [
+                Body::new(Mass(10000.0), Radius(7.0)),
+                Body::new(Mass(1000.0), Radius(2.0)),
+                Body::new(Mass(1.0), Radius(1.2)),
+            ],
+            integrator,
+            solver,
         };
 
         let r = 5.5;
         let R = 30.0;
 
-        obj.Sun.pos = Vec2::new(0.0, 0.0);
-        obj.Sun.vel = Vec2::new(0.0, 0.0);
+        obj.bodies[SUN].pos = Vec3::new(0.0, 0.0, 0.0);
+        obj.bodies[SUN].vel = Vec3::new(0.0, 0.0, 0.0);
 
-        obj.Earth.pos = Vec2::new(R, 0.0);
-        obj.Earth.vel = Vec2::new(0.0, (obj.Sun.mass / R).sqrt());
+        obj.bodies[EARTH].pos = Vec3::new(R, 0.0, 0.0);
+        obj.bodies[EARTH].vel = Vec3::new(0.0, (obj.bodies[SUN].mass / R).sqrt(), 0.0);
 
-        obj.Moon.pos = Vec2::new(R, r);
-        obj.Moon.vel = Vec2::new((obj.Earth.mass / r).sqrt(), obj.Earth.vel.y);
+        obj.bodies[MOON].pos = Vec3::new(R, r, 0.0);
+        obj.bodies[MOON].vel = Vec3::new((obj.bodies[EARTH].mass / r).sqrt(), obj.bodies[EARTH].vel.y, 0.0);
 
         obj
     }
 
+    // Wisdom-Holman mapping around the sun, its kicks need the unsoftened pull of the sun
+    pub fn wisdom_holman() -> SunEarthMoon {
+        SunEarthMoon::with_integrator_and_solver(
+            Box::new(IntegratorWisdomHolman::new(G)),
+            Box::new(DirectSolver::with_softening(G, Softening::None)),
+        )
+    }
+
     fn plot_body(&self, renderer : &mut dyn Screen, body: Body) {
-        renderer.plot_circle(body.pos.x, body.pos.y, body.radius);
+        let (x, y) = renderer.project(&body.pos);
+        renderer.plot_circle(x, y, body.radius);
     }
 }
 impl Scenario for SunEarthMoon {
     fn process(&mut self, dt: f64) {
-        self.Moon.pull_by(&self.Earth, self.G);
-        self.Moon.pull_by(&self.Sun, self.G);
-        self.Earth.pull_by(&self.Moon, self.G);
-        self.Earth.pull_by(&self.Sun, self.G);
-        self.Sun.pull_by(&self.Moon, self.G);
-        self.Sun.pull_by(&self.Earth, self.G);
-
-        self.Moon.process_forces(dt);
-        self.Earth.process_forces(dt);
-        self.Sun.process_forces(dt);
+        self.integrator.integrate(dt, &mut self.bodies, self.solver.as_mut());
     }
 
     fn draw(&self, renderer : &mut dyn Screen) {
         renderer.clear();
-        renderer.position(self.Sun.pos.x, self.Sun.pos.y);
+        let (x, y) = renderer.project(&self.bodies[SUN].pos);
+        renderer.position(x, y);
 
-        self.plot_body(renderer, self.Moon);
-        self.plot_body(renderer, self.Earth);
-        self.plot_body(renderer, self.Sun);
+        self.plot_body(renderer, self.bodies[MOON]);
+        self.plot_body(renderer, self.bodies[EARTH]);
+        self.plot_body(renderer, self.bodies[SUN]);
 
         renderer.draw();
     }
-}
 
+    fn diagnostics(&mut self) -> Diagnostics {
+        Diagnostics::compute(&self.bodies, self.solver.as_mut())
+    }
+}
@@ -1,42 +1,95 @@
 use crate::body::{Body, Mass, Radius};
+use crate::diagnostics::Diagnostics;
+use crate::integrator::{Integrator, IntegratorEuler, IntegratorYoshida4};
+use crate::softening::Softening;
+use crate::solver::{DirectSolver, ForceSolver};
 use crate::screen::Screen;
 use crate::scenario::Scenario;
 
 type Vec2 = nalgebra::Vector2<f64>;
+type Vec3 = nalgebra::Vector3<f64>;
 
 pub struct ThreeBody {
     solarSystem: [Body; 3],
-    G: f64,
+    integrator: Box<dyn Integrator>,
+    solver: Box<dyn ForceSolver>,
 }
 
+const G: f64 = 1.0;
+
 impl ThreeBody {
     pub fn new() -> ThreeBody {
+        ThreeBody::with_integrator(Box::new(IntegratorEuler::new()))
+    }
+
+    pub fn with_integrator(integrator: Box<dyn Integrator>) -> ThreeBody {
+        // the figure-eight is a unit length orbit, any softening would distort it
+        let solver = Box::new(DirectSolver::with_softening(G, Softening::None));
+        ThreeBody::with_integrator_and_solver(integrator, solver)
+    }
+
+    pub fn with_integrator_and_solver(
+        integrator: Box<dyn Integrator>,
+        solver: Box<dyn ForceSolver>,
+    ) -> ThreeBody {
         let mut obj = ThreeBody {
             solarSystem: [
                 Body::new(Mass(1.0), Radius(0.1)),
                 Body::new(Mass(1.0), Radius(0.1)),
                 Body::new(Mass(1.0), Radius(0.1)),
             ],
-            G: 1.0,
+            integrator,
+            solver,
         };
 
-        obj.solarSystem[0].pos = Vec2::new(-0.9700436, 0.24308753);
-        obj.solarSystem[0].vel = Vec2::new(0.4662036850, 0.4323657300);
+        obj.solarSystem[0].pos = Vec3::new(-0.9700436, 0.24308753, 0.0);
+        obj.solarSystem[0].vel = Vec3::new(0.4662036850, 0.4323657300, 0.0);
 
-        obj.solarSystem[1].pos = Vec2::new(0.0, 0.0);
-        obj.solarSystem[1].vel = Vec2::new(-0.93240737, -0.86473146);
+        obj.solarSystem[1].pos = Vec3::new(0.0, 0.0, 0.0);
+        obj.solarSystem[1].vel = Vec3::new(-0.93240737, -0.86473146, 0.0);
 
-        obj.solarSystem[2].pos = Vec2::new(0.9700436, -0.24308753);
-        obj.solarSystem[2].vel = Vec2::new(0.4662036850, 0.4323657300);
+        obj.solarSystem[2].pos = Vec3::new(0.9700436, -0.24308753, 0.0);
+        obj.solarSystem[2].vel = Vec3::new(0.4662036850, 0.4323657300, 0.0);
 
         obj
     }
 
+    /*
+     * Hierarchical triple: an equal mass binary on a circular orbit in the x-y plane and a lighter
+     * third body circling it five separations away, its orbit tilted by inclination (radians) around
+     * the x axis. Inclinations roughly between 40 and 140 degrees make the inner eccentricity
+     * oscillate (Kozai-Lidov). Integrated with Yoshida, euler doesn't keep the binary bound for long.
+     */
+    pub fn inclined(inclination: f64) -> ThreeBody {
+        let solver = Box::new(DirectSolver::with_softening(G, Softening::None));
+        let mut obj = ThreeBody::with_integrator_and_solver(Box::new(IntegratorYoshida4::new()), solver);
+        obj.solarSystem[2].mass = 0.5;
+
+        let inner_mass = obj.solarSystem[0].mass + obj.solarSystem[1].mass;
+        let total_mass = inner_mass + obj.solarSystem[2].mass;
+        let inner_speed = (G * inner_mass / 1.0).sqrt();
+        let outer_speed = (G * total_mass / 5.0).sqrt();
+        let outer_direction = Vec3::new(0.0, inclination.cos(), inclination.sin());
+
+        // positions and velocities relative to the center of mass of the binary, then of the system
+        let binary_pos = Vec3::new(-5.0, 0.0, 0.0) * obj.solarSystem[2].mass / total_mass;
+        let binary_vel = -outer_speed * outer_direction * obj.solarSystem[2].mass / total_mass;
+        obj.solarSystem[0].pos = binary_pos + Vec3::new(0.0, -0.5, 0.0);
+        obj.solarSystem[0].vel = binary_vel + Vec3::new(0.5 * inner_speed, 0.0, 0.0);
+        obj.solarSystem[1].pos = binary_pos + Vec3::new(0.0, 0.5, 0.0);
+        obj.solarSystem[1].vel = binary_vel + Vec3::new(-0.5 * inner_speed, 0.0, 0.0);
+        obj.solarSystem[2].pos = Vec3::new(5.0, 0.0, 0.0) * inner_mass / total_mass;
+        obj.solarSystem[2].vel = outer_speed * outer_direction * inner_mass / total_mass;
+        obj
+    }
+
     fn plot_body(&self, renderer : &mut dyn Screen, body: Body) {
-        let O = body.pos;
-        let X = body.pos + 0.5 * body.vel;
+        let (x, y) = renderer.project(&body.pos);
+        let O = Vec2::new(x, y);
+        let (x, y) = renderer.project(&(body.pos + 0.5 * body.vel));
+        let X = Vec2::new(x, y);
 
-        renderer.plot_circle(body.pos.x, body.pos.y, body.radius);
+        renderer.plot_circle(O.x, O.y, body.radius);
         renderer.plot_line(O.x, O.y, X.x, X.y);
 
         let mut a = O - X;
@@ -53,18 +106,7 @@ impl ThreeBody {
 
 impl Scenario for ThreeBody {
     fn process(&mut self, dt : f64) {
-        for i in 0..(self.solarSystem.len()-1) {
-            for j in i+1..self.solarSystem.len() {
-                let (left, right) = self.solarSystem.split_at_mut(i+1);
-                let idx2 = j - i - 1;
-                left[i].pull_by(&right[idx2], self.G);
-                right[idx2].pull_by(&left[i], self.G);
-            }
-        }
-
-        for i in 0..self.solarSystem.len() {
-            self.solarSystem[i].process_forces(dt);
-        }
+        self.integrator.integrate(dt, &mut self.solarSystem, self.solver.as_mut());
     }
 
     fn draw(&self, renderer : &mut dyn Screen) {
@@ -74,5 +116,8 @@ impl Scenario for ThreeBody {
         }
         renderer.draw();
     }
-}
 
+    fn diagnostics(&mut self) -> Diagnostics {
+        Diagnostics::compute(&self.solarSystem, self.solver.as_mut())
+    }
+}
@@ -1,3 +1,5 @@
+type Vec3 = nalgebra::Vector3<f64>;
+
 pub trait Screen
 {
     fn clear(&mut self);
@@ -9,6 +11,11 @@ pub trait Screen
     fn set_zoom(&mut self, zoom : f64);
     fn draw(&mut self);
     fn set_palette(&mut self, palette : i32);
+
+    // world position on the screen plane, by default looking down the z axis
+    fn project(&self, pos : &Vec3) -> (f64, f64) {
+        (pos.x, pos.y)
+    }
 }
 
 pub trait TextOutputter {
@@ -1,7 +1,17 @@
+use crate::barnes_hut::GAMMA;
+use crate::diagnostics::DiagnosticsTracker;
+use crate::fmm::FastMultipole;
+use crate::integrator::{
+    IntegratorAdaptiveLeapfrog, IntegratorBlockLeapfrog, IntegratorBulirschStoer, IntegratorHermite,
+    IntegratorIAS15,
+    IntegratorLeapfrog, TimestepCriterion,
+};
+use crate::particle_mesh::ParticleMesh;
 use crate::scenario::*;
 use crate::screen::*;
 
 type Vec2 = nalgebra::Vector2<f64>;
+type Vec3 = nalgebra::Vector3<f64>;
 use crate::screen::Point;
 
 use eframe::{egui, epi};
@@ -10,6 +20,8 @@ pub struct EguiScreen {
     renderer: EguiRenderer,
     activeScenario: Box<dyn Scenario>,
     dt: f64,
+    diagnostics: DiagnosticsTracker,
+    show_diagnostics: bool,
 }
 
 impl Default for EguiScreen {
@@ -18,6 +30,8 @@ impl Default for EguiScreen {
             renderer: EguiRenderer::default(),
             activeScenario: Box::new(SunEarthMoon::new()), // TODO avoid duplication?
             dt: 1.0 / 100.0,
+            diagnostics: DiagnosticsTracker::new(),
+            show_diagnostics: false,
         };
 
         obj
@@ -33,31 +47,187 @@ impl epi::App for EguiScreen {
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             // The top panel is often a good place for a menu bar:
             ui.heading("Scenarios:");
+            let mut any_clicked = false;
             ui.horizontal(|ui| {
                 if ui.button("SunEarthMoon").clicked() {
+                    any_clicked = true;
                     self.renderer.set_zoom(5.0);
                     self.activeScenario = Box::new(SunEarthMoon::new());
                     self.dt = 1.0 / 100.0;
                 }
+                if ui.button("SunEarthMoon IAS15").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(5.0);
+                    self.activeScenario =
+                        Box::new(SunEarthMoon::with_integrator(Box::new(IntegratorIAS15::new())));
+                    self.dt = 1.0 / 100.0;
+                }
+                if ui.button("SunEarthMoon Wisdom-Holman").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(5.0);
+                    self.activeScenario = Box::new(SunEarthMoon::wisdom_holman());
+                    self.dt = 1.0 / 100.0;
+                }
                 if ui.button("Galaxy Collision").clicked() {
+                    any_clicked = true;
                     self.renderer.set_zoom(5.0);
                     self.activeScenario = Box::new(Collision::new(20000));
                     self.dt = 1.0 / 40.0;
                 }
+                if ui.button("Galaxy Collision Halos").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(5.0);
+                    self.activeScenario = Box::new(Collision::with_halos(20000));
+                    self.dt = 1.0 / 40.0;
+                }
+                if ui.button("Galaxy Collision Merging").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(5.0);
+                    self.activeScenario = Box::new(Collision::merging(20000));
+                    self.dt = 1.0 / 40.0;
+                }
+                if ui.button("Galaxy Collision Bounce").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(5.0);
+                    self.activeScenario = Box::new(Collision::bouncing(20000, 0.5));
+                    self.dt = 1.0 / 40.0;
+                }
                 if ui.button("Galaxy Collision BarnesHut").clicked() {
+                    any_clicked = true;
                     self.renderer.set_zoom(20.0);
                     self.activeScenario = Box::new(GalaxyCollisionBarnesHut::new());
                     self.dt = 100.0;
                 }
+                if ui.button("Galaxy Collision Inclined").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(20.0);
+                    self.activeScenario = Box::new(GalaxyCollisionBarnesHut::inclined(60f64.to_radians()));
+                    self.dt = 100.0;
+                }
+                if ui.button("Galaxy Collision Sinks").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(20.0);
+                    self.activeScenario = Box::new(GalaxyCollisionBarnesHut::with_sinks(0.2));
+                    self.dt = 100.0;
+                }
+                if ui.button("Galaxy Collision Post-Newtonian").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(20.0);
+                    self.activeScenario = Box::new(GalaxyCollisionBarnesHut::post_newtonian(1e-4));
+                    self.dt = 100.0;
+                }
+                if ui.button("Galaxy Collision Block Steps").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(20.0);
+                    self.activeScenario = Box::new(GalaxyCollisionBarnesHut::with_integrator(Box::new(
+                        IntegratorBlockLeapfrog::new(TimestepCriterion::Acceleration {
+                            eta: 0.025,
+                            length: 0.1,
+                        }),
+                    )));
+                    self.dt = 100.0;
+                }
+                if ui.button("Galaxy Collision PM").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(20.0);
+                    self.activeScenario = Box::new(GalaxyCollisionBarnesHut::with_integrator_and_solver(
+                        Box::new(IntegratorLeapfrog::new()),
+                        Box::new(ParticleMesh::new(GAMMA, 256)),
+                    ));
+                    self.dt = 100.0;
+                }
+                if ui.button("Galaxy Collision FMM").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(20.0);
+                    self.activeScenario = Box::new(GalaxyCollisionBarnesHut::with_integrator_and_solver(
+                        Box::new(IntegratorLeapfrog::new()),
+                        Box::new(FastMultipole::new(GAMMA)),
+                    ));
+                    self.dt = 100.0;
+                }
                 if ui.button("ThreeBody").clicked() {
+                    any_clicked = true;
                     self.renderer.set_zoom(200.0);
                     self.activeScenario = Box::new(ThreeBody::new());
                     self.dt = 1.0 / 100.0;
                 }
+                if ui.button("ThreeBody Adaptive").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(200.0);
+                    self.activeScenario = Box::new(ThreeBody::with_integrator(Box::new(
+                        IntegratorAdaptiveLeapfrog::new(TimestepCriterion::Jerk(0.01)),
+                    )));
+                    self.dt = 1.0 / 100.0;
+                }
+                if ui.button("ThreeBody Hermite").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(200.0);
+                    self.activeScenario =
+                        Box::new(ThreeBody::with_integrator(Box::new(IntegratorHermite::new())));
+                    self.dt = 1.0 / 100.0;
+                }
+                if ui.button("ThreeBody IAS15").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(200.0);
+                    self.activeScenario =
+                        Box::new(ThreeBody::with_integrator(Box::new(IntegratorIAS15::new())));
+                    self.dt = 1.0 / 100.0;
+                }
+                if ui.button("ThreeBody Bulirsch-Stoer").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(200.0);
+                    self.activeScenario = Box::new(ThreeBody::with_integrator(Box::new(
+                        IntegratorBulirschStoer::new(),
+                    )));
+                    self.dt = 1.0 / 100.0;
+                }
+                if ui.button("ThreeBody Inclined").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(50.0);
+                    self.activeScenario = Box::new(ThreeBody::inclined(60f64.to_radians()));
+                    self.dt = 1.0 / 100.0;
+                }
+                if ui.button("Protoplanets").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(5.0);
+                    self.activeScenario = Box::new(Protoplanets::new(400));
+                    self.dt = 1.0 / 100.0;
+                }
+                if ui.button("Gas Disc").clicked() {
+                    any_clicked = true;
+                    self.renderer.set_zoom(5.0);
+                    self.activeScenario = Box::new(GasDisc::new(2000));
+                    self.dt = 1.0 / 10.0;
+                }
             });
+            if any_clicked {
+                self.diagnostics.reset();
+            }
+
+            ui.add(egui::Slider::new(&mut self.renderer.tilt, 0.0..=180.0).text("view tilt (degrees)"));
+            ui.checkbox(&mut self.show_diagnostics, "Diagnostics");
+            if self.show_diagnostics {
+                if let Some(current) = self.diagnostics.current() {
+                    let drift = self.diagnostics.drift();
+                    ui.monospace(format!(
+                        "E={:.6e} K={:.6e} W={:.6e} virial={:.4}",
+                        current.total_energy(),
+                        current.kinetic_energy,
+                        current.potential_energy,
+                        current.virial_ratio()
+                    ));
+                    ui.monospace(format!(
+                        "drift: dE/E={:.3e} dP={:.3e} dL={:.3e}",
+                        drift.energy, drift.momentum, drift.angular_momentum
+                    ));
+                }
+            }
         });
 
         self.activeScenario.process(self.dt);
+        if self.show_diagnostics {
+            self.diagnostics.record(self.activeScenario.diagnostics());
+        }
 
         egui::CentralPanel::default().show(ctx, |ui| {
             let painter = egui::Painter::new(
@@ -83,6 +253,7 @@ struct EguiRenderer {
     height: i64,
     zoom: f64,
     center: Vec2,
+    tilt: f64, // rotation of the view around the x axis in degrees, 0 looks down the z axis
     shapes: Vec<egui::Shape>,
     default_color: egui::Color32,
     painter: Option<egui::Painter>,
@@ -95,6 +266,7 @@ impl Default for EguiRenderer {
             height: 600,
             zoom: 5.0, // TODO
             center: Vec2::new(0.0, 0.0),
+            tilt: 0.0,
             shapes: Default::default(),
             default_color: egui::Color32::GREEN,
             painter: Default::default(),
@@ -192,4 +364,10 @@ impl Screen for EguiRenderer {
     fn set_palette(&mut self, _palette: i32) {
         // TODO implement different colors?
     }
+
+    // orthographic, at 90 degrees discs in the x-y plane are seen edge-on
+    fn project(&self, pos: &Vec3) -> (f64, f64) {
+        let (sin, cos) = self.tilt.to_radians().sin_cos();
+        (pos.x, pos.y * cos + pos.z * sin)
+    }
 }
@@ -0,0 +1,121 @@
+/*
+ * Softening removes the singularity of the point mass force when two bodies get very close.
+ * Every kernel is given as factors for the force and the potential:
+ *     acc = G * m * force_factor * (other.pos - pos)
+ *     potential = -G * m * potential_factor
+ * so for a point mass force_factor = 1/r^3 and potential_factor = 1/r.
+ * Squared epsilons of both interacting particles are passed, only PerBody makes use of them.
+ */
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Softening {
+    // newtonian point masses
+    None,
+    // plummer sphere with given epsilon, force never becomes exactly newtonian
+    Plummer(f64),
+    // cubic spline kernel (Monaghan & Lattanzio 1985) as used in GADGET, force is exactly newtonian
+    // beyond h = 2.8 * epsilon, epsilon gives the same potential depth as plummer softening
+    Spline(f64),
+    // plummer softening with epsilon of each body, pair epsilon is sqrt((eps_i^2 + eps_j^2) / 2)
+    PerBody,
+}
+
+// h = SPLINE_RADIUS * epsilon
+const SPLINE_RADIUS: f64 = 2.8;
+
+impl Default for Softening {
+    fn default() -> Softening {
+        Softening::Plummer(0.1)
+    }
+}
+
+impl Softening {
+    pub fn force_factor(&self, dist2: f64, eps2_1: f64, eps2_2: f64) -> f64 {
+        match *self {
+            Softening::None => {
+                let dist = dist2.sqrt();
+                1.0 / (dist2 * dist)
+            }
+            Softening::Plummer(eps) => plummer_force(dist2, eps * eps),
+            Softening::PerBody => plummer_force(dist2, 0.5 * (eps2_1 + eps2_2)),
+            Softening::Spline(eps) => {
+                let h = SPLINE_RADIUS * eps;
+                let dist = dist2.sqrt();
+                if dist >= h {
+                    return 1.0 / (dist2 * dist);
+                }
+                let u = dist / h;
+                let h3 = h * h * h;
+                if u < 0.5 {
+                    (10.666666666667 + u * u * (32.0 * u - 38.4)) / h3
+                } else {
+                    (21.333333333333 - 48.0 * u + 38.4 * u * u
+                        - 10.666666666667 * u * u * u
+                        - 0.066666666667 / (u * u * u))
+                        / h3
+                }
+            }
+        }
+    }
+
+    // derivative of force_factor with respect to dist2, the jerk of a pair is
+    //     G * m * (force_factor * dv + 2 * force_factor_derivative * dot(dx, dv) * dx)
+    pub fn force_factor_derivative(&self, dist2: f64, eps2_1: f64, eps2_2: f64) -> f64 {
+        match *self {
+            Softening::None => {
+                let dist = dist2.sqrt();
+                -1.5 / (dist2 * dist2 * dist)
+            }
+            Softening::Plummer(eps) => plummer_force_derivative(dist2, eps * eps),
+            Softening::PerBody => plummer_force_derivative(dist2, 0.5 * (eps2_1 + eps2_2)),
+            Softening::Spline(eps) => {
+                let h = SPLINE_RADIUS * eps;
+                let dist = dist2.sqrt();
+                if dist >= h {
+                    return -1.5 / (dist2 * dist2 * dist);
+                }
+                let u = dist / h;
+                let h5 = h * h * h * h * h;
+                if u < 0.5 {
+                    (48.0 * u - 38.4) / h5
+                } else {
+                    (-48.0 + 76.8 * u - 32.0 * u * u + 0.2 / (u * u * u * u)) / (2.0 * u * h5)
+                }
+            }
+        }
+    }
+
+    pub fn potential_factor(&self, dist2: f64, eps2_1: f64, eps2_2: f64) -> f64 {
+        match *self {
+            Softening::None => 1.0 / dist2.sqrt(),
+            Softening::Plummer(eps) => 1.0 / (dist2 + eps * eps).sqrt(),
+            Softening::PerBody => 1.0 / (dist2 + 0.5 * (eps2_1 + eps2_2)).sqrt(),
+            Softening::Spline(eps) => {
+                let h = SPLINE_RADIUS * eps;
+                let dist = dist2.sqrt();
+                if dist >= h {
+                    return 1.0 / dist;
+                }
+                let u = dist / h;
+                if u < 0.5 {
+                    -(-2.8 + u * u * (5.333333333333 + u * u * (6.4 * u - 9.6))) / h
+                } else {
+                    -(-3.2
+                        + 0.066666666667 / u
+                        + u * u * (10.666666666667 + u * (-16.0 + u * (9.6 - 2.133333333333 * u))))
+                        / h
+                }
+            }
+        }
+    }
+}
+
+fn plummer_force(dist2: f64, eps2: f64) -> f64 {
+    let dist = (dist2 + eps2).sqrt();
+    1.0 / (dist * dist * dist)
+}
+
+fn plummer_force_derivative(dist2: f64, eps2: f64) -> f64 {
+    let dist = (dist2 + eps2).sqrt();
+    let dist2 = dist * dist;
+    -1.5 / (dist2 * dist2 * dist)
+}
@@ -0,0 +1,51 @@
+use crate::body::Body;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+mod direct;
+mod dynamical_friction;
+mod external;
+mod post_newtonian;
+mod restricted;
+
+pub use direct::DirectSolver;
+pub use dynamical_friction::{Background, DynamicalFriction};
+pub use external::ExternalField;
+pub use post_newtonian::PostNewtonian;
+pub use restricted::RestrictedSolver;
+
+/*
+ * A force solver evaluates gravity for a particle set. It has to overwrite `Body::acc` of every
+ * body in the slice with the acceleration caused by the rest of the set at the current positions.
+ * How the sum is computed (exactly, approximately, only from some of the bodies) is up to the solver,
+ * so the same scenario can be run with any of them.
+ * The potential energy has to follow the same approximation, otherwise energy drift would measure
+ * the solver and not the integrator.
+ */
+pub trait ForceSolver {
+    fn eval(&mut self, bodies: &mut [Body]);
+    fn potential_energy(&mut self, bodies: &[Body]) -> f64;
+
+    // accelerations of the active bodies like eval_active, returns their jerks (da/dt) in the
+    // order of active. Only solvers summing pairs exactly can provide them, the others return None
+    // and leave the bodies alone. Hermite integration then differentiates accelerations numerically
+    fn eval_jerk(&mut self, _bodies: &mut [Body], _active: &[usize]) -> Option<Vec<Vec3>> {
+        None
+    }
+
+    // like eval, but only `acc` of the bodies listed in active has to be updated, the others keep
+    // theirs. Block timesteps move few bodies at a time, solvers that can skip the rest override it
+    fn eval_active(&mut self, bodies: &mut [Body], active: &[usize]) {
+        let saved: Vec<Body> = bodies.to_vec();
+        self.eval(bodies);
+        let mut is_active = vec![false; bodies.len()];
+        for &index in active {
+            is_active[index] = true;
+        }
+        for (body, (old, active)) in bodies.iter_mut().zip(saved.iter().zip(is_active)) {
+            if !active {
+                body.acc = old.acc;
+            }
+        }
+    }
+}
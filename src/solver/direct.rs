@@ -0,0 +1,120 @@
+use crate::body::Body;
+use crate::softening::Softening;
+use crate::solver::ForceSolver;
+use rayon::prelude::*;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// below this many bodies the sums are cheaper than handing them to the thread pool
+const SERIAL_BELOW: usize = 64;
+
+// f of every index below count, in parallel only for large sets. Each f runs on a single thread,
+// so both paths give the same results
+fn map_indices<T: Send>(count: usize, bodies: usize, f: impl Fn(usize) -> T + Sync + Send) -> Vec<T> {
+    if bodies < SERIAL_BELOW {
+        (0..count).map(f).collect()
+    } else {
+        (0..count).into_par_iter().map(f).collect()
+    }
+}
+
+// every body is pulled by every other one, O(N^2)
+pub struct DirectSolver {
+    gravitational_constant: f64,
+    softening: Softening,
+}
+
+impl DirectSolver {
+    pub fn new(gravitational_constant: f64) -> Self {
+        Self::with_softening(gravitational_constant, Softening::default())
+    }
+
+    pub fn with_softening(gravitational_constant: f64, softening: Softening) -> Self {
+        Self {
+            gravitational_constant,
+            softening,
+        }
+    }
+}
+
+impl ForceSolver for DirectSolver {
+    // every body sums the pulls of all the others in index order on a single thread, results don't
+    // depend on the number of threads
+    fn eval(&mut self, bodies: &mut [Body]) {
+        let gravitational_constant = self.gravitational_constant;
+        let softening = self.softening;
+        let sources: &[Body] = bodies;
+        let accelerations: Vec<Vec3> = map_indices(sources.len(), sources.len(), |i| {
+            let mut acc = Vec3::new(0.0, 0.0, 0.0);
+            for (j, other) in sources.iter().enumerate() {
+                if i != j {
+                    acc += sources[i].compute_force(other, gravitational_constant, &softening);
+                }
+            }
+            acc
+        });
+        for (body, acc) in bodies.iter_mut().zip(accelerations) {
+            body.acc = acc;
+        }
+    }
+
+    // only the active bodies are summed, O(active * N)
+    fn eval_active(&mut self, bodies: &mut [Body], active: &[usize]) {
+        let gravitational_constant = self.gravitational_constant;
+        let softening = self.softening;
+        let sources: &[Body] = bodies;
+        let accelerations: Vec<Vec3> = map_indices(active.len(), sources.len(), |k| {
+            let i = active[k];
+            let mut acc = Vec3::new(0.0, 0.0, 0.0);
+            for (j, other) in sources.iter().enumerate() {
+                if i != j {
+                    acc += sources[i].compute_force(other, gravitational_constant, &softening);
+                }
+            }
+            acc
+        });
+        for (&i, acc) in active.iter().zip(accelerations) {
+            bodies[i].acc = acc;
+        }
+    }
+
+    fn eval_jerk(&mut self, bodies: &mut [Body], active: &[usize]) -> Option<Vec<Vec3>> {
+        let gravitational_constant = self.gravitational_constant;
+        let softening = self.softening;
+        let sources: &[Body] = bodies;
+        let derivatives: Vec<(Vec3, Vec3)> = map_indices(active.len(), sources.len(), |k| {
+            let i = active[k];
+            let mut acc = Vec3::new(0.0, 0.0, 0.0);
+            let mut jerk = Vec3::new(0.0, 0.0, 0.0);
+            for (j, other) in sources.iter().enumerate() {
+                if i != j {
+                    acc += sources[i].compute_force(other, gravitational_constant, &softening);
+                    jerk += sources[i].compute_jerk(other, gravitational_constant, &softening);
+                }
+            }
+            (acc, jerk)
+        });
+        let jerks = active
+            .iter()
+            .zip(derivatives)
+            .map(|(&i, (acc, jerk))| {
+                bodies[i].acc = acc;
+                jerk
+            })
+            .collect();
+        Some(jerks)
+    }
+
+    // partial sums per body are added up sequentially, in the same order for any number of threads
+    fn potential_energy(&mut self, bodies: &[Body]) -> f64 {
+        let gravitational_constant = self.gravitational_constant;
+        let softening = self.softening;
+        let energies: Vec<f64> = map_indices(bodies.len(), bodies.len(), |i| {
+            bodies[i + 1..]
+                .iter()
+                .map(|other| bodies[i].mass * bodies[i].compute_potential(other, gravitational_constant, &softening))
+                .sum::<f64>()
+        });
+        energies.iter().sum()
+    }
+}
@@ -0,0 +1,156 @@
+use crate::body::Body;
+use crate::potential::{Centre, ExternalPotential};
+use crate::solver::ForceSolver;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// the stars and dark matter a heavy body moves through
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Background {
+    // density of the profile, velocity dispersion circular speed / sqrt(2) as in an isothermal sphere
+    Profile(ExternalPotential),
+    Uniform { density: f64, dispersion: f64 },
+}
+
+impl Background {
+    fn density_and_dispersion(&self, offset: Vec3, gravitational_constant: f64) -> (f64, f64) {
+        match *self {
+            Background::Profile(profile) => {
+                let radius = offset.norm();
+                let dispersion = profile.circular_speed(radius, gravitational_constant) / 2f64.sqrt();
+                (profile.density(offset, gravitational_constant), dispersion)
+            }
+            Background::Uniform { density, dispersion } => (density, dispersion),
+        }
+    }
+}
+
+/*
+ * Chandrasekhar dynamical friction (Binney & Tremaine 8.7) on top of another solver. The first
+ * `heavy` bodies are slowed down by the wake they raise in every background they move through:
+ *     acc = -4 pi G^2 M rho ln(Lambda) / v^3 * (erf(X) - 2 X / sqrt(pi) exp(-X^2)) * v,  X = v / (sqrt(2) sigma)
+ * with v relative to the centre of the background, rho and sigma taken at the position of the body.
+ * A heavy body isn't dragged by a background attached to itself.
+ * Restricted simulations don't resolve the wake, with friction galaxy centres sink and merge.
+ * The drag depends on velocities and takes energy away, potential energy is the one of the wrapped solver.
+ */
+pub struct DynamicalFriction {
+    newtonian: Box<dyn ForceSolver>,
+    heavy: usize,
+    gravitational_constant: f64,
+    coulomb_logarithm: f64,
+    cutoff: f64,
+    backgrounds: Vec<(Background, Centre)>,
+}
+
+impl DynamicalFriction {
+    pub fn new(
+        newtonian: Box<dyn ForceSolver>,
+        heavy: usize,
+        gravitational_constant: f64,
+        coulomb_logarithm: f64,
+    ) -> Self {
+        Self {
+            newtonian,
+            heavy,
+            gravitational_constant,
+            coulomb_logarithm,
+            cutoff: 0.0,
+            backgrounds: Vec::new(),
+        }
+    }
+
+    // no drag closer than radius to the centre of a background, a body that got there has merged
+    // with it and cusps of the profiles would make the drag diverge
+    pub fn set_cutoff(&mut self, radius: f64) {
+        self.cutoff = radius;
+    }
+
+    pub fn add(&mut self, background: Background, centre: Centre) {
+        self.backgrounds.push((background, centre));
+    }
+
+    fn drag(&self, bodies: &[Body], index: usize) -> Vec3 {
+        let body = &bodies[index];
+        let mut acc = Vec3::new(0.0, 0.0, 0.0);
+        for (background, centre) in &self.backgrounds {
+            let (pos, vel) = match *centre {
+                Centre::Fixed(pos) => (pos, Vec3::new(0.0, 0.0, 0.0)),
+                Centre::Body(host) if host == index => continue,
+                Centre::Body(host) => (bodies[host].pos, bodies[host].vel),
+            };
+            let v = body.vel - vel;
+            let speed = v.norm();
+            if speed == 0.0 || (body.pos - pos).norm() < self.cutoff {
+                continue;
+            }
+            let (density, dispersion) =
+                background.density_and_dispersion(body.pos - pos, self.gravitational_constant);
+            let x = speed / (2f64.sqrt() * dispersion);
+            let fraction = slower_fraction(x);
+            let g = self.gravitational_constant;
+            acc -= 4.0 * std::f64::consts::PI * g * g * body.mass * density * self.coulomb_logarithm * fraction
+                / (speed * speed * speed)
+                * v;
+        }
+        acc
+    }
+
+    fn add_drag(&self, bodies: &mut [Body], active: impl Iterator<Item = usize>) {
+        for i in active {
+            if i < self.heavy {
+                let acc = self.drag(bodies, i);
+                bodies[i].acc += acc;
+            }
+        }
+    }
+}
+
+/*
+ * Fraction of the background slower than the body, erf(x) - 2 x / sqrt(pi) exp(-x^2). For slow
+ * bodies both terms cancel down to 4 x^3 / (3 sqrt(pi)) and any error of erf survives the
+ * subtraction, so below x = 2 it is summed as the series
+ *     2 / sqrt(pi) * sum_n (-1)^(n+1) 2n x^(2n+1) / (n! (2n+1))
+ * which keeps the drag linear in v down to v = 0.
+ */
+fn slower_fraction(x: f64) -> f64 {
+    if x >= 2.0 {
+        return erf(x) - 2.0 * x / std::f64::consts::PI.sqrt() * (-x * x).exp();
+    }
+    let mut power = x; // (-1)^n x^(2n+1) / n!
+    let mut sum = 0.0;
+    for n in 1..60 {
+        let n = n as f64;
+        power *= -x * x / n;
+        let term = -power * 2.0 * n / (2.0 * n + 1.0);
+        sum += term;
+        if term.abs() <= 1e-17 * sum.abs() {
+            break;
+        }
+    }
+    2.0 / std::f64::consts::PI.sqrt() * sum
+}
+
+// Abramowitz & Stegun 7.1.26, error below 1.5e-7
+fn erf(x: f64) -> f64 {
+    let t = 1.0 / (1.0 + 0.3275911 * x.abs());
+    let polynomial =
+        t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    (1.0 - polynomial * (-x * x).exp()).copysign(x)
+}
+
+impl ForceSolver for DynamicalFriction {
+    fn eval(&mut self, bodies: &mut [Body]) {
+        self.newtonian.eval(bodies);
+        self.add_drag(bodies, 0..self.heavy.min(bodies.len()));
+    }
+
+    fn eval_active(&mut self, bodies: &mut [Body], active: &[usize]) {
+        self.newtonian.eval_active(bodies, active);
+        self.add_drag(bodies, active.iter().copied());
+    }
+
+    fn potential_energy(&mut self, bodies: &[Body]) -> f64 {
+        self.newtonian.potential_energy(bodies)
+    }
+}
@@ -0,0 +1,103 @@
+use crate::body::Body;
+use crate::potential::{Centre, ExternalPotential};
+use crate::solver::ForceSolver;
+use rayon::prelude::*;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+/*
+ * Adds static external potentials to the accelerations of another solver, e.g. a dark matter halo
+ * around galaxy centres that are point masses in the particle set. Every body is pulled by every
+ * potential, except a body by the potential attached to it. Attached potentials don't pull back on
+ * their body, so momentum isn't conserved, and they change with time, so neither is energy.
+ */
+pub struct ExternalField {
+    newtonian: Box<dyn ForceSolver>,
+    gravitational_constant: f64,
+    potentials: Vec<(ExternalPotential, Centre)>,
+}
+
+impl ExternalField {
+    pub fn new(newtonian: Box<dyn ForceSolver>, gravitational_constant: f64) -> Self {
+        Self {
+            newtonian,
+            gravitational_constant,
+            potentials: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, potential: ExternalPotential, centre: Centre) {
+        self.potentials.push((potential, centre));
+    }
+
+    // centres of the potentials for the current positions, None for the body carrying a potential
+    fn centres(&self, bodies: &[Body]) -> Vec<(Vec3, Option<usize>)> {
+        self.potentials
+            .iter()
+            .map(|(_, centre)| match *centre {
+                Centre::Fixed(pos) => (pos, None),
+                Centre::Body(index) => (bodies[index].pos, Some(index)),
+            })
+            .collect()
+    }
+}
+
+// pull of all the potentials on body number index, apart from one attached to it
+fn acceleration(
+    potentials: &[(ExternalPotential, Centre)],
+    gravitational_constant: f64,
+    centres: &[(Vec3, Option<usize>)],
+    index: usize,
+    body: &Body,
+) -> Vec3 {
+    let mut acc = Vec3::new(0.0, 0.0, 0.0);
+    for ((potential, _), &(centre, host)) in potentials.iter().zip(centres) {
+        if host != Some(index) {
+            acc += potential.acceleration(body.pos - centre, gravitational_constant);
+        }
+    }
+    acc
+}
+
+impl ForceSolver for ExternalField {
+    fn eval(&mut self, bodies: &mut [Body]) {
+        self.newtonian.eval(bodies);
+        let centres = self.centres(bodies);
+        let potentials = &self.potentials;
+        let gravitational_constant = self.gravitational_constant;
+        bodies.par_iter_mut().enumerate().for_each(|(i, body)| {
+            body.acc += acceleration(potentials, gravitational_constant, &centres, i, body);
+        });
+    }
+
+    fn eval_active(&mut self, bodies: &mut [Body], active: &[usize]) {
+        self.newtonian.eval_active(bodies, active);
+        let centres = self.centres(bodies);
+        for &i in active {
+            let acc = acceleration(&self.potentials, self.gravitational_constant, &centres, i, &bodies[i]);
+            bodies[i].acc += acc;
+        }
+    }
+
+    // partial sums per body are added up sequentially, in the same order for any number of threads
+    fn potential_energy(&mut self, bodies: &[Body]) -> f64 {
+        let centres = self.centres(bodies);
+        let potentials = &self.potentials;
+        let gravitational_constant = self.gravitational_constant;
+        let energies: Vec<f64> = bodies
+            .par_iter()
+            .enumerate()
+            .map(|(i, body)| {
+                potentials
+                    .iter()
+                    .zip(&centres)
+                    .filter(|(_, &(_, host))| host != Some(i))
+                    .map(|((potential, _), &(centre, _))| {
+                        body.mass * potential.potential(body.pos - centre, gravitational_constant)
+                    })
+                    .sum::<f64>()
+            })
+            .collect();
+        self.newtonian.potential_energy(bodies) + energies.iter().sum::<f64>()
+    }
+}
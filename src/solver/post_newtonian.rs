@@ -0,0 +1,135 @@
+use crate::body::Body;
+use crate::solver::ForceSolver;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+/*
+ * Post-Newtonian corrections on top of any other solver, for black hole pairs and tight orbits.
+ * The first `relativistic` bodies get the two body terms of every pair among them added to the
+ * newtonian accelerations of the wrapped solver, all the other bodies stay newtonian.
+ * Relative acceleration of a pair in harmonic coordinates (Blanchet, Living Rev. Relativity 17, 2014,
+ * eq. 219), x = x_i - x_j, r = |x|, n = x / r, v = v_i - v_j, r' = n.v, M = m_i + m_j, eta = m_i m_j / M^2:
+ *     1PN:   G M / (c^2 r^2) * [((4 + 2 eta) G M / r - (1 + 3 eta) v^2 + 3/2 eta r'^2) n + (4 - 2 eta) r' v]
+ *     2.5PN: 8/5 eta (G M)^2 / (c^5 r^3) * [(3 v^2 + 17/3 G M / r) r' n - (v^2 + 3 G M / r) v]
+ * split between the two bodies as in their center of mass frame. 1PN makes orbits precess and has
+ * a conserved energy, 2.5PN is the radiation reaction, binaries lose energy and spiral in.
+ * Accelerations depend on velocities, integrators have to evaluate forces with positions and
+ * velocities of the same time (RK4, ABM6, IAS15, Bulirsch-Stoer). The terms aren't softened.
+ */
+pub struct PostNewtonian {
+    newtonian: Box<dyn ForceSolver>,
+    relativistic: usize,
+    gravitational_constant: f64,
+    speed_of_light: f64,
+    radiation_reaction: bool,
+}
+
+impl PostNewtonian {
+    // speed of light in the units of the scenario, 1PN only
+    pub fn new(
+        newtonian: Box<dyn ForceSolver>,
+        relativistic: usize,
+        gravitational_constant: f64,
+        speed_of_light: f64,
+    ) -> Self {
+        Self {
+            newtonian,
+            relativistic,
+            gravitational_constant,
+            speed_of_light,
+            radiation_reaction: false,
+        }
+    }
+
+    // adds the 2.5PN gravitational wave reaction
+    pub fn set_radiation_reaction(&mut self, enabled: bool) {
+        self.radiation_reaction = enabled;
+    }
+
+    // post-newtonian part of the acceleration of body relative to other
+    fn relative_acceleration(&self, body: &Body, other: &Body) -> Vec3 {
+        let gm = self.gravitational_constant * (body.mass + other.mass);
+        let eta = body.mass * other.mass / ((body.mass + other.mass) * (body.mass + other.mass));
+        let c2 = self.speed_of_light * self.speed_of_light;
+        let d = body.pos - other.pos;
+        let v = body.vel - other.vel;
+        let r = d.norm();
+        let n = d / r;
+        let rdot = n.dot(&v);
+        let v2 = v.norm_squared();
+
+        let mut acc = gm / (c2 * r * r)
+            * (((4.0 + 2.0 * eta) * gm / r - (1.0 + 3.0 * eta) * v2 + 1.5 * eta * rdot * rdot) * n
+                + (4.0 - 2.0 * eta) * rdot * v);
+        if self.radiation_reaction {
+            let c5 = c2 * c2 * self.speed_of_light;
+            acc += 1.6 * eta * gm * gm / (c5 * r * r * r)
+                * ((3.0 * v2 + 17.0 / 3.0 * gm / r) * rdot * n - (v2 + 3.0 * gm / r) * v);
+        }
+        acc
+    }
+
+    // 1PN energy of a pair less the newtonian one, in the center of mass frame of the pair
+    fn pair_energy(&self, body: &Body, other: &Body) -> f64 {
+        let mass = body.mass + other.mass;
+        let gm = self.gravitational_constant * mass;
+        let eta = body.mass * other.mass / (mass * mass);
+        let d = body.pos - other.pos;
+        let v = body.vel - other.vel;
+        let r = d.norm();
+        let rdot = d.dot(&v) / r;
+        let v2 = v.norm_squared();
+        let specific = 0.375 * (1.0 - 3.0 * eta) * v2 * v2
+            + 0.5 * gm / r * ((3.0 + eta) * v2 + eta * rdot * rdot + gm / r);
+        eta * mass * specific / (self.speed_of_light * self.speed_of_light)
+    }
+
+    fn add_corrections(&self, bodies: &mut [Body]) {
+        let relativistic = self.relativistic.min(bodies.len());
+        for i in 0..relativistic {
+            for j in i + 1..relativistic {
+                let acc = self.relative_acceleration(&bodies[i], &bodies[j]);
+                let mass = bodies[i].mass + bodies[j].mass;
+                let (mass_i, mass_j) = (bodies[i].mass, bodies[j].mass);
+                bodies[i].acc += mass_j / mass * acc;
+                bodies[j].acc -= mass_i / mass * acc;
+            }
+        }
+    }
+}
+
+impl ForceSolver for PostNewtonian {
+    fn eval(&mut self, bodies: &mut [Body]) {
+        self.newtonian.eval(bodies);
+        self.add_corrections(bodies);
+    }
+
+    // bodies outside active keep their acc, corrections of pairs with one of them are dropped again
+    fn eval_active(&mut self, bodies: &mut [Body], active: &[usize]) {
+        let saved: Vec<Vec3> = bodies.iter().map(|body| body.acc).collect();
+        self.newtonian.eval_active(bodies, active);
+        self.add_corrections(bodies);
+        let mut is_active = vec![false; bodies.len()];
+        for &index in active {
+            is_active[index] = true;
+        }
+        for (body, (acc, active)) in bodies.iter_mut().zip(saved.into_iter().zip(is_active)) {
+            if !active {
+                body.acc = acc;
+            }
+        }
+    }
+
+    // newtonian potential plus the 1PN energy of relativistic pairs, which depends on velocities too.
+    // The sum is conserved by 1PN orbits, radiation reaction takes energy away
+    fn potential_energy(&mut self, bodies: &[Body]) -> f64 {
+        let relativistic = self.relativistic.min(bodies.len());
+        let mut energy = self.newtonian.potential_energy(bodies);
+        for i in 0..relativistic {
+            for j in i + 1..relativistic {
+                energy += self.pair_energy(&bodies[i], &bodies[j]);
+            }
+        }
+        energy
+    }
+}
@@ -0,0 +1,103 @@
+use crate::body::Body;
+use crate::softening::Softening;
+use crate::solver::ForceSolver;
+use rayon::prelude::*;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// only the first `sources` bodies attract, all the others are massless test particles for them
+// O(N * sources), this is how the original galaxy collision was simulated
+pub struct RestrictedSolver {
+    sources: usize,
+    gravitational_constant: f64,
+    softening: Softening,
+}
+
+impl RestrictedSolver {
+    pub fn new(sources: usize, gravitational_constant: f64) -> Self {
+        Self::with_softening(sources, gravitational_constant, Softening::default())
+    }
+
+    pub fn with_softening(sources: usize, gravitational_constant: f64, softening: Softening) -> Self {
+        Self {
+            sources,
+            gravitational_constant,
+            softening,
+        }
+    }
+}
+
+impl ForceSolver for RestrictedSolver {
+    fn eval(&mut self, bodies: &mut [Body]) {
+        let sources = self.sources.min(bodies.len());
+        let (centres, stars) = bodies.split_at_mut(sources);
+
+        // centres attract each other
+        for i in 0..centres.len() {
+            centres[i].acc = Vec3::new(0.0, 0.0, 0.0);
+            for j in 0..centres.len() {
+                if i != j {
+                    let other = centres[j];
+                    centres[i].pull_by(&other, self.gravitational_constant, &self.softening);
+                }
+            }
+        }
+
+        // particles are attracted to centres, each one on its own so the number of threads doesn't
+        // change the result
+        let centres: &[Body] = centres;
+        let gravitational_constant = self.gravitational_constant;
+        let softening = self.softening;
+        stars.par_iter_mut().for_each(|star| {
+            star.acc = Vec3::new(0.0, 0.0, 0.0);
+            for centre in centres.iter() {
+                star.pull_by(centre, gravitational_constant, &softening);
+            }
+        });
+    }
+
+    // only the active bodies sum the pulls of the centres, in the same order as eval. Block timesteps
+    // of the galaxy collision move a few stars at a time, O(active * sources)
+    fn eval_active(&mut self, bodies: &mut [Body], active: &[usize]) {
+        let sources = self.sources.min(bodies.len());
+        let gravitational_constant = self.gravitational_constant;
+        let softening = self.softening;
+        let centres: &[Body] = &bodies[..sources];
+        let all: &[Body] = bodies;
+        let accelerations: Vec<Vec3> = active
+            .par_iter()
+            .map(|&i| {
+                let mut acc = Vec3::new(0.0, 0.0, 0.0);
+                for (j, centre) in centres.iter().enumerate() {
+                    if i != j {
+                        acc += all[i].compute_force(centre, gravitational_constant, &softening);
+                    }
+                }
+                acc
+            })
+            .collect();
+        for (&i, acc) in active.iter().zip(accelerations) {
+            bodies[i].acc = acc;
+        }
+    }
+
+    // test particles don't attract each other, so only pairs with a centre contribute
+    // partial sums per body are added up sequentially, in the same order for any number of threads
+    fn potential_energy(&mut self, bodies: &[Body]) -> f64 {
+        let sources = self.sources.min(bodies.len());
+        let energies: Vec<f64> = bodies
+            .par_iter()
+            .enumerate()
+            .map(|(j, body)| {
+                bodies[..sources.min(j)]
+                    .iter()
+                    .map(|centre| {
+                        body.mass
+                            * body.compute_potential(centre, self.gravitational_constant, &self.softening)
+                    })
+                    .sum::<f64>()
+            })
+            .collect();
+        energies.iter().sum()
+    }
+}
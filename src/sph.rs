@@ -0,0 +1,347 @@
+use crate::barnes_hut::{BarnesHutSimulation, Octree};
+use crate::body::Body;
+use crate::solver::ForceSolver;
+use rayon::prelude::*;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// smoothing length in units of the mean particle spacing (m / rho)^(1/3), about 58 neighbours
+// within the kernel support of 2 h
+const ETA: f64 = 1.2;
+// fixed point iterations of smoothing length and density per evaluation, more for the first one
+const ITERATIONS: usize = 3;
+const FIRST_ITERATIONS: usize = 20;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EquationOfState {
+    // P = c^2 rho, gas is kept at the temperature given by the sound speed c, heating is radiated away
+    Isothermal { sound_speed: f64 },
+    // P = A rho^gamma, the entropy A of every particle only grows by viscous heating
+    Adiabatic { gamma: f64 },
+}
+
+// cubic spline (Monaghan & Lattanzio 1985), support 2 h
+fn kernel(r: f64, h: f64) -> f64 {
+    let q = r / h;
+    let norm = 1.0 / (std::f64::consts::PI * h * h * h);
+    if q < 1.0 {
+        norm * (1.0 - 1.5 * q * q + 0.75 * q * q * q)
+    } else if q < 2.0 {
+        norm * 0.25 * (2.0 - q) * (2.0 - q) * (2.0 - q)
+    } else {
+        0.0
+    }
+}
+
+// dW/dr divided by r, the gradient of the kernel is that times the separation vector
+fn kernel_gradient(r: f64, h: f64) -> f64 {
+    let q = r / h;
+    let norm = 1.0 / (std::f64::consts::PI * h * h * h * h * h);
+    if q < 1.0 {
+        norm * (-3.0 + 2.25 * q)
+    } else if q < 2.0 {
+        -norm * 0.75 * (2.0 - q) * (2.0 - q) / q
+    } else {
+        0.0
+    }
+}
+
+// dW/dh
+fn kernel_derivative(r: f64, h: f64) -> f64 {
+    -(3.0 * kernel(r, h) + r * r * kernel_gradient(r, h)) / h
+}
+
+/*
+ * Smoothed particle hydrodynamics (Monaghan 1992, Springel & Hernquist 2002) on top of Barnes-Hut
+ * gravity. The first `gas` bodies are gas particles, all others are collisionless.
+ * Every evaluation
+ *   - lets the Barnes-Hut solver build its octree and compute gravity for all bodies,
+ *   - finds neighbours of gas particles in the same tree, nodes know the longest smoothing length
+ *     of their particles so every pair within 2 max(h_i, h_j) is found from both sides,
+ *   - adapts smoothing lengths to h = 1.2 (m / rho)^(1/3) together with densities
+ *     rho_i = sum_j m_j W(r_ij, h_i),
+ *   - adds pressure and viscosity accelerations, symmetric in i and j so momentum is conserved
+ *     a_i = -sum_j m_j (f_i P_i / rho_i^2 grad W(h_i) + f_j P_j / rho_j^2 grad W(h_j) + Pi_ij grad W_ij)
+ *     with W_ij = (W(h_i) + W(h_j)) / 2. The grad-h factors f_i = 1 / (1 + h_i / (3 rho_i) d rho_i / d h_i)
+ *     account for smoothing lengths changing with the positions, so energy is conserved as well,
+ *     with Monaghan viscosity Pi_ij = (-alpha c_ij mu_ij + beta mu_ij^2) / rho_ij for approaching pairs,
+ *     mu_ij = h_ij v_ij.r_ij / (r_ij^2 + 0.01 h_ij^2).
+ * Integrators only advance positions and velocities, the entropies of adiabatic gas are advanced by
+ * `advance_entropy(dt)` once per step. Without it shocks don't heat the gas.
+ * The potential energy includes the thermal energy of the gas, sum m u, which for isothermal gas
+ * is the free energy sum m c^2 ln(rho).
+ */
+pub struct Sph {
+    gravity: BarnesHutSimulation,
+    gas: usize,
+    equation_of_state: EquationOfState,
+    alpha: f64,
+    beta: f64,
+    smoothing: Vec<f64>,
+    density: Vec<f64>,
+    pressure: Vec<f64>,
+    sound_speed: Vec<f64>,
+    entropy: Vec<f64>,
+    entropy_rate: Vec<f64>,
+    correction: Vec<f64>, // grad-h factors f_i
+    internal_energy: Option<Vec<f64>>, // given by the user, turned into entropies at the next evaluation
+    node_radius: Vec<f64>,
+}
+
+impl Sph {
+    pub fn new(gravity: BarnesHutSimulation, gas: usize, equation_of_state: EquationOfState) -> Self {
+        Self {
+            gravity,
+            gas,
+            equation_of_state,
+            alpha: 1.0,
+            beta: 2.0,
+            smoothing: Vec::new(),
+            density: Vec::new(),
+            pressure: Vec::new(),
+            sound_speed: Vec::new(),
+            entropy: Vec::new(),
+            entropy_rate: Vec::new(),
+            correction: Vec::new(),
+            internal_energy: None,
+            node_radius: Vec::new(),
+        }
+    }
+
+    // strength of the linear and quadratic artificial viscosity terms, 1 and 2 by default
+    pub fn set_viscosity(&mut self, alpha: f64, beta: f64) {
+        self.alpha = alpha;
+        self.beta = beta;
+    }
+
+    // specific internal energy of every gas particle, for adiabatic gas
+    pub fn set_internal_energy(&mut self, energy: Vec<f64>) {
+        self.internal_energy = Some(energy);
+    }
+
+    pub fn densities(&self) -> &[f64] {
+        &self.density
+    }
+
+    pub fn smoothing_lengths(&self) -> &[f64] {
+        &self.smoothing
+    }
+
+    // specific internal energy at the last evaluation
+    pub fn internal_energies(&self) -> Vec<f64> {
+        (0..self.density.len()).map(|i| self.specific_energy(i, self.density[i])).collect()
+    }
+
+    // adds the viscous heating of the last evaluation to the entropies of adiabatic gas
+    pub fn advance_entropy(&mut self, dt: f64) {
+        for (entropy, rate) in self.entropy.iter_mut().zip(&self.entropy_rate) {
+            *entropy += dt * rate;
+        }
+    }
+
+    // adiabatic gas has no entropy before its first evaluation
+    fn specific_energy(&self, i: usize, density: f64) -> f64 {
+        match self.equation_of_state {
+            EquationOfState::Isothermal { sound_speed } => sound_speed * sound_speed * density.ln(),
+            EquationOfState::Adiabatic { gamma } => {
+                self.entropy.get(i).copied().unwrap_or(0.0) * density.powf(gamma - 1.0) / (gamma - 1.0)
+            }
+        }
+    }
+
+    // builds the tree and fills densities, pressures and sound speeds of the gas
+    fn update_gas(&mut self, bodies: &[Body]) {
+        let gas = self.gas.min(bodies.len());
+        let tree = self.gravity.tree();
+        self.smoothing = if self.smoothing.len() != gas {
+            adapt_smoothing(tree, bodies, gas, first_smoothing(tree, gas), FIRST_ITERATIONS)
+        } else {
+            adapt_smoothing(tree, bodies, gas, std::mem::take(&mut self.smoothing), ITERATIONS)
+        };
+        let (density, correction): (Vec<f64>, Vec<f64>) = densities(tree, bodies, gas, &self.smoothing)
+            .iter()
+            .zip(&self.smoothing)
+            .map(|(&(density, derivative), &h)| (density, correction(density, derivative, h)))
+            .unzip();
+        self.density = density;
+        self.correction = correction;
+
+        if let Some(energy) = self.internal_energy.take() {
+            if let EquationOfState::Adiabatic { gamma } = self.equation_of_state {
+                self.entropy = energy
+                    .iter()
+                    .zip(&self.density)
+                    .map(|(u, density)| (gamma - 1.0) * u / density.powf(gamma - 1.0))
+                    .collect();
+            }
+        }
+        if self.entropy.len() != gas {
+            self.entropy.resize(gas, 0.0);
+        }
+
+        let (pressure, sound_speed): (Vec<f64>, Vec<f64>) = (0..gas)
+            .map(|i| match self.equation_of_state {
+                EquationOfState::Isothermal { sound_speed } => {
+                    (sound_speed * sound_speed * self.density[i], sound_speed)
+                }
+                EquationOfState::Adiabatic { gamma } => {
+                    let pressure = self.entropy[i] * self.density[i].powf(gamma);
+                    (pressure, (gamma * pressure / self.density[i]).sqrt())
+                }
+            })
+            .unzip();
+        self.pressure = pressure;
+        self.sound_speed = sound_speed;
+
+        // longest search radius of the gas in every node, a node covers a contiguous range of keys
+        let smoothing = &self.smoothing;
+        self.node_radius.clear();
+        self.node_radius.par_extend(tree.nodes.par_iter().map(|node| {
+            tree.keys[node.particle_range()]
+                .iter()
+                .filter(|&&(_, body)| (body as usize) < gas)
+                .map(|&(_, body)| 2.0 * smoothing[body as usize])
+                .fold(0.0, f64::max)
+        }));
+    }
+
+    // pressure and viscosity accelerations and entropy rates of gas particle i
+    fn hydro(&self, bodies: &[Body], i: usize, found: &mut Vec<usize>) -> (Vec3, f64) {
+        let gas = self.smoothing.len();
+        let body = &bodies[i];
+        let h_i = self.smoothing[i];
+        let mut acc = Vec3::new(0.0, 0.0, 0.0);
+        let mut heating = 0.0;
+        self.gravity.tree().neighbours(body.pos, 2.0 * h_i, &self.node_radius, found);
+        for &j in found.iter() {
+            if j == i || j >= gas {
+                continue;
+            }
+            let other = &bodies[j];
+            let h_j = self.smoothing[j];
+            let d = body.pos - other.pos;
+            let r = d.norm();
+            if r >= 2.0 * h_i.max(h_j) {
+                continue;
+            }
+            let gradient_i = kernel_gradient(r, h_i) * d;
+            let gradient_j = kernel_gradient(r, h_j) * d;
+            let gradient = 0.5 * (gradient_i + gradient_j);
+            let v = body.vel - other.vel;
+            let approach = v.dot(&d);
+            let viscosity = if approach < 0.0 {
+                let h = 0.5 * (h_i + h_j);
+                let mu = h * approach / (r * r + 0.01 * h * h);
+                let c = 0.5 * (self.sound_speed[i] + self.sound_speed[j]);
+                let density = 0.5 * (self.density[i] + self.density[j]);
+                (-self.alpha * c * mu + self.beta * mu * mu) / density
+            } else {
+                0.0
+            };
+            let pressure_i = self.correction[i] * self.pressure[i] / (self.density[i] * self.density[i]);
+            let pressure_j = self.correction[j] * self.pressure[j] / (self.density[j] * self.density[j]);
+            acc -= other.mass * (pressure_i * gradient_i + pressure_j * gradient_j + viscosity * gradient);
+            heating += 0.5 * other.mass * viscosity * v.dot(&gradient);
+        }
+        let rate = match self.equation_of_state {
+            EquationOfState::Isothermal { .. } => 0.0,
+            EquationOfState::Adiabatic { gamma } => (gamma - 1.0) / self.density[i].powf(gamma - 1.0) * heating,
+        };
+        (acc, rate)
+    }
+}
+
+// grad-h factor, left out as in GADGET-2 for particles with hardly any neighbours, where the density
+// comes mostly from the particle itself and the factor diverges
+fn correction(density: f64, derivative: f64, h: f64) -> f64 {
+    let term = h / (3.0 * density) * derivative;
+    if term > -0.9 {
+        1.0 / (1.0 + term)
+    } else {
+        1.0
+    }
+}
+
+// mean spacing of the gas in the volume of the tree, where the smoothing lengths start
+fn first_smoothing(tree: &Octree, gas: usize) -> Vec<f64> {
+    let size = tree
+        .nodes
+        .first()
+        .map_or(1.0, |root| (root.pos_upper_bound - root.pos_lower_bound).max());
+    vec![ETA * size / (gas.max(1) as f64).cbrt(); gas]
+}
+
+// fixed point iterations of smoothing lengths and densities
+fn adapt_smoothing(tree: &Octree, bodies: &[Body], gas: usize, mut smoothing: Vec<f64>, iterations: usize) -> Vec<f64> {
+    for _ in 0..iterations {
+        smoothing = densities(tree, bodies, gas, &smoothing)
+            .iter()
+            .zip(&bodies[..gas])
+            .map(|(&(density, _), body)| ETA * (body.mass / density).cbrt())
+            .collect();
+    }
+    smoothing
+}
+
+// gather densities of the gas with given smoothing lengths, and their derivatives by h
+fn densities(tree: &Octree, bodies: &[Body], gas: usize, smoothing: &[f64]) -> Vec<(f64, f64)> {
+    (0..gas)
+        .into_par_iter()
+        .map_init(Vec::new, |found, i| {
+            let h = smoothing[i];
+            tree.neighbours(bodies[i].pos, 2.0 * h, &[], found);
+            found.iter().filter(|&&j| j < gas).fold((0.0, 0.0), |(density, derivative), &j| {
+                let r = (bodies[j].pos - bodies[i].pos).norm();
+                (density + bodies[j].mass * kernel(r, h), derivative + bodies[j].mass * kernel_derivative(r, h))
+            })
+        })
+        .collect()
+}
+
+impl ForceSolver for Sph {
+    // every gas particle sums its neighbours on a single thread in tree order, results don't depend
+    // on the number of threads
+    fn eval(&mut self, bodies: &mut [Body]) {
+        self.gravity.eval(bodies);
+        self.update_gas(bodies);
+
+        let sph = &*self;
+        let sources: &[Body] = bodies;
+        let hydro: Vec<(Vec3, f64)> = (0..self.smoothing.len())
+            .into_par_iter()
+            .map_init(Vec::new, |found, i| sph.hydro(sources, i, found))
+            .collect();
+        self.entropy_rate.clear();
+        for (body, (acc, rate)) in bodies.iter_mut().zip(hydro) {
+            body.acc += acc;
+            self.entropy_rate.push(rate);
+        }
+    }
+
+    // gravity and thermal energy, densities for the given positions with the last smoothing lengths.
+    // Before the first evaluation smoothing lengths are adapted here without keeping them, and internal
+    // energies that are not entropies yet count as they are, so no gas state changes
+    fn potential_energy(&mut self, bodies: &[Body]) -> f64 {
+        let gravity = self.gravity.potential_energy(bodies);
+        let gas = self.gas.min(bodies.len());
+        let tree = self.gravity.tree();
+        let first;
+        let smoothing = if self.smoothing.len() == gas {
+            &self.smoothing
+        } else {
+            first = adapt_smoothing(tree, bodies, gas, first_smoothing(tree, gas), FIRST_ITERATIONS);
+            &first
+        };
+        let density = densities(tree, bodies, gas, smoothing);
+        let thermal: f64 = (0..gas)
+            .map(|i| {
+                let energy = match (&self.internal_energy, self.equation_of_state) {
+                    (Some(energy), EquationOfState::Adiabatic { .. }) => energy[i],
+                    _ => self.specific_energy(i, density[i].0),
+                };
+                bodies[i].mass * energy
+            })
+            .sum();
+        gravity + thermal
+    }
+}
@@ -0,0 +1,260 @@
+use galaxyy::barnes_hut::{BarnesHutSimulation, OpeningCriterion};
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::softening::Softening;
+use galaxyy::solver::{DirectSolver, ForceSolver};
+use rand::{Rng, SeedableRng};
+
+type Vec3 = nalgebra::Vector3<f64>;
+type Criterion = fn(f64) -> OpeningCriterion;
+
+// Plummer sphere-ish cluster of unequal masses, seeded so every run sees the same bodies
+fn cluster(count: usize) -> Vec<Body> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+    (0..count)
+        .map(|_| {
+            let mut body = Body::new(Mass(rng.gen_range(0.5..2.0)), Radius(0.0));
+            let r = 1.0 / (rng.gen_range(0.01..1.0f64).powf(-2.0 / 3.0) - 1.0).sqrt();
+            let direction = Vec3::new(
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+                rng.gen_range(-1.0..1.0),
+            );
+            body.pos = r.min(10.0) * direction.normalize();
+            body
+        })
+        .collect()
+}
+
+// accelerations of the solver next to direct summation
+fn evaluate(solver: &mut dyn ForceSolver, bodies: &[Body]) -> Vec<Vec3> {
+    let mut bodies = bodies.to_vec();
+    solver.eval(&mut bodies);
+    bodies.iter().map(|body| body.acc).collect()
+}
+
+// median and largest relative error of the accelerations
+fn errors(approximate: &[Vec3], exact: &[Vec3]) -> (f64, f64) {
+    let mut errors: Vec<f64> = approximate
+        .iter()
+        .zip(exact)
+        .map(|(approximate, exact)| (approximate - exact).norm() / exact.norm())
+        .collect();
+    errors.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    (errors[errors.len() / 2], errors[errors.len() - 1])
+}
+
+fn direct(bodies: &[Body], softening: Softening) -> Vec<Vec3> {
+    evaluate(&mut DirectSolver::with_softening(1.0, softening), bodies)
+}
+
+// the default theta of 0.9 is tuned for speed, at 0.3 the tree is close to exact
+#[test]
+fn tree_forces_match_direct_summation() {
+    let bodies = cluster(1000);
+    for &softening in &[
+        Softening::None,
+        Softening::Plummer(0.1),
+        Softening::Spline(0.1),
+    ] {
+        let exact = direct(&bodies, softening);
+        let mut solver = BarnesHutSimulation::with_softening(1.0, softening);
+        let (median, largest) = errors(&evaluate(&mut solver, &bodies), &exact);
+        assert!(
+            median < 3e-2 && largest < 0.5,
+            "{:?}: median error {:e}, largest {:e}",
+            softening,
+            median,
+            largest
+        );
+
+        solver.set_opening_criterion(OpeningCriterion::Geometric(0.3));
+        let (median, largest) = errors(&evaluate(&mut solver, &bodies), &exact);
+        assert!(
+            median < 2e-3 && largest < 3e-2,
+            "{:?}: median error {:e}, largest {:e}",
+            softening,
+            median,
+            largest
+        );
+    }
+}
+
+fn at(x: f64, y: f64, z: f64) -> Body {
+    let mut body = Body::new(Mass(1.0), Radius(0.0));
+    body.pos = Vec3::new(x, y, z);
+    body
+}
+
+/*
+ * The root cube spans -0.025..1.025 and splits at 0.5, four corners get an octant each. The bodies at
+ * 0.9 and 1 stay together in halves of the upper octant until its mid point 0.959 separates them at
+ * depth 4: root, 4 + 1 + 1 + 2 nodes below it, 3 + 2 leaves. Two bodies at the same position never
+ * separate, they share a leaf at the maximum depth of 21 and add a node on every level down to it.
+ */
+#[test]
+fn tree_stats_of_hand_placed_bodies() {
+    let mut bodies = vec![
+        at(0.0, 0.0, 0.0),
+        at(1.0, 0.0, 0.0),
+        at(0.0, 1.0, 0.0),
+        at(1.0, 1.0, 1.0),
+        at(0.9, 0.9, 0.9),
+        at(f64::NAN, 0.0, 0.0),
+    ];
+    let mut solver = BarnesHutSimulation::new(1.0);
+    solver.eval(&mut bodies);
+    let stats = solver.stats();
+    assert_eq!(
+        (stats.inserted, stats.coincident, stats.out_of_bounds),
+        (5, 0, 1)
+    );
+    assert_eq!(
+        (stats.depth, stats.nodes, stats.leaves),
+        (4, 9, 5),
+        "{:?}",
+        stats
+    );
+
+    bodies.push(at(0.0, 0.0, 0.0));
+    solver.eval(&mut bodies);
+    let stats = solver.stats();
+    assert_eq!(
+        (stats.inserted, stats.coincident, stats.out_of_bounds),
+        (5, 1, 1)
+    );
+    assert_eq!(
+        (stats.depth, stats.nodes, stats.leaves),
+        (21, 9 + 20, 5),
+        "{:?}",
+        stats
+    );
+}
+
+// a massless body has a leaf of its own, without a mass center. Test particles and the nodes above them
+// stay finite for every criterion and the forces still match direct summation
+#[test]
+fn massless_bodies_keep_the_tree_finite() {
+    let mut bodies = cluster(200);
+    bodies[7].mass = 0.0;
+    bodies[100].mass = 0.0;
+    let exact = direct(&bodies, Softening::Plummer(0.1));
+    let criteria = [
+        OpeningCriterion::Geometric(0.5),
+        OpeningCriterion::MinimumDistance(0.5),
+        OpeningCriterion::RelativeAcceleration(1e-3),
+    ];
+    for &criterion in criteria.iter() {
+        for &quadrupole in &[false, true] {
+            let mut solver = BarnesHutSimulation::with_softening(1.0, Softening::Plummer(0.1));
+            solver.set_opening_criterion(criterion);
+            solver.set_quadrupole(quadrupole);
+            let accelerations = evaluate(&mut solver, &bodies);
+            assert!(
+                solver.center.iter().all(|v| v.is_finite()),
+                "{:?}: centre {:?}",
+                criterion,
+                solver.center
+            );
+            assert!(
+                solver.potential_energy(&bodies).is_finite(),
+                "{:?}",
+                criterion
+            );
+            let (median, _) = errors(&accelerations, &exact);
+            assert!(
+                median < 2e-2,
+                "{:?}, quadrupole {}: median error {:e}",
+                criterion,
+                quadrupole,
+                median
+            );
+        }
+    }
+
+    let mut bodies = vec![at(0.0, 0.0, 0.0), at(1.0, 1.0, 1.0), at(0.9, 0.9, 0.9)];
+    bodies[2].mass = 0.0;
+    let mut solver = BarnesHutSimulation::new(1.0);
+    solver.eval(&mut bodies);
+    assert_eq!(solver.center, Vec3::new(0.5, 0.5, 0.5));
+    assert!(
+        bodies
+            .iter()
+            .all(|body| body.acc.iter().all(|v| v.is_finite())),
+        "{:?}",
+        bodies
+    );
+}
+
+// median and largest error of the tree against direct summation. The relative acceleration criterion
+// needs the accelerations of a previous evaluation, the first one falls back to a geometric theta
+fn criterion_errors(
+    criterion: OpeningCriterion,
+    quadrupole: bool,
+    bodies: &[Body],
+    exact: &[Vec3],
+) -> (f64, f64) {
+    let mut solver = BarnesHutSimulation::with_softening(1.0, Softening::Plummer(0.1));
+    solver.set_opening_criterion(criterion);
+    solver.set_quadrupole(quadrupole);
+    let mut bodies = bodies.to_vec();
+    solver.eval(&mut bodies);
+    errors(&evaluate(&mut solver, &bodies), exact)
+}
+
+// tighter opening angles and tolerances open more nodes, the force error falls for every criterion
+#[test]
+fn errors_fall_as_criteria_tighten() {
+    let bodies = cluster(500);
+    let exact = direct(&bodies, Softening::Plummer(0.1));
+    let criteria: [(&str, Criterion, [f64; 3]); 3] = [
+        ("geometric", OpeningCriterion::Geometric, [0.9, 0.6, 0.3]),
+        (
+            "minimum distance",
+            OpeningCriterion::MinimumDistance,
+            [0.9, 0.6, 0.3],
+        ),
+        (
+            "relative acceleration",
+            OpeningCriterion::RelativeAcceleration,
+            [1e-2, 1e-3, 1e-4],
+        ),
+    ];
+    for (name, criterion, parameters) in criteria.iter() {
+        let errors: Vec<(f64, f64)> = parameters
+            .iter()
+            .map(|&parameter| criterion_errors(criterion(parameter), false, &bodies, &exact))
+            .collect();
+        for (coarse, fine) in errors.iter().zip(&errors[1..]) {
+            assert!(
+                fine.0 < 0.5 * coarse.0 && fine.1 < coarse.1,
+                "{}: median and largest error {:e}, {:e} after {:e}, {:e}",
+                name,
+                fine.0,
+                fine.1,
+                coarse.0,
+                coarse.1
+            );
+        }
+    }
+}
+
+// at the same opening angle quadrupole moments roughly halve the median error of monopoles
+#[test]
+fn quadrupoles_are_more_accurate_than_monopoles() {
+    let bodies = cluster(500);
+    let exact = direct(&bodies, Softening::Plummer(0.1));
+    for &theta in &[0.3, 0.5, 0.7, 0.9] {
+        let criterion = OpeningCriterion::Geometric(theta);
+        let (monopole, monopole_largest) = criterion_errors(criterion, false, &bodies, &exact);
+        let (quadrupole, quadrupole_largest) = criterion_errors(criterion, true, &bodies, &exact);
+        assert!(
+            quadrupole < 0.6 * monopole && quadrupole_largest < monopole_largest,
+            "theta {}: median and largest error {:e}, {:e} with quadrupoles, {:e}, {:e} without",
+            theta,
+            quadrupole,
+            quadrupole_largest,
+            monopole,
+            monopole_largest
+        );
+    }
+}
@@ -0,0 +1,104 @@
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::contact::{Bounce, ContactHandler, SpatialGrid};
+use galaxyy::scenario::{Collision, Scenario};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+fn body(mass: f64, radius: f64, pos: Vec3, vel: Vec3) -> Body {
+    let mut body = Body::new(Mass(mass), Radius(radius));
+    body.pos = pos;
+    body.vel = vel;
+    body
+}
+
+fn momentum(bodies: &[Body]) -> Vec3 {
+    bodies.iter().map(|body| body.mass * body.vel).sum()
+}
+
+fn kinetic_energy(bodies: &[Body]) -> f64 {
+    bodies.iter().map(|body| 0.5 * body.mass * body.vel.norm_squared()).sum()
+}
+
+// off centre impact, the line of centres isn't along the relative velocity
+fn impact() -> Vec<Body> {
+    vec![
+        body(1.0, 1.0, Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.2, 0.0)),
+        body(3.0, 0.5, Vec3::new(1.2, 0.6, 0.3), Vec3::new(-0.5, 0.0, 0.1)),
+    ]
+}
+
+#[test]
+fn elastic_impact_conserves_momentum_and_energy() {
+    let mut bodies = impact();
+    let (momentum_before, energy_before) = (momentum(&bodies), kinetic_energy(&bodies));
+    let mut bounce = Bounce::new(1.0);
+    assert!(bounce.resolve(&mut bodies, 0.0));
+    assert_eq!(bounce.collisions(), 1);
+    assert!((momentum(&bodies) - momentum_before).norm() < 1e-14);
+    assert!((kinetic_energy(&bodies) - energy_before).abs() < 1e-14 * energy_before);
+    // the overlap is gone and the pair separates
+    let normal = (bodies[1].pos - bodies[0].pos).normalize();
+    assert!((bodies[1].pos - bodies[0].pos).norm() >= 1.5 - 1e-12);
+    assert!((bodies[1].vel - bodies[0].vel).dot(&normal) > 0.0);
+}
+
+// perfectly inelastic, the pair leaves with the same velocity along the line of centres
+#[test]
+fn inelastic_impact_removes_normal_velocity() {
+    let mut bodies = impact();
+    let normal = (bodies[1].pos - bodies[0].pos).normalize();
+    let tangential = |bodies: &[Body]| (bodies[1].vel - bodies[0].vel) - (bodies[1].vel - bodies[0].vel).dot(&normal) * normal;
+    let (momentum_before, tangential_before) = (momentum(&bodies), tangential(&bodies));
+    Bounce::new(0.0).resolve(&mut bodies, 0.0);
+    assert!((momentum(&bodies) - momentum_before).norm() < 1e-14);
+    assert!((bodies[1].vel - bodies[0].vel).dot(&normal).abs() < 1e-14);
+    assert!((tangential(&bodies) - tangential_before).norm() < 1e-14, "sliding isn't damped");
+}
+
+// galaxy centres are far larger than the grid cells sized by the stars, they are checked against
+// every body instead of filling thousands of cells
+#[test]
+fn grid_finds_pairs_with_large_bodies() {
+    let mut bodies: Vec<Body> =
+        (0..100).map(|i| body(1.0, 0.1, Vec3::new(0.15 * i as f64, 0.0, 0.0), Vec3::zeros())).collect();
+    bodies.push(body(100.0, 2.0, Vec3::new(0.0, 1.0, 0.0), Vec3::zeros()));
+    bodies.push(body(100.0, 3.0, Vec3::new(4.0, 0.0, 0.0), Vec3::zeros()));
+    let mut grid = SpatialGrid::new();
+    grid.build(&bodies);
+    let pairs = grid.overlapping_pairs(&bodies);
+
+    let mut expected = Vec::new();
+    for i in 0..bodies.len() {
+        for j in i + 1..bodies.len() {
+            let reach = bodies[i].radius + bodies[j].radius;
+            if (bodies[i].pos - bodies[j].pos).norm() < reach {
+                expected.push((i, j));
+            }
+        }
+    }
+    assert_eq!(pairs, expected);
+}
+
+// the 40000 stars of the galaxy collision start packed around the centres, bouncing pushes them
+// apart while without contacts they keep passing through each other
+#[test]
+fn galaxy_collision_bounces() {
+    let overlapping = |scenario: &Collision| {
+        let mut grid = SpatialGrid::new();
+        grid.build(scenario.bodies());
+        grid.overlapping_pairs(scenario.bodies()).len()
+    };
+    let mut bouncing = Collision::bouncing(20000, 0.5);
+    let mut passing = Collision::new(20000);
+    for _ in 0..20 {
+        bouncing.process(1.0 / 40.0);
+        passing.process(1.0 / 40.0);
+    }
+    assert_eq!(bouncing.bodies().len(), 40002);
+    assert!(
+        4 * overlapping(&bouncing) < overlapping(&passing),
+        "{} pairs overlap with bounces, {} without",
+        overlapping(&bouncing),
+        overlapping(&passing)
+    );
+}
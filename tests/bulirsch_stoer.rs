@@ -0,0 +1,67 @@
+mod common;
+
+use common::newtonian;
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::diagnostics::Diagnostics;
+use galaxyy::integrator::{Integrator, IntegratorBulirschStoer};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// Chenciner & Montgomery, period 6.3259
+fn figure_eight() -> Vec<Body> {
+    let mut bodies = vec![Body::new(Mass(1.0), Radius(0.0)); 3];
+    bodies[0].pos = Vec3::new(-0.97000436, 0.24308753, 0.0);
+    bodies[0].vel = Vec3::new(0.4662036850, 0.4323657300, 0.0);
+    bodies[1].vel = Vec3::new(-0.93240737, -0.86473146, 0.0);
+    bodies[2].pos = Vec3::new(0.97000436, -0.24308753, 0.0);
+    bodies[2].vel = Vec3::new(0.4662036850, 0.4323657300, 0.0);
+    bodies
+}
+
+// Burrau's problem: masses 3, 4 and 5 at rest on the corners of a 3-4-5 triangle
+fn pythagorean() -> Vec<Body> {
+    let mut bodies = vec![
+        Body::new(Mass(3.0), Radius(0.0)),
+        Body::new(Mass(4.0), Radius(0.0)),
+        Body::new(Mass(5.0), Radius(0.0)),
+    ];
+    bodies[0].pos = Vec3::new(1.0, 3.0, 0.0);
+    bodies[1].pos = Vec3::new(-2.0, -1.0, 0.0);
+    bodies[2].pos = Vec3::new(1.0, -1.0, 0.0);
+    bodies
+}
+
+#[test]
+fn figure_eight_closes_after_one_period() {
+    let mut bodies = figure_eight();
+    let start: Vec<Vec3> = bodies.iter().map(|body| body.pos).collect();
+    let mut integrator = IntegratorBulirschStoer::with_tolerance(1e-12);
+    integrator.integrate(6.32591398, &mut bodies, &mut newtonian());
+    for (body, start) in bodies.iter().zip(&start) {
+        let error = (body.pos - start).norm();
+        assert!(error < 1e-6, "position error {:e}", error);
+    }
+}
+
+// the close encounters around t = 16, 46 and 59 need steps a thousand times shorter than the rest,
+// local errors of 1e-12 add up to an energy error of about 1e-7 by t = 70
+#[test]
+fn pythagorean_energy_and_step_errors() {
+    let mut bodies = pythagorean();
+    let mut solver = newtonian();
+    let initial = Diagnostics::compute(&bodies, &mut solver).total_energy();
+    let tolerance = 1e-12;
+    let mut integrator = IntegratorBulirschStoer::with_tolerance(tolerance);
+    let mut steps = 0;
+    for _ in 0..700 {
+        integrator.integrate(0.1, &mut bodies, &mut solver);
+        steps += integrator.errors().len();
+        for &error in integrator.errors() {
+            assert!(error <= tolerance, "step error {:e}", error);
+        }
+    }
+    let energy = Diagnostics::compute(&bodies, &mut solver).total_energy();
+    let drift = ((energy - initial) / initial).abs();
+    assert!(drift < 1e-6, "energy error {:e}", drift);
+    assert!(steps > 700, "{} steps", steps);
+}
@@ -0,0 +1,35 @@
+use galaxyy::body::Body;
+use galaxyy::contact::{ContactHandler, Merger};
+use galaxyy::scenario::{Collision, Scenario};
+
+// throws away the first body, like an unprotected handler absorbing a centre into the other one
+struct DropFirst;
+
+impl ContactHandler for DropFirst {
+    fn resolve(&mut self, bodies: &mut Vec<Body>, _time: f64) -> bool {
+        bodies.remove(0);
+        true
+    }
+}
+
+// stars merge into the centres and each other, the centres stay the first two bodies
+#[test]
+fn protected_merger_keeps_the_centres() {
+    let mut scenario = Collision::new(2000);
+    let centres = [scenario.bodies()[0].mass, scenario.bodies()[1].mass];
+    scenario.set_contact_handler(Box::new(Merger::with_protected(2)));
+    for _ in 0..20 {
+        scenario.process(0.01);
+    }
+    assert!(scenario.bodies().len() < 4002, "nothing merged");
+    assert!(scenario.bodies()[0].mass >= centres[0]);
+    assert!(scenario.bodies()[1].mass >= centres[1]);
+}
+
+#[test]
+#[should_panic(expected = "removed a galaxy centre")]
+fn removing_a_centre_panics() {
+    let mut scenario = Collision::new(100);
+    scenario.set_contact_handler(Box::new(DropFirst));
+    scenario.process(0.01);
+}
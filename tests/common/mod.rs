@@ -0,0 +1,33 @@
+// fixtures shared by the integration tests, not every test file uses all of them
+#![allow(dead_code)]
+
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::softening::Softening;
+use galaxyy::solver::DirectSolver;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// period of the kepler orbit
+pub const PERIOD: f64 = 2.0 * std::f64::consts::PI;
+
+// point masses, G = 1
+pub fn newtonian() -> DirectSolver {
+    DirectSolver::with_softening(1.0, Softening::None)
+}
+
+// a planet of a thousandth of the mass on an orbit with semi-major axis 1 around a star, G (M + m) = 1
+// so the period is 2 pi. The relative orbit starts at pericentre, the centre of mass rests in the origin
+pub fn kepler(eccentricity: f64) -> Vec<Body> {
+    let planet_mass = 1e-3;
+    let mut bodies = vec![
+        Body::new(Mass(1.0 - planet_mass), Radius(0.0)),
+        Body::new(Mass(planet_mass), Radius(0.0)),
+    ];
+    let pos = Vec3::new(1.0 - eccentricity, 0.0, 0.0);
+    let vel = Vec3::new(0.0, ((1.0 + eccentricity) / (1.0 - eccentricity)).sqrt(), 0.0);
+    bodies[0].pos = -planet_mass * pos;
+    bodies[0].vel = -planet_mass * vel;
+    bodies[1].pos = (1.0 - planet_mass) * pos;
+    bodies[1].vel = (1.0 - planet_mass) * vel;
+    bodies
+}
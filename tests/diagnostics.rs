@@ -0,0 +1,84 @@
+mod common;
+
+use common::newtonian;
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::diagnostics::{center_of_mass, Diagnostics, DiagnosticsTracker, Drift};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+fn body(mass: f64, pos: Vec3, vel: Vec3) -> Body {
+    let mut body = Body::new(Mass(mass), Radius(0.0));
+    body.pos = pos;
+    body.vel = vel;
+    body
+}
+
+// masses 2 and 1 three apart, moving in opposite directions around their centre of mass in the origin
+fn two_bodies() -> Vec<Body> {
+    vec![
+        body(2.0, Vec3::new(-1.0, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+        body(1.0, Vec3::new(2.0, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0)),
+    ]
+}
+
+fn compute(bodies: &[Body]) -> Diagnostics {
+    Diagnostics::compute(bodies, &mut newtonian())
+}
+
+// K = 2 / 2 * 1 + 1 / 2 * 4, W = -G m1 m2 / r = -2 / 3, L = 2 * 1 + 1 * 4 along z
+#[test]
+fn two_body_values() {
+    let bodies = two_bodies();
+    let diagnostics = compute(&bodies);
+    assert_eq!(diagnostics.kinetic_energy, 3.0);
+    assert!((diagnostics.potential_energy + 2.0 / 3.0).abs() < 1e-15, "{}", diagnostics.potential_energy);
+    assert!((diagnostics.total_energy() - 7.0 / 3.0).abs() < 1e-15);
+    assert_eq!(diagnostics.momentum, Vec3::zeros());
+    assert_eq!(diagnostics.angular_momentum, Vec3::new(0.0, 0.0, 6.0));
+    assert!((diagnostics.virial_ratio() - 9.0).abs() < 1e-14, "{}", diagnostics.virial_ratio());
+    assert_eq!(center_of_mass(&bodies), Vec3::zeros());
+}
+
+/*
+ * A kick of 1 along x on the lighter body adds 1/2 to the energy of 7/3, momentum changes by 1 against
+ * the summed momenta of 2 + 2 and the angular momentum doesn't change, the kick points away from the
+ * origin.
+ */
+#[test]
+fn tracker_drift_is_relative_to_the_first_state() {
+    let mut bodies = two_bodies();
+    let mut tracker = DiagnosticsTracker::new();
+    assert_eq!(tracker.drift(), Drift::default());
+
+    tracker.record(compute(&bodies));
+    assert_eq!(tracker.drift(), Drift::default());
+
+    bodies[1].vel.x = 1.0;
+    tracker.record(compute(&bodies));
+    tracker.record(compute(&bodies));
+    let drift = tracker.drift();
+    assert!((drift.energy - 3.0 / 14.0).abs() < 1e-14, "{:?}", drift);
+    assert!((drift.momentum - 0.25).abs() < 1e-15, "{:?}", drift);
+    assert_eq!(drift.angular_momentum, 0.0);
+    assert_eq!(tracker.initial(), Some(compute(&two_bodies())));
+
+    tracker.reset();
+    assert_eq!(tracker.initial(), None);
+    tracker.record(compute(&bodies));
+    assert_eq!(tracker.drift(), Drift::default());
+}
+
+// bodies at rest have no momentum to compare with, the drift is the absolute change
+#[test]
+fn drift_from_rest_is_absolute() {
+    let mut bodies = two_bodies();
+    for body in bodies.iter_mut() {
+        body.vel = Vec3::zeros();
+    }
+    let mut tracker = DiagnosticsTracker::new();
+    tracker.record(compute(&bodies));
+    bodies[0].vel = Vec3::new(0.0, 0.0, 0.5);
+    tracker.record(compute(&bodies));
+    let drift = tracker.drift();
+    assert_eq!((drift.momentum, drift.angular_momentum), (1.0, 1.0));
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use common::newtonian;
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::integrator::{Integrator, IntegratorIAS15};
+use galaxyy::potential::{Centre, ExternalPotential};
+use galaxyy::scenario::{Collision, Scenario};
+use galaxyy::solver::{Background, DynamicalFriction, ExternalField, ForceSolver};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// at v = sqrt(2) sigma the fraction of slower background particles is erf(1) - 2 / sqrt(pi) / e
+#[test]
+fn drag_in_uniform_background() {
+    let (density, dispersion, coulomb_logarithm) = (0.3, 2.0, 4.0);
+    let mut solver = DynamicalFriction::new(Box::new(newtonian()), 1, 1.0, coulomb_logarithm);
+    solver.add(Background::Uniform { density, dispersion }, Centre::Fixed(Vec3::new(0.0, 0.0, 0.0)));
+    let mut bodies = vec![Body::new(Mass(5.0), Radius(0.0))];
+    let speed = 2f64.sqrt() * dispersion;
+    bodies[0].pos = Vec3::new(1.0, 2.0, 3.0);
+    bodies[0].vel = Vec3::new(0.0, 0.0, speed);
+    solver.eval(&mut bodies);
+    let fraction = 0.8427007929 - 2.0 / std::f64::consts::PI.sqrt() / std::f64::consts::E;
+    let expected = -4.0 * std::f64::consts::PI * 5.0 * density * coulomb_logarithm * fraction / (speed * speed);
+    assert!(bodies[0].acc.x == 0.0 && bodies[0].acc.y == 0.0);
+    assert!(((bodies[0].acc.z - expected) / expected).abs() < 1e-6, "{} {}", bodies[0].acc.z, expected);
+}
+
+/*
+ * A circular orbit in a singular isothermal sphere sinks to the centre in
+ *     t = 1.17 r^2 v_c / (G M ln(Lambda))  (Binney & Tremaine 8.13)
+ * the logarithmic potential without core is that sphere.
+ */
+#[test]
+fn sinking_time_in_isothermal_sphere() {
+    let halo = ExternalPotential::Logarithmic { speed: 1.0, core: 0.0, flattening: 1.0 };
+    let mut field = ExternalField::new(Box::new(newtonian()), 1.0);
+    field.add(halo, Centre::Fixed(Vec3::new(0.0, 0.0, 0.0)));
+    let (mass, coulomb_logarithm, radius) = (0.01, 5.0, 10.0);
+    let mut solver = DynamicalFriction::new(Box::new(field), 1, 1.0, coulomb_logarithm);
+    solver.add(Background::Profile(halo), Centre::Fixed(Vec3::new(0.0, 0.0, 0.0)));
+
+    let mut bodies = vec![Body::new(Mass(mass), Radius(0.0))];
+    bodies[0].pos = Vec3::new(radius, 0.0, 0.0);
+    bodies[0].vel = Vec3::new(0.0, 1.0, 0.0);
+    let mut integrator = IntegratorIAS15::new();
+    let mut time = 0.0;
+    while bodies[0].pos.norm() > 0.1 * radius {
+        integrator.integrate(1.0, &mut bodies, &mut solver);
+        time += 1.0;
+    }
+    // the last tenth of the radius takes a hundredth of the time
+    let expected = 0.99 * 1.17 * radius * radius / (mass * coulomb_logarithm);
+    assert!(((time - expected) / expected).abs() < 0.02, "sinking time {}, expected {}", time, expected);
+}
+
+// without friction the galaxy centres swing through each other again and again
+#[test]
+fn galaxy_centres_merge() {
+    let separation = |scenario: &Collision| (scenario.bodies()[0].pos - scenario.bodies()[1].pos).norm();
+    let mut merging = Collision::merging(0);
+    let mut passing = Collision::with_halos(0);
+    let mut widest: f64 = 0.0;
+    let mut widest_passing: f64 = 0.0;
+    for frame in 0..8000 {
+        merging.process(1.0 / 40.0);
+        passing.process(1.0 / 40.0);
+        if frame >= 4000 {
+            widest = widest.max(separation(&merging));
+            widest_passing = widest_passing.max(separation(&passing));
+        }
+    }
+    assert!(widest < 10.0, "centres separate up to {}", widest);
+    assert!(widest_passing > 100.0, "centres without friction separate up to {}", widest_passing);
+}
+
+// slow bodies see only the slow tail of the background, the drag goes to zero linearly with v
+#[test]
+fn drag_is_linear_in_speed_when_slow() {
+    let (density, dispersion, coulomb_logarithm) = (0.3, 2.0, 4.0);
+    let mut solver = DynamicalFriction::new(Box::new(newtonian()), 1, 1.0, coulomb_logarithm);
+    solver.add(Background::Uniform { density, dispersion }, Centre::Fixed(Vec3::new(0.0, 0.0, 0.0)));
+    // fraction 4 X^3 / (3 sqrt(pi)) with X = v / (sqrt(2) sigma)
+    let slope = -4.0 * std::f64::consts::PI * 5.0 * density * coulomb_logarithm * 4.0
+        / (3.0 * std::f64::consts::PI.sqrt())
+        / (2f64.sqrt() * dispersion).powi(3);
+    for &speed in &[1e-2, 1e-4, 1e-6, 1e-8, 1e-12] {
+        let mut bodies = vec![Body::new(Mass(5.0), Radius(0.0))];
+        bodies[0].vel = Vec3::new(speed, 0.0, 0.0);
+        solver.eval(&mut bodies);
+        let ratio = bodies[0].acc.x / speed;
+        assert!(ratio.is_finite());
+        let tolerance = if speed > 1e-3 { 1e-4 } else { 1e-9 };
+        assert!(((ratio - slope) / slope).abs() < tolerance, "speed {}: {} {}", speed, ratio, slope);
+    }
+}
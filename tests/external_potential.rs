@@ -0,0 +1,102 @@
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::diagnostics::Diagnostics;
+use galaxyy::integrator::{Integrator, IntegratorIAS15};
+use galaxyy::potential::{Centre, ExternalPotential};
+use galaxyy::softening::Softening;
+use galaxyy::solver::{DirectSolver, ExternalField, ForceSolver};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+const G: f64 = 2.0;
+
+fn potentials() -> Vec<ExternalPotential> {
+    vec![
+        ExternalPotential::Nfw { mass: 3.0, scale: 2.0 },
+        ExternalPotential::Hernquist { mass: 3.0, scale: 0.5 },
+        ExternalPotential::MiyamotoNagai { mass: 3.0, a: 1.5, b: 0.3 },
+        ExternalPotential::Logarithmic { speed: 1.2, core: 0.5, flattening: 0.8 },
+    ]
+}
+
+#[test]
+fn acceleration_is_gradient_of_potential() {
+    let h = 1e-5;
+    for potential in potentials() {
+        for &offset in &[Vec3::new(0.3, -0.2, 0.1), Vec3::new(2.0, 1.0, -0.7), Vec3::new(-5.0, 0.5, 3.0)] {
+            let acc = potential.acceleration(offset, G);
+            for axis in 0..3 {
+                let mut step = Vec3::new(0.0, 0.0, 0.0);
+                step[axis] = h;
+                let gradient =
+                    (potential.potential(offset + step, G) - potential.potential(offset - step, G)) / (2.0 * h);
+                let error = (acc[axis] + gradient).abs() / acc.norm();
+                assert!(error < 1e-7, "{:?} at {:?}: error {:e}", potential, offset, error);
+            }
+        }
+    }
+}
+
+#[test]
+fn logarithmic_rotation_curve_is_flat() {
+    let potential = ExternalPotential::Logarithmic { speed: 1.2, core: 0.5, flattening: 0.8 };
+    for &radius in &[5.0, 10.0, 50.0] {
+        let speed = potential.circular_speed(radius, G);
+        assert!((speed - 1.2).abs() < 0.01, "circular speed {} at {}", speed, radius);
+    }
+}
+
+// a star on an inclined eccentric orbit through a bulge, disc and halo, which don't move
+#[test]
+fn energy_is_conserved_in_static_potentials() {
+    let mut field = ExternalField::new(Box::new(DirectSolver::with_softening(G, Softening::None)), G);
+    for potential in potentials() {
+        field.add(potential, Centre::Fixed(Vec3::new(1.0, 0.0, 0.0)));
+    }
+    let mut bodies = vec![Body::new(Mass(1.0), Radius(0.0))];
+    bodies[0].pos = Vec3::new(4.0, 0.0, 0.5);
+    bodies[0].vel = Vec3::new(0.0, 1.5, 0.8);
+    let initial = Diagnostics::compute(&bodies, &mut field).total_energy();
+    let mut integrator = IntegratorIAS15::new();
+    for _ in 0..1000 {
+        integrator.integrate(0.1, &mut bodies, &mut field);
+    }
+    let energy = Diagnostics::compute(&bodies, &mut field).total_energy();
+    let drift = ((energy - initial) / initial).abs();
+    assert!(drift < 1e-12, "energy error {:e}", drift);
+}
+
+// a body doesn't feel its own halo, the others do
+#[test]
+fn attached_potential_moves_with_its_body() {
+    let halo = ExternalPotential::Hernquist { mass: 10.0, scale: 1.0 };
+    let mut field = ExternalField::new(Box::new(DirectSolver::with_softening(G, Softening::None)), G);
+    field.add(halo, Centre::Body(0));
+    let mut bodies = vec![Body::new(Mass(1.0), Radius(0.0)), Body::new(Mass(1e-9), Radius(0.0))];
+    bodies[0].pos = Vec3::new(5.0, 5.0, 0.0);
+    bodies[1].pos = Vec3::new(7.0, 5.0, 0.0);
+    field.eval(&mut bodies);
+    let newtonian = G * bodies[1].mass / 4.0;
+    assert!((bodies[0].acc - Vec3::new(newtonian, 0.0, 0.0)).norm() < 1e-15);
+    let pull = halo.acceleration(Vec3::new(2.0, 0.0, 0.0), G) - Vec3::new(G * bodies[0].mass / 4.0, 0.0, 0.0);
+    assert!((bodies[1].acc - pull).norm() < 1e-12, "{:?} {:?}", bodies[1].acc, pull);
+}
+
+// Poisson's equation, laplacian of the potential is 4 pi G rho
+#[test]
+fn density_generates_potential() {
+    let h = 1e-3;
+    for potential in potentials() {
+        for &offset in &[Vec3::new(0.3, -0.2, 0.1), Vec3::new(2.0, 1.0, -0.7), Vec3::new(-5.0, 0.5, 3.0)] {
+            let mut laplacian = -6.0 * potential.potential(offset, G);
+            for axis in 0..3 {
+                let mut step = Vec3::new(0.0, 0.0, 0.0);
+                step[axis] = h;
+                laplacian += potential.potential(offset + step, G) + potential.potential(offset - step, G);
+            }
+            laplacian /= h * h;
+            let density = potential.density(offset, G);
+            let error = (laplacian - 4.0 * std::f64::consts::PI * G * density).abs() / laplacian.abs();
+            assert!(error < 1e-4, "{:?} at {:?}: error {:e}", potential, offset, error);
+        }
+    }
+}
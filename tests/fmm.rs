@@ -0,0 +1,108 @@
+mod common;
+
+use common::newtonian;
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::fmm::FastMultipole;
+use galaxyy::softening::Softening;
+use galaxyy::solver::{DirectSolver, ForceSolver};
+use rand::{Rng, SeedableRng};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// exponential disc like a galaxy of GalaxyCollisionBarnesHut, seeded so every run sees the same bodies
+fn disc(count: usize) -> Vec<Body> {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1729);
+    (0..count)
+        .map(|_| {
+            let r = -2.0 * (1.0 - rng.gen_range(0.0..1.0f64)).ln();
+            let a = 2.0 * std::f64::consts::PI * rng.gen_range(0.0..1.0);
+            let mut body = Body::new(Mass(0.03 + 20.0 * rng.gen_range(0.0..1.0)), Radius(0.05));
+            body.pos = Vec3::new(r * a.cos(), r * a.sin(), 0.0);
+            body
+        })
+        .collect()
+}
+
+// median and largest relative error of the accelerations against direct summation
+fn errors(solver: &mut dyn ForceSolver, bodies: &[Body], exact: &[Body]) -> (f64, f64) {
+    let mut bodies = bodies.to_vec();
+    solver.eval(&mut bodies);
+    let mut errors: Vec<f64> =
+        bodies.iter().zip(exact).map(|(body, exact)| (body.acc - exact.acc).norm() / exact.acc.norm()).collect();
+    errors.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    (errors[errors.len() / 2], errors[errors.len() - 1])
+}
+
+// (order, median, largest) error bounds on the disc against direct summation
+const BOUNDS: [(usize, f64, f64); 6] =
+    [(2, 3e-2, 1.0), (4, 2e-3, 0.2), (6, 1.5e-4, 5e-2), (8, 1.5e-5, 1e-2), (12, 5e-7, 5e-4), (16, 2e-8, 5e-5)];
+
+// without softening the expansions are the only approximation
+#[test]
+fn newtonian_errors_fall_with_expansion_order() {
+    let bodies = disc(2000);
+    let mut exact = bodies.clone();
+    newtonian().eval(&mut exact);
+    for &(order, median_bound, largest_bound) in &BOUNDS {
+        let mut fmm = FastMultipole::with_softening(1.0, Softening::None);
+        fmm.set_order(order);
+        let (median, largest) = errors(&mut fmm, &bodies, &exact);
+        assert!(median < median_bound, "order {}: median error {:e}", order, median);
+        assert!(largest < largest_bound, "order {}: largest error {:e}", order, largest);
+    }
+}
+
+/*
+ * The spline kernel is newtonian beyond 2.8 epsilons, typical errors fall as without softening.
+ * Nodes closer than that only add their softened monopole, the few bodies next to them keep an error
+ * of a couple of percent whatever the order. Plummer softening never becomes newtonian, its error
+ * levels off where the expansions ignore it.
+ */
+#[test]
+fn softened_errors_fall_with_expansion_order() {
+    let bodies = disc(2000);
+    let mut exact = bodies.clone();
+    DirectSolver::with_softening(1.0, Softening::Spline(0.1)).eval(&mut exact);
+    for &(order, median_bound, _) in &BOUNDS {
+        let mut fmm = FastMultipole::with_softening(1.0, Softening::Spline(0.1));
+        fmm.set_order(order);
+        let (median, largest) = errors(&mut fmm, &bodies, &exact);
+        assert!(median < median_bound, "order {}: median error {:e}", order, median);
+        assert!(largest < 0.6, "order {}: largest error {:e}", order, largest);
+    }
+
+    let mut exact = bodies.clone();
+    DirectSolver::with_softening(1.0, Softening::Plummer(0.1)).eval(&mut exact);
+    for &order in &[4, 8, 16] {
+        let mut fmm = FastMultipole::with_softening(1.0, Softening::Plummer(0.1));
+        fmm.set_order(order);
+        let (median, largest) = errors(&mut fmm, &bodies, &exact);
+        assert!(median < 1e-2 && largest < 0.5, "order {}: median error {:e}, largest {:e}", order, median, largest);
+    }
+}
+
+// the expansions are planar, a body above the plane must not get the force of its projection
+#[test]
+#[should_panic(expected = "FastMultipole is planar")]
+fn rejects_bodies_off_the_plane() {
+    let mut bodies = vec![Body::new(Mass(1.0), Radius(0.0)), Body::new(Mass(1.0), Radius(0.0))];
+    bodies[1].pos = Vec3::new(1.0, 0.0, 0.5);
+    FastMultipole::new(1.0).eval(&mut bodies);
+}
+
+// the bodies are checked before anything is evaluated, a rejected call leaves them and the solver as they were
+#[test]
+fn rejected_bodies_stay_untouched() {
+    let mut solver = FastMultipole::new(1.0);
+    let mut bodies = vec![Body::new(Mass(1.0), Radius(0.0)), Body::new(Mass(1.0), Radius(0.0))];
+    bodies[1].pos = Vec3::new(1.0, 0.0, 0.5);
+    bodies[1].acc = Vec3::new(7.0, 8.0, 9.0);
+    let before = bodies.clone();
+    let rejected = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| solver.eval(&mut bodies)));
+    assert!(rejected.is_err());
+    assert_eq!(bodies, before);
+
+    bodies[1].pos.z = 0.0;
+    solver.eval(&mut bodies);
+    assert!((bodies[0].acc - Vec3::new(1.0, 0.0, 0.0)).norm() < 0.1, "{:?}", bodies[0].acc);
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use common::{kepler, newtonian, PERIOD};
+use galaxyy::diagnostics::Diagnostics;
+use galaxyy::integrator::{Integrator, IntegratorHermite};
+use galaxyy::solver::{ExternalField, ForceSolver};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// ten periods, returns the planet relative to the star and the relative energy error
+fn orbit(solver: &mut dyn ForceSolver) -> (Vec3, f64) {
+    let mut bodies = kepler(0.5);
+    let initial = Diagnostics::compute(&bodies, solver).total_energy();
+    let mut integrator = IntegratorHermite::new();
+    for _ in 0..100 {
+        integrator.integrate(PERIOD / 10.0, &mut bodies, solver);
+    }
+    let energy = Diagnostics::compute(&bodies, solver).total_energy();
+    (bodies[1].pos - bodies[0].pos, ((energy - initial) / initial).abs())
+}
+
+#[test]
+fn kepler_orbit_closes() {
+    let (pos, energy_error) = orbit(&mut newtonian());
+    assert!((pos - Vec3::new(0.5, 0.0, 0.0)).norm() < 1e-3, "planet at {:?}", pos);
+    assert!(energy_error < 1e-4, "energy error {:e}", energy_error);
+}
+
+// an external field without potentials is plain gravity but provides no jerks, they are
+// differentiated numerically and the orbit follows the analytic ones closely
+#[test]
+fn numerical_jerks_for_solvers_without_them() {
+    let (exact, _) = orbit(&mut newtonian());
+    let mut field = ExternalField::new(Box::new(newtonian()), 1.0);
+    assert!(field.eval_jerk(&mut kepler(0.5), &[0, 1]).is_none());
+    let (pos, energy_error) = orbit(&mut field);
+    assert!((pos - exact).norm() < 1e-6, "planet at {:?}, {:?} with analytic jerks", pos, exact);
+    assert!(energy_error < 1e-4, "energy error {:e}", energy_error);
+}
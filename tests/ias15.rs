@@ -0,0 +1,71 @@
+mod common;
+
+use common::{kepler, newtonian};
+use galaxyy::body::Body;
+use galaxyy::diagnostics;
+use galaxyy::integrator::{Integrator, IntegratorIAS15};
+use galaxyy::scenario::{Scenario, SunEarthMoon, ThreeBody};
+use galaxyy::solver::ForceSolver;
+
+fn energy(bodies: &[Body]) -> f64 {
+    diagnostics::kinetic_energy(bodies) + newtonian().potential_energy(bodies)
+}
+
+// round-off of 1e-16 per step adds up to a few 1e-13 as a random walk over 10^7 steps. Takes minutes,
+// run it with cargo test --release --test ias15 -- --ignored
+#[test]
+#[ignore]
+fn kepler_energy_stays_at_round_off_for_1e5_orbits() {
+    let mut bodies = kepler(0.5);
+    let period = 2.0 * std::f64::consts::PI / (bodies[0].mass + bodies[1].mass).sqrt();
+    let initial = energy(&bodies);
+    let mut integrator = IntegratorIAS15::new();
+    let mut solver = newtonian();
+    let mut worst: f64 = 0.0;
+    for orbit in 1..=100_000 {
+        integrator.integrate(period, &mut bodies, &mut solver);
+        if orbit % 1000 == 0 {
+            worst = worst.max(((energy(&bodies) - initial) / initial).abs());
+        }
+    }
+    assert!(worst < 1e-12, "energy error {:e}", worst);
+}
+
+#[test]
+fn kepler_orbit_closes() {
+    let mut bodies = kepler(0.5);
+    let period = 2.0 * std::f64::consts::PI / (bodies[0].mass + bodies[1].mass).sqrt();
+    let start = bodies[1].pos - bodies[0].pos;
+    let mut integrator = IntegratorIAS15::new();
+    let mut solver = newtonian();
+    for _ in 0..100 {
+        integrator.integrate(period, &mut bodies, &mut solver);
+    }
+    let error = (bodies[1].pos - bodies[0].pos - start).norm();
+    assert!(error < 1e-9, "position error {:e}", error);
+}
+
+fn energy_drift(scenario: &mut dyn Scenario, dt: f64, frames: usize) -> f64 {
+    let initial = scenario.diagnostics().total_energy();
+    let mut worst: f64 = 0.0;
+    for _ in 0..frames {
+        scenario.process(dt);
+        let current = scenario.diagnostics().total_energy();
+        worst = worst.max(((current - initial) / initial).abs());
+    }
+    worst
+}
+
+#[test]
+fn sun_earth_moon_energy() {
+    let mut scenario = SunEarthMoon::with_integrator(Box::new(IntegratorIAS15::new()));
+    let drift = energy_drift(&mut scenario, 1.0, 200);
+    assert!(drift < 1e-13, "energy error {:e}", drift);
+}
+
+#[test]
+fn figure_eight_energy() {
+    let mut scenario = ThreeBody::with_integrator(Box::new(IntegratorIAS15::new()));
+    let drift = energy_drift(&mut scenario, 1.0, 200);
+    assert!(drift < 1e-13, "energy error {:e}", drift);
+}
@@ -0,0 +1,136 @@
+mod common;
+
+use common::{kepler, newtonian, PERIOD};
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::diagnostics::Diagnostics;
+use galaxyy::integrator::{
+    Integrator, IntegratorABM6, IntegratorEuler, IntegratorLeapfrog, IntegratorRK4, IntegratorYoshida4,
+};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+type NewIntegrator = fn() -> Box<dyn Integrator>;
+
+// relative position and velocity of the planet after 1.3 periods with the given number of steps per
+// period. The orbit is symmetric around the pericentre, where the first order error of euler cancels
+fn after_periods(integrator: &mut dyn Integrator, steps: usize) -> (Vec3, Vec3) {
+    let mut bodies = kepler(0.5);
+    let mut solver = newtonian();
+    for _ in 0..steps * 13 / 10 {
+        integrator.integrate(PERIOD / steps as f64, &mut bodies, &mut solver);
+    }
+    (bodies[1].pos - bodies[0].pos, bodies[1].vel - bodies[0].vel)
+}
+
+// halving the step divides the error by 2^order, errors are taken against the next finer run.
+// Semi-implicit euler gets positions to second order, its first order velocity error only
+// dominates for small steps
+fn measured_order(new: NewIntegrator, steps: usize) -> f64 {
+    let runs: Vec<(Vec3, Vec3)> =
+        [steps, 2 * steps, 4 * steps].iter().map(|&steps| after_periods(&mut *new(), steps)).collect();
+    let error = |a: (Vec3, Vec3), b: (Vec3, Vec3)| (a.0 - b.0).norm() + (a.1 - b.1).norm();
+    (error(runs[0], runs[1]) / error(runs[1], runs[2])).log2()
+}
+
+// relative energy error after every step
+fn energy_errors(integrator: &mut dyn Integrator, orbits: usize, steps_per_orbit: usize) -> Vec<f64> {
+    let mut bodies = kepler(0.5);
+    let mut solver = newtonian();
+    let initial = Diagnostics::compute(&bodies, &mut solver).total_energy();
+    (0..orbits * steps_per_orbit)
+        .map(|_| {
+            integrator.integrate(PERIOD / steps_per_orbit as f64, &mut bodies, &mut solver);
+            (Diagnostics::compute(&bodies, &mut solver).total_energy() - initial) / initial.abs()
+        })
+        .collect()
+}
+
+fn largest(errors: &[f64]) -> f64 {
+    errors.iter().fold(0.0, |largest, error| largest.max(error.abs()))
+}
+
+#[test]
+fn order_of_convergence() {
+    let cases: [(&str, NewIntegrator, usize, f64); 5] = [
+        ("euler", || Box::new(IntegratorEuler::new()), 40000, 1.0),
+        ("leapfrog", || Box::new(IntegratorLeapfrog::new()), 500, 2.0),
+        ("rk4", || Box::new(IntegratorRK4::new()), 250, 4.0),
+        ("yoshida4", || Box::new(IntegratorYoshida4::new()), 250, 4.0),
+        // the five fourth order runge-kutta starting steps keep it near order 5 for coarser steps
+        ("abm6", || Box::new(IntegratorABM6::new()), 800, 6.0),
+    ];
+    for &(name, new, steps, expected) in &cases {
+        let order = measured_order(new, steps);
+        assert!((order - expected).abs() < 0.3, "{} has order {:.2} instead of {}", name, order, expected);
+    }
+}
+
+// symplectic integrators keep the energy error bounded, over 100 orbits it gets no larger than in the first 10
+#[test]
+fn symplectic_energy_error_stays_bounded() {
+    let cases: [(&str, Box<dyn Integrator>, f64); 3] = [
+        ("euler", Box::new(IntegratorEuler::new()), 0.1),
+        ("leapfrog", Box::new(IntegratorLeapfrog::new()), 1e-2),
+        ("yoshida4", Box::new(IntegratorYoshida4::new()), 1e-4),
+    ];
+    for (name, mut integrator, bound) in cases {
+        let errors = energy_errors(&mut *integrator, 100, 200);
+        let (first, all) = (largest(&errors[..errors.len() / 10]), largest(&errors));
+        assert!(all < bound, "{} energy error {:e}", name, all);
+        assert!(all < 1.1 * first, "{} energy error {:e} in the first 10 orbits, {:e} in 100", name, first, all);
+    }
+}
+
+// runge-kutta is not symplectic, its orbit loses about the same energy in every period
+#[test]
+fn runge_kutta_energy_drifts() {
+    let errors = energy_errors(&mut IntegratorRK4::new(), 100, 200);
+    let (ten, hundred) = (errors[errors.len() / 10 - 1], errors[errors.len() - 1]);
+    assert!(ten < 0.0 && hundred < 0.0);
+    assert!((hundred / ten - 10.0).abs() < 1.0, "energy error {:e} after 10, {:e} after 100 orbits", ten, hundred);
+}
+
+
+// a new step or another number of bodies drops the history, the integrator starts over exactly like a
+// new one from the same state
+#[test]
+fn adams_bashforth_moulton_restarts() {
+    let mut solver = newtonian();
+    let mut bodies = kepler(0.5);
+    let mut integrator = IntegratorABM6::new();
+    for _ in 0..20 {
+        integrator.integrate(PERIOD / 200.0, &mut bodies, &mut solver);
+    }
+
+    let mut restarted = bodies.clone();
+    let mut fresh = bodies.clone();
+    let mut new = IntegratorABM6::new();
+    for _ in 0..10 {
+        integrator.integrate(PERIOD / 300.0, &mut restarted, &mut solver);
+        new.integrate(PERIOD / 300.0, &mut fresh, &mut solver);
+    }
+    assert_eq!(restarted, fresh);
+
+    let mut continued = bodies.clone();
+    let mut fresh = bodies.clone();
+    integrator.reset();
+    let mut new = IntegratorABM6::new();
+    for _ in 0..10 {
+        integrator.integrate(PERIOD / 200.0, &mut continued, &mut solver);
+        new.integrate(PERIOD / 200.0, &mut fresh, &mut solver);
+    }
+    assert_eq!(continued, fresh);
+
+    let mut more = bodies.clone();
+    let mut planet = Body::new(Mass(0.0), Radius(0.0));
+    planet.pos = Vec3::new(2.0, 0.0, 0.0);
+    planet.vel = Vec3::new(0.0, 0.7, 0.0);
+    more.push(planet);
+    let mut fresh = more.clone();
+    let mut new = IntegratorABM6::new();
+    for _ in 0..10 {
+        integrator.integrate(PERIOD / 200.0, &mut more, &mut solver);
+        new.integrate(PERIOD / 200.0, &mut fresh, &mut solver);
+    }
+    assert_eq!(more, fresh);
+}
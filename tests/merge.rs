@@ -0,0 +1,85 @@
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::contact::{ContactHandler, Merger};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+fn body(mass: f64, radius: f64, x: f64, vx: f64) -> Body {
+    let mut body = Body::new(Mass(mass), Radius(radius));
+    body.pos = Vec3::new(x, 0.0, 0.0);
+    body.vel = Vec3::new(vx, 0.0, 0.0);
+    body
+}
+
+fn momentum(bodies: &[Body]) -> Vec3 {
+    bodies.iter().map(|body| body.mass * body.vel).sum()
+}
+
+// the survivor touches both neighbours, after absorbing the heavy one on the left it has moved away from
+// the one on the right and only merges with it once it has grown enough
+#[test]
+fn survivor_is_checked_again_after_every_merge() {
+    let mut bodies = vec![body(1.0, 1.0, 0.0, 1.0), body(9.0, 0.2, -1.1, 0.0), body(1.0, 0.1, 1.05, -1.0)];
+    let mass: f64 = bodies.iter().map(|body| body.mass).sum();
+    let before = momentum(&bodies);
+    let mut merger = Merger::new();
+    merger.resolve(&mut bodies, 0.0);
+
+    // after the first merge the survivor sits at x = -0.99 with radius 1.004, 0.036 short of the last body
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(merger.log().len(), 1);
+    assert_eq!((merger.log()[0].survivor, merger.log()[0].absorbed), (0, 1));
+    assert!((bodies[0].pos.x + 0.99).abs() < 1e-12);
+    assert!((bodies.iter().map(|body| body.mass).sum::<f64>() - mass).abs() < 1e-14);
+    assert!((momentum(&bodies) - before).norm() < 1e-14);
+}
+
+// in a packed lattice merged bodies grow into their neighbours, merging repeats until nothing overlaps
+#[test]
+fn nothing_overlaps_after_resolve() {
+    let mut bodies = Vec::new();
+    for i in 0..64 {
+        let mut body = body(1.0 + i as f64, 0.6, (i % 4) as f64, 0.1 * i as f64);
+        body.pos.y = (i / 4 % 4) as f64;
+        body.pos.z = (i / 16) as f64;
+        bodies.push(body);
+    }
+    let before = momentum(&bodies);
+    let mut merger = Merger::new();
+    merger.resolve(&mut bodies, 1.0);
+
+    assert!(bodies.len() < 32);
+    assert_eq!(merger.log().len(), 64 - bodies.len());
+    assert_eq!(bodies.iter().map(|body| body.mass).sum::<f64>(), 64.0 * 65.0 / 2.0);
+    assert!((momentum(&bodies) - before).norm() < 1e-11);
+    for (i, a) in bodies.iter().enumerate() {
+        for b in &bodies[i + 1..] {
+            assert!((a.pos - b.pos).norm() >= a.radius + b.radius);
+        }
+    }
+}
+
+#[test]
+fn massless_bodies_merge_to_their_mean() {
+    let mut bodies = vec![body(0.0, 1.0, 0.0, 1.0), body(0.0, 1.0, 1.0, 3.0)];
+    Merger::new().resolve(&mut bodies, 0.0);
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(bodies[0].mass, 0.0);
+    assert_eq!(bodies[0].pos.x, 0.5);
+    assert_eq!(bodies[0].vel.x, 2.0);
+}
+
+// protected bodies swallow others but never each other, so indices into the front of the list hold
+#[test]
+fn protected_bodies_keep_their_indices() {
+    let mut bodies = vec![
+        body(10.0, 1.0, 0.0, 0.0),
+        body(10.0, 1.0, 1.5, 0.0),
+        body(1.0, 0.1, 3.0, 0.0),
+        body(1.0, 0.1, 2.3, 0.0),
+    ];
+    let mut merger = Merger::with_protected(2);
+    merger.resolve(&mut bodies, 0.0);
+    assert_eq!(bodies.len(), 3);
+    assert_eq!((bodies[0].mass, bodies[1].mass, bodies[2].mass), (10.0, 11.0, 1.0));
+    assert_eq!(bodies[0].pos.x, 0.0);
+}
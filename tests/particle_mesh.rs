@@ -0,0 +1,145 @@
+mod common;
+
+use common::newtonian;
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::particle_mesh::fft::Fft;
+use galaxyy::particle_mesh::{Assignment, Boundary, ParticleMesh};
+use galaxyy::softening::Softening;
+use galaxyy::solver::ForceSolver;
+
+type Vec2 = nalgebra::Vector2<f64>;
+type Vec3 = nalgebra::Vector3<f64>;
+type Complex = nalgebra::Complex<f64>;
+
+fn body(mass: f64, x: f64, y: f64) -> Body {
+    let mut body = Body::new(Mass(mass), Radius(0.0));
+    body.pos = Vec3::new(x, y, 0.0);
+    body
+}
+
+// X_k = sum of x_n exp(-2 pi i k n / N)
+fn naive_dft(data: &[Complex]) -> Vec<Complex> {
+    let len = data.len();
+    (0..len)
+        .map(|k| {
+            data.iter()
+                .enumerate()
+                .map(|(n, x)| {
+                    let angle = -2.0 * std::f64::consts::PI * (k * n % len) as f64 / len as f64;
+                    x * Complex::new(angle.cos(), angle.sin())
+                })
+                .sum()
+        })
+        .collect()
+}
+
+#[test]
+fn fft_matches_naive_dft_and_inverts() {
+    for &len in &[1, 2, 8, 64, 256] {
+        let input: Vec<Complex> =
+            (0..len).map(|n| Complex::new((n as f64 * 0.7).sin() + 0.1 * n as f64, (n as f64 * 1.3).cos())).collect();
+        let fft = Fft::new(len);
+        let mut data = input.clone();
+        fft.transform(&mut data, false);
+        for (fast, naive) in data.iter().zip(naive_dft(&input)) {
+            assert!((fast - naive).norm_sqr().sqrt() < 1e-10 * len as f64, "length {}: {} instead of {}", len, fast, naive);
+        }
+        fft.transform(&mut data, true);
+        for (back, original) in data.iter().zip(&input) {
+            assert!((back - original).norm_sqr().sqrt() < 1e-13 * len as f64, "length {}: {} instead of {}", len, back, original);
+        }
+    }
+
+    // rows and columns of a mesh
+    let len = 16;
+    let input: Vec<Complex> = (0..len * len).map(|n| Complex::new((n as f64).sqrt(), (n % 7) as f64)).collect();
+    let fft = Fft::new(len);
+    let (mut data, mut scratch) = (input.clone(), Vec::new());
+    fft.transform_2d(&mut data, &mut scratch, false);
+    let rows: Vec<Complex> = input.chunks(len).flat_map(naive_dft).collect();
+    for x in 0..len {
+        let column: Vec<Complex> = (0..len).map(|y| rows[y * len + x]).collect();
+        for (y, naive) in naive_dft(&column).into_iter().enumerate() {
+            assert!((data[y * len + x] - naive).norm_sqr().sqrt() < 1e-9, "({}, {}): {} instead of {}", x, y, data[y * len + x], naive);
+        }
+    }
+    fft.transform_2d(&mut data, &mut scratch, true);
+    assert!(data.iter().zip(&input).all(|(back, original)| (back - original).norm_sqr().sqrt() < 1e-12));
+}
+
+// bodies many cells apart feel newtonian gravity, the mesh only smooths below a couple of cells
+#[test]
+fn isolated_mesh_matches_direct_summation() {
+    let bodies = vec![
+        body(3.0, 0.0, 0.0),
+        body(1.0, 10.0, 2.0),
+        body(2.0, -4.0, 9.0),
+        body(0.5, 6.0, -8.0),
+        body(1.5, -9.0, -5.0),
+    ];
+    // 19 by 17 units spread over 122 or 10 cells, pairs are at least 60 or 5 cells apart
+    let cases = [
+        (128, Assignment::CloudInCell, 2e-3),
+        (128, Assignment::TriangularShapedCloud, 2e-3),
+        (16, Assignment::CloudInCell, 5e-2),
+        (16, Assignment::TriangularShapedCloud, 5e-2),
+    ];
+    for &(cells, assignment, tolerance) in &cases {
+        let mut mesh = ParticleMesh::with_softening(1.0, cells, Softening::None);
+        mesh.set_assignment(assignment);
+        let mut direct = newtonian();
+        let (mut approximate, mut exact) = (bodies.clone(), bodies.clone());
+        mesh.eval(&mut approximate);
+        direct.eval(&mut exact);
+        for (approximate, exact) in approximate.iter().zip(&exact) {
+            let error = (approximate.acc - exact.acc).norm() / exact.acc.norm();
+            assert!(error < tolerance, "{} {:?}: {:?} instead of {:?}", cells, assignment, approximate.acc, exact.acc);
+        }
+    }
+}
+
+// every body of a uniform lattice has the same surroundings in all directions, in a periodic box
+// the pulls cancel exactly
+#[test]
+fn periodic_lattice_feels_no_force() {
+    let (size, spacing) = (8.0, 1.0);
+    let mut bodies: Vec<Body> = (0..64).map(|n| body(1.0, 0.3 + (n % 8) as f64 * spacing, 0.3 + (n / 8) as f64 * spacing)).collect();
+    // a single body off the lattice shows the scale of the forces
+    let mut single = bodies.clone();
+    single[0].pos.x += 0.5;
+    let mut mesh = ParticleMesh::with_softening(1.0, 32, Softening::None);
+    mesh.set_boundary(Boundary::Periodic { lower: Vec2::new(0.0, 0.0), size });
+    mesh.eval(&mut single);
+    mesh.eval(&mut bodies);
+    let scale = single[0].acc.norm();
+    assert!(scale > 0.1);
+    for body in &bodies {
+        assert!(body.acc.norm() < 1e-10 * scale, "body at {:?} pulled by {:?}", body.pos, body.acc);
+    }
+}
+
+// the mesh only covers the x-y plane, a body above it must not get the force of its projection
+#[test]
+#[should_panic(expected = "ParticleMesh is planar")]
+fn rejects_bodies_off_the_plane() {
+    let mut bodies = vec![Body::new(Mass(1.0), Radius(0.0)), Body::new(Mass(1.0), Radius(0.0))];
+    bodies[1].pos = Vec3::new(1.0, 0.0, 0.5);
+    ParticleMesh::new(1.0, 32).eval(&mut bodies);
+}
+
+// the bodies are checked before anything is evaluated, a rejected call leaves them and the solver as they were
+#[test]
+fn rejected_bodies_stay_untouched() {
+    let mut solver = ParticleMesh::new(1.0, 32);
+    let mut bodies = vec![Body::new(Mass(1.0), Radius(0.0)), Body::new(Mass(1.0), Radius(0.0))];
+    bodies[1].pos = Vec3::new(1.0, 0.0, 0.5);
+    bodies[1].acc = Vec3::new(7.0, 8.0, 9.0);
+    let before = bodies.clone();
+    let rejected = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| solver.eval(&mut bodies)));
+    assert!(rejected.is_err());
+    assert_eq!(bodies, before);
+
+    bodies[1].pos.z = 0.0;
+    solver.eval(&mut bodies);
+    assert!((bodies[0].acc - Vec3::new(1.0, 0.0, 0.0)).norm() < 0.1, "{:?}", bodies[0].acc);
+}
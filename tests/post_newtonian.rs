@@ -0,0 +1,100 @@
+mod common;
+
+use common::newtonian;
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::diagnostics::Diagnostics;
+use galaxyy::integrator::{Integrator, IntegratorBulirschStoer, IntegratorIAS15};
+use galaxyy::solver::PostNewtonian;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+// gaussian units: astronomical units, solar masses and 1 / k days (k = 0.01720209895), so G = 1
+const DAY: f64 = 0.01720209895;
+const SPEED_OF_LIGHT: f64 = 173.1446326846693 / DAY;
+const ARCSECOND: f64 = std::f64::consts::PI / (180.0 * 3600.0);
+
+fn solver(speed_of_light: f64) -> PostNewtonian {
+    PostNewtonian::new(Box::new(newtonian()), 2, 1.0, speed_of_light)
+}
+
+// a light body starting at pericentre of an orbit with semi-major axis a and eccentricity e
+fn binary(mass: f64, a: f64, e: f64) -> Vec<Body> {
+    let mut bodies = vec![Body::new(Mass(1.0), Radius(0.0)), Body::new(Mass(mass), Radius(0.0))];
+    let total = 1.0 + mass;
+    let pos = Vec3::new(a * (1.0 - e), 0.0, 0.0);
+    let vel = Vec3::new(0.0, (total * (1.0 + e) / (a * (1.0 - e))).sqrt(), 0.0);
+    bodies[0].pos = -mass / total * pos;
+    bodies[0].vel = -mass / total * vel;
+    bodies[1].pos = pos / total;
+    bodies[1].vel = vel / total;
+    bodies
+}
+
+// Laplace-Runge-Lenz vector of the relative orbit, points at pericentre
+fn pericentre(bodies: &[Body]) -> Vec3 {
+    let d = bodies[1].pos - bodies[0].pos;
+    let v = bodies[1].vel - bodies[0].vel;
+    let gm = bodies[0].mass + bodies[1].mass;
+    v.cross(&d.cross(&v)) / gm - d.normalize()
+}
+
+#[test]
+fn mercury_perihelion_precesses_43_arcseconds_per_century() {
+    let mut bodies = binary(1.6601e-7, 0.387098, 0.205630);
+    let mut solver = solver(SPEED_OF_LIGHT);
+    let mut integrator = IntegratorIAS15::new();
+    let start = pericentre(&bodies);
+    for _ in 0..36525 {
+        integrator.integrate(DAY, &mut bodies, &mut solver);
+    }
+    let end = pericentre(&bodies);
+    let precession = start.cross(&end).z.atan2(start.dot(&end)) / ARCSECOND;
+    assert!((precession - 42.98).abs() < 0.1, "precession {} arcseconds", precession);
+}
+
+// the truncated equations of motion conserve the 1PN energy up to (v / c)^4, while the newtonian
+// energy changes by (v / c)^2
+#[test]
+fn first_order_energy_is_conserved() {
+    let mut bodies = binary(0.5, 1.0, 0.6);
+    let mut solver = solver(100.0);
+    let mut newtonian = newtonian();
+    let initial = Diagnostics::compute(&bodies, &mut solver).total_energy();
+    let initial_newtonian = Diagnostics::compute(&bodies, &mut newtonian).total_energy();
+    let mut integrator = IntegratorBulirschStoer::new();
+    let mut worst: f64 = 0.0;
+    let mut worst_newtonian: f64 = 0.0;
+    for _ in 0..100 {
+        integrator.integrate(1.0, &mut bodies, &mut solver);
+        let energy = Diagnostics::compute(&bodies, &mut solver).total_energy();
+        let newtonian = Diagnostics::compute(&bodies, &mut newtonian).total_energy();
+        worst = worst.max(((energy - initial) / initial).abs());
+        worst_newtonian = worst_newtonian.max(((newtonian - initial_newtonian) / initial_newtonian).abs());
+    }
+    assert!(worst < 2e-5, "energy error {:e}", worst);
+    assert!(worst_newtonian > 1e-3, "newtonian energy error {:e}", worst_newtonian);
+}
+
+// Peters (1964): dE/dt = -32/5 G^4 mu^2 M^3 / (c^5 a^5) for a circular orbit. The 1PN energy itself
+// wanders by (v / c)^4, the loss is measured against the same orbit without radiation reaction.
+// Newtonian initial conditions leave an eccentricity of 3 G M / (c^2 a), which speeds up the loss by 5 e
+#[test]
+fn radiation_reaction_loses_energy_at_peters_rate() {
+    let mass = 0.5;
+    let speed_of_light = 100.0;
+    let time = 100.0;
+    let final_energy = |radiation_reaction| {
+        let mut bodies = binary(mass, 1.0, 0.0);
+        let mut solver = solver(speed_of_light);
+        solver.set_radiation_reaction(radiation_reaction);
+        let mut integrator = IntegratorBulirschStoer::new();
+        for _ in 0..100 {
+            integrator.integrate(time / 100.0, &mut bodies, &mut solver);
+        }
+        Diagnostics::compute(&bodies, &mut solver).total_energy()
+    };
+    let rate = (final_energy(true) - final_energy(false)) / time;
+    let reduced = mass / (1.0 + mass);
+    let peters = -32.0 / 5.0 * reduced * reduced * (1.0 + mass).powi(3) / speed_of_light.powi(5);
+    assert!(((rate - peters) / peters).abs() < 0.01, "dE/dt {:e}, peters {:e}", rate, peters);
+}
@@ -0,0 +1,149 @@
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::contact::{ContactHandler, Sinks};
+use galaxyy::integrator::{Integrator, IntegratorLeapfrog};
+use galaxyy::softening::Softening;
+use galaxyy::solver::DirectSolver;
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+fn body(mass: f64, pos: Vec3, vel: Vec3) -> Body {
+    let mut body = Body::new(Mass(mass), Radius(0.1));
+    body.pos = pos;
+    body.vel = vel;
+    body
+}
+
+fn momentum(bodies: &[Body]) -> Vec3 {
+    bodies.iter().map(|body| body.mass * body.vel).sum()
+}
+
+fn centre_of_mass(bodies: &[Body]) -> Vec3 {
+    bodies.iter().map(|body| body.mass * body.pos).sum::<Vec3>() / bodies.iter().map(|body| body.mass).sum::<f64>()
+}
+
+#[test]
+fn accretion_conserves_mass_and_momentum() {
+    let mut bodies = vec![
+        body(10.0, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.1, 0.0, 0.0)),
+        body(1.0, Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 2.0, 0.0)),
+        body(2.0, Vec3::new(0.0, 0.0, -0.9), Vec3::new(0.0, 0.0, 1.0)),
+        body(3.0, Vec3::new(1.5, 0.0, 0.0), Vec3::new(0.0, -1.0, 0.0)),
+    ];
+    let mass: f64 = bodies.iter().map(|body| body.mass).sum();
+    let (before, centre) = (momentum(&bodies), centre_of_mass(&bodies));
+    let outside = bodies[3];
+
+    let mut sinks = Sinks::new(vec![1.0]);
+    sinks.resolve(&mut bodies, 5.0);
+
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(bodies[1], outside);
+    assert_eq!(bodies[0].mass, 13.0);
+    assert_eq!(bodies[0].radius, 0.1);
+    assert!((bodies.iter().map(|body| body.mass).sum::<f64>() - mass).abs() < 1e-14);
+    assert!((momentum(&bodies) - before).norm() < 1e-14);
+    assert!((centre_of_mass(&bodies) - centre).norm() < 1e-14);
+
+    let log = sinks.log();
+    assert_eq!(log.len(), 2);
+    assert_eq!((log[0].sink, log[0].absorbed, log[0].accreted, log[0].mass), (0, 1, 1.0, 11.0));
+    assert_eq!((log[1].sink, log[1].absorbed, log[1].accreted, log[1].mass), (0, 2, 2.0, 13.0));
+    assert_eq!(log[0].time, 5.0);
+    assert_eq!(log[1].pos, Vec3::new(0.0, 0.0, -0.9));
+    // relative to the sink after the first accretion
+    assert!((log[1].vel - Vec3::new(-0.1 / 1.1, -2.0 / 11.0, 1.0)).norm() < 1e-14);
+}
+
+// the second sink falls into the first, the body it would have swallowed goes to the first as well
+#[test]
+fn sinks_absorb_each_other() {
+    let mut bodies = vec![
+        body(10.0, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)),
+        body(5.0, Vec3::new(0.8, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)),
+        body(1.0, Vec3::new(5.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)),
+        body(1.0, Vec3::new(0.8, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)),
+    ];
+    let mut sinks = Sinks::new(vec![1.0, 0.5]);
+    sinks.resolve(&mut bodies, 0.0);
+    assert_eq!(bodies.len(), 2);
+    assert_eq!(bodies[0].mass, 16.0);
+    assert_eq!(sinks.radii(), &[1.0]);
+    assert_eq!(sinks.log().iter().map(|event| event.absorbed).collect::<Vec<_>>(), vec![1, 3]);
+}
+
+// protected sinks pass through each other, the body between them goes to the first one and the
+// indices of the sinks stay the same
+#[test]
+fn protected_sinks_meet() {
+    let mut bodies = vec![
+        body(10.0, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)),
+        body(5.0, Vec3::new(0.8, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)),
+        body(1.0, Vec3::new(5.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)),
+        body(1.0, Vec3::new(0.7, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)),
+    ];
+    let mut sinks = Sinks::with_protected(vec![1.0, 0.5], 2);
+    assert!(sinks.resolve(&mut bodies, 0.0));
+    assert_eq!(bodies.len(), 3);
+    assert_eq!((bodies[0].mass, bodies[1].mass), (11.0, 5.0));
+    assert_eq!(bodies[1].pos, Vec3::new(0.8, 0.0, 0.0));
+    assert_eq!(sinks.radii(), &[1.0, 0.5]);
+    assert_eq!(sinks.log().iter().map(|event| (event.sink, event.absorbed)).collect::<Vec<_>>(), vec![(0, 3)]);
+    assert!(!sinks.resolve(&mut bodies, 0.0));
+}
+
+// a star plunging almost radially into a softened point mass is swallowed at the end of its free fall
+#[test]
+fn plunging_star_is_swallowed() {
+    let mut bodies = vec![
+        body(1.0, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)),
+        body(1e-3, Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 0.01, 0.0)),
+    ];
+    let before = momentum(&bodies);
+    let mut solver = DirectSolver::with_softening(1.0, Softening::Plummer(0.01));
+    let mut integrator = IntegratorLeapfrog::new();
+    let mut sinks = Sinks::new(vec![0.05]);
+    let dt = 0.001;
+    for step in 1..=2000 {
+        integrator.integrate(dt, &mut bodies, &mut solver);
+        sinks.resolve(&mut bodies, step as f64 * dt);
+    }
+    assert_eq!(bodies.len(), 1);
+    assert!((momentum(&bodies) - before).norm() < 1e-15);
+    // free fall time pi / 2 * sqrt(r^3 / (2 G M)), with the mass of both bodies
+    let free_fall = std::f64::consts::FRAC_PI_2 * (1.0 / (2.0 * 1.001f64)).sqrt();
+    let time = sinks.log()[0].time;
+    assert!((time - free_fall).abs() < 0.01, "swallowed at {} instead of {}", time, free_fall);
+}
+
+// a fast body crosses the accretion radius between two calls, its end positions are both outside
+#[test]
+fn tunnelling_body_is_swallowed() {
+    let (dt, speed) = (0.02, 100.0);
+    let start = |y: f64| {
+        vec![
+            body(1.0, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0)),
+            body(1e-3, Vec3::new(-1.0, y, 0.0), Vec3::new(speed, 0.0, 0.0)),
+        ]
+    };
+    let step = |bodies: &mut Vec<Body>| bodies[1].pos.x += speed * dt;
+
+    let mut bodies = start(0.1);
+    let mut sinks = Sinks::new(vec![0.5]);
+    assert!(!sinks.resolve(&mut bodies, 0.0));
+    step(&mut bodies);
+    assert_eq!(bodies[1].pos.x, 1.0);
+    assert!(sinks.resolve(&mut bodies, dt));
+    assert_eq!(bodies.len(), 1);
+    assert_eq!(sinks.log()[0].accreted, 1e-3);
+
+    // passing outside the radius, and moving away after having passed, keep the body
+    let mut bodies = start(0.6);
+    let mut sinks = Sinks::new(vec![0.5]);
+    sinks.resolve(&mut bodies, 0.0);
+    step(&mut bodies);
+    assert!(!sinks.resolve(&mut bodies, dt));
+    step(&mut bodies);
+    bodies[1].pos.y = 0.0;
+    assert!(!sinks.resolve(&mut bodies, 2.0 * dt));
+    assert_eq!(bodies.len(), 2);
+}
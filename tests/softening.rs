@@ -0,0 +1,83 @@
+use galaxyy::softening::Softening;
+
+const KERNELS: [Softening; 4] =
+    [Softening::None, Softening::Plummer(0.1), Softening::Spline(0.1), Softening::PerBody];
+
+// pair epsilons for PerBody, sqrt((0.05^2 + 0.13^2) / 2) is about 0.1
+const EPS2_1: f64 = 0.05 * 0.05;
+const EPS2_2: f64 = 0.13 * 0.13;
+
+fn force(kernel: &Softening, r: f64) -> f64 {
+    kernel.force_factor(r * r, EPS2_1, EPS2_2)
+}
+
+fn potential(kernel: &Softening, r: f64) -> f64 {
+    kernel.potential_factor(r * r, EPS2_1, EPS2_2)
+}
+
+fn relative(a: f64, b: f64) -> f64 {
+    (a - b).abs() / b.abs()
+}
+
+// the spline kernel reaches the newtonian force at h = 2.8 eps and at u = 0.5 switches between its two
+// polynomials, both have to join without a jump in force, potential or the derivative used for jerks
+#[test]
+fn spline_is_continuous() {
+    let kernel = Softening::Spline(0.1);
+    let h = 0.28;
+    for &r in &[h, 0.5 * h] {
+        let (below, above) = (r * (1.0 - 1e-9), r * (1.0 + 1e-9));
+        assert!(relative(force(&kernel, below), force(&kernel, above)) < 1e-8, "force jumps at {}", r);
+        assert!(relative(potential(&kernel, below), potential(&kernel, above)) < 1e-8, "potential jumps at {}", r);
+        let derivative = |r: f64| kernel.force_factor_derivative(r * r, 0.0, 0.0);
+        assert!(relative(derivative(below), derivative(above)) < 1e-7, "derivative jumps at {}", r);
+    }
+}
+
+#[test]
+fn spline_is_newtonian_beyond_support() {
+    let (kernel, newton) = (Softening::Spline(0.1), Softening::None);
+    for &r in &[0.28, 0.3, 1.0, 17.0] {
+        assert_eq!(force(&kernel, r), force(&newton, r));
+        assert_eq!(potential(&kernel, r), potential(&newton, r));
+        let derivative = |kernel: &Softening| kernel.force_factor_derivative(r * r, 0.0, 0.0);
+        assert_eq!(derivative(&kernel), derivative(&newton));
+    }
+    assert!(relative(force(&kernel, 0.14), force(&newton, 0.14)) > 0.01);
+}
+
+// plummer softening never becomes newtonian, but its relative error falls off as 1.5 eps^2 / r^2
+#[test]
+fn plummer_approaches_newtonian() {
+    let newton = Softening::None;
+    for kernel in &[Softening::Plummer(0.1), Softening::PerBody] {
+        let eps2 = if *kernel == Softening::PerBody { 0.5 * (EPS2_1 + EPS2_2) } else { 0.01 };
+        for &r in &[1.0, 10.0, 100.0] {
+            let error = relative(force(kernel, r), force(&newton, r));
+            assert!(error > 0.0 && error < 1.5 * eps2 / (r * r), "{:?} force error {:e} at {}", kernel, error, r);
+            let error = relative(potential(kernel, r), potential(&newton, r));
+            assert!(error > 0.0 && error < 0.5 * eps2 / (r * r), "{:?} potential error {:e}", kernel, error);
+        }
+    }
+}
+
+// the force factor is -1/r dphi/dr of the potential factor, checked by central differences
+#[test]
+fn force_matches_potential() {
+    for kernel in &KERNELS {
+        for &r in &[0.01, 0.05, 0.1, 0.15, 0.27, 0.29, 1.0, 3.0] {
+            let step = 1e-6 * r;
+            let slope = (potential(kernel, r + step) - potential(kernel, r - step)) / (2.0 * step);
+            let error = relative(-slope / r, force(kernel, r));
+            assert!(error < 1e-6, "{:?} force and potential disagree by {:e} at {}", kernel, error, r);
+        }
+    }
+}
+
+// softened forces stay finite where two bodies sit on top of each other
+#[test]
+fn softened_forces_are_finite_at_zero() {
+    for kernel in &KERNELS[1..] {
+        assert!(force(kernel, 0.0).is_finite() && potential(kernel, 0.0).is_finite(), "{:?}", kernel);
+    }
+}
@@ -0,0 +1,74 @@
+use galaxyy::barnes_hut::BarnesHutSimulation;
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::scenario::Collision;
+use galaxyy::solver::{DirectSolver, ForceSolver, RestrictedSolver};
+
+type Vec3 = nalgebra::Vector3<f64>;
+type MakeSolver = fn() -> Box<dyn ForceSolver>;
+
+// accelerations and potential energy of a fresh solver, evaluated in a pool with the given number of threads
+fn evaluate_in_pool(threads: usize, solver: MakeSolver, bodies: &[Body]) -> (Vec<Vec3>, f64) {
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().unwrap();
+    pool.install(|| {
+        let mut solver = solver();
+        let mut bodies = bodies.to_vec();
+        solver.eval(&mut bodies);
+        let energy = solver.potential_energy(&bodies);
+        (bodies.iter().map(|body| body.acc).collect(), energy)
+    })
+}
+
+// every body is summed by a single thread in a fixed order and partial sums are added up sequentially,
+// so runs are reproducible on any machine, bit for bit
+#[test]
+fn results_do_not_depend_on_the_number_of_threads() {
+    let bodies = Collision::new(300).bodies().to_vec();
+    let solvers: [(&str, MakeSolver); 3] = [
+        ("direct", || Box::new(DirectSolver::new(3.0))),
+        ("restricted", || Box::new(RestrictedSolver::new(2, 3.0))),
+        ("barnes-hut", || Box::new(BarnesHutSimulation::new(3.0))),
+    ];
+    for (name, solver) in solvers.iter() {
+        let (single, single_energy) = evaluate_in_pool(1, *solver, &bodies);
+        let (parallel, parallel_energy) = evaluate_in_pool(4, *solver, &bodies);
+        assert!(single == parallel, "{}: accelerations differ between 1 and 4 threads", name);
+        assert!(single_energy == parallel_energy, "{}: {} != {}", name, single_energy, parallel_energy);
+    }
+}
+
+// eval_active gives the active bodies exactly the accelerations of eval and leaves the others alone
+#[test]
+fn restricted_active_bodies_match_full_evaluation() {
+    let bodies = Collision::new(500).bodies().to_vec();
+    let mut solver = RestrictedSolver::new(2, 3.0);
+    let mut full = bodies.clone();
+    solver.eval(&mut full);
+
+    let active = [0, 1, 7, 300, 999];
+    let mut partial = bodies.clone();
+    let untouched = Vec3::new(1.0, 2.0, 3.0);
+    for body in partial.iter_mut() {
+        body.acc = untouched;
+    }
+    solver.eval_active(&mut partial, &active);
+    for (i, (partial, full)) in partial.iter().zip(&full).enumerate() {
+        if active.contains(&i) {
+            assert_eq!(partial.acc, full.acc, "body {}", i);
+        } else {
+            assert_eq!(partial.acc, untouched, "body {} isn't active", i);
+        }
+    }
+}
+
+// with fewer bodies than sources every body is a centre
+#[test]
+fn restricted_active_bodies_with_few_bodies() {
+    let mut bodies = vec![Body::new(Mass(1.0), Radius(0.0)), Body::new(Mass(2.0), Radius(0.0))];
+    bodies[1].pos = Vec3::new(1.0, 0.0, 0.0);
+    let mut solver = RestrictedSolver::new(3, 1.0);
+    let mut full = bodies.clone();
+    solver.eval(&mut full);
+    solver.eval_active(&mut bodies, &[1]);
+    assert_eq!(bodies[1].acc, full[1].acc);
+    assert_eq!(bodies[0].acc, Vec3::zeros());
+}
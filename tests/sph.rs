@@ -0,0 +1,191 @@
+use galaxyy::barnes_hut::BarnesHutSimulation;
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::diagnostics::Diagnostics;
+use galaxyy::integrator::{Integrator, IntegratorLeapfrog};
+use galaxyy::softening::Softening;
+use galaxyy::solver::ForceSolver;
+use galaxyy::sph::{EquationOfState, Sph};
+use rand::{Rng, SeedableRng};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+fn lattice(side: usize, spacing: f64, offset: Vec3) -> Vec<Body> {
+    let mut bodies = Vec::new();
+    for x in 0..side {
+        for y in 0..side {
+            for z in 0..side {
+                let mut body = Body::new(Mass(1.0), Radius(0.0));
+                body.pos = offset + spacing * Vec3::new(x as f64, y as f64, z as f64);
+                bodies.push(body);
+            }
+        }
+    }
+    bodies
+}
+
+// gas ball of given radius, uniform at random, moving with velocity
+fn ball(particles: usize, radius: f64, centre: Vec3, velocity: Vec3, rng: &mut rand::rngs::StdRng) -> Vec<Body> {
+    (0..particles)
+        .map(|_| {
+            let offset = loop {
+                let offset = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+                if offset.norm() < 1.0 {
+                    break offset;
+                }
+            };
+            let mut body = Body::new(Mass(1.0 / particles as f64), Radius(0.0));
+            body.pos = centre + radius * offset;
+            body.vel = velocity;
+            body
+        })
+        .collect()
+}
+
+// no gravity, hydrodynamics only
+fn hydro(gas: usize, equation_of_state: EquationOfState) -> Sph {
+    Sph::new(BarnesHutSimulation::with_softening(0.0, Softening::None), gas, equation_of_state)
+}
+
+fn cubic_spline(r: f64, h: f64) -> f64 {
+    let q = r / h;
+    let w = if q < 1.0 {
+        1.0 - 1.5 * q * q + 0.75 * q * q * q
+    } else if q < 2.0 {
+        0.25 * (2.0 - q).powi(3)
+    } else {
+        0.0
+    };
+    w / (std::f64::consts::PI * h * h * h)
+}
+
+#[test]
+fn lattice_has_uniform_density() {
+    let mut bodies = lattice(12, 0.5, Vec3::new(0.0, 0.0, 0.0));
+    let mut sph = hydro(bodies.len(), EquationOfState::Isothermal { sound_speed: 1.0 });
+    sph.eval(&mut bodies);
+    for (body, &density) in bodies.iter().zip(sph.densities()) {
+        // away from the surface of the cube
+        if body.pos.iter().all(|&x| (2.5..=3.0).contains(&x)) {
+            assert!((density - 8.0).abs() < 0.08, "density {} at {:?}", density, body.pos);
+            // pressure gradients cancel inside
+            assert!(body.acc.norm() < 1e-10, "acceleration {:?} at {:?}", body.acc, body.pos);
+        }
+    }
+}
+
+// the tree search finds every neighbour, stars mixed into the gas don't count
+#[test]
+fn densities_match_direct_sum() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+    let mut bodies = ball(500, 1.0, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), &mut rng);
+    bodies.extend(ball(300, 2.0, Vec3::new(0.5, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), &mut rng));
+    let mut sph = Sph::new(BarnesHutSimulation::new(1.0), 500, EquationOfState::Adiabatic { gamma: 5.0 / 3.0 });
+    sph.set_internal_energy(vec![0.05; 500]);
+    sph.eval(&mut bodies);
+    for i in 0..500 {
+        let h = sph.smoothing_lengths()[i];
+        let direct: f64 = bodies[..500]
+            .iter()
+            .map(|other| other.mass * cubic_spline((other.pos - bodies[i].pos).norm(), h))
+            .sum();
+        let density = sph.densities()[i];
+        assert!((density - direct).abs() < 1e-12 * direct, "{}: {} vs {}", i, density, direct);
+        // smoothing lengths have converged
+        let expected = 1.2 * (bodies[i].mass / density).cbrt();
+        assert!((h - expected).abs() < 1e-3 * h, "{}: h {} vs {}", i, h, expected);
+    }
+}
+
+// pairwise symmetric forces, with viscosity and smoothing lengths that differ between particles
+#[test]
+fn momentum_is_conserved() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(2);
+    let mut bodies = ball(400, 1.0, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), &mut rng);
+    for body in bodies.iter_mut() {
+        body.pos *= body.pos.norm();
+        body.vel = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0));
+    }
+    let mut sph = hydro(bodies.len(), EquationOfState::Adiabatic { gamma: 5.0 / 3.0 });
+    sph.set_internal_energy(vec![1.0; bodies.len()]);
+    sph.eval(&mut bodies);
+    let force: Vec3 = bodies.iter().map(|body| body.mass * body.acc).sum();
+    let scale: f64 = bodies.iter().map(|body| body.mass * body.acc.norm()).sum();
+    assert!(force.norm() < 1e-13 * scale, "net force {:?} of {}", force, scale);
+}
+
+// two blobs collide head on, the shock turns kinetic energy into heat and the total is kept
+#[test]
+fn colliding_blobs_conserve_energy() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+    let mut bodies = ball(100, 1.0, Vec3::new(-1.5, 0.0, 0.0), Vec3::new(2.0, 0.0, 0.0), &mut rng);
+    bodies.extend(ball(100, 1.0, Vec3::new(1.5, 0.0, 0.0), Vec3::new(-2.0, 0.0, 0.0), &mut rng));
+    let mut sph = hydro(bodies.len(), EquationOfState::Adiabatic { gamma: 5.0 / 3.0 });
+    sph.set_internal_energy(vec![0.01; bodies.len()]);
+    let initial = Diagnostics::compute(&bodies, &mut sph);
+
+    let mut integrator = IntegratorLeapfrog::new();
+    let mut heat: f64 = 0.0;
+    for step in 1..=200 {
+        integrator.integrate(0.0075, &mut bodies, &mut sph);
+        sph.advance_entropy(0.0075);
+        if step % 20 == 0 {
+            heat = heat.max(Diagnostics::compute(&bodies, &mut sph).potential_energy);
+        }
+    }
+    let last = Diagnostics::compute(&bodies, &mut sph);
+    let drift = ((last.total_energy() - initial.total_energy()) / initial.total_energy()).abs();
+    assert!(drift < 0.02, "energy error {:e}", drift);
+    // about half of the kinetic energy went into heat at the time of largest compression
+    assert!(heat > 0.4 * initial.kinetic_energy, "thermal {} kinetic {}", heat, initial.kinetic_energy);
+}
+
+// a self-gravitating adiabatic ball without viscosity, pressure and gravity both do work
+#[test]
+fn gravitating_ball_conserves_energy() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+    let mut bodies = ball(200, 1.0, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), &mut rng);
+    let gravity = BarnesHutSimulation::with_softening(1.0, Softening::Plummer(0.05));
+    let mut sph = Sph::new(gravity, bodies.len(), EquationOfState::Adiabatic { gamma: 5.0 / 3.0 });
+    sph.set_viscosity(0.0, 0.0);
+    sph.set_internal_energy(vec![0.05; bodies.len()]);
+    let initial = Diagnostics::compute(&bodies, &mut sph).total_energy();
+
+    let mut integrator = IntegratorLeapfrog::new();
+    for _ in 0..50 {
+        integrator.integrate(0.004, &mut bodies, &mut sph);
+        sph.advance_entropy(0.004);
+    }
+    let energy = Diagnostics::compute(&bodies, &mut sph).total_energy();
+    let drift = ((energy - initial) / initial).abs();
+    assert!(drift < 0.01, "energy error {:e}", drift);
+}
+
+// the energy before the first evaluation adapts smoothing lengths on the side and counts the internal
+// energies as given. It leaves the gas alone, the evaluation after it matches one without it
+#[test]
+fn energy_before_the_first_evaluation_keeps_the_gas() {
+    let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+    let bodies = ball(200, 1.0, Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 0.0), &mut rng);
+    let new = || {
+        let gravity = BarnesHutSimulation::with_softening(1.0, Softening::Plummer(0.05));
+        let mut sph = Sph::new(gravity, bodies.len(), EquationOfState::Adiabatic { gamma: 5.0 / 3.0 });
+        sph.set_internal_energy(vec![0.05; bodies.len()]);
+        sph
+    };
+
+    let mut sph = new();
+    let before = sph.potential_energy(&bodies);
+    assert!(sph.smoothing_lengths().is_empty() && sph.densities().is_empty());
+
+    let mut evaluated = bodies.clone();
+    sph.eval(&mut evaluated);
+    let mut fresh = bodies.clone();
+    let mut untouched = new();
+    untouched.eval(&mut fresh);
+    assert_eq!(evaluated, fresh);
+    assert_eq!(sph.smoothing_lengths(), untouched.smoothing_lengths());
+    assert_eq!(sph.internal_energies(), untouched.internal_energies());
+
+    let after = sph.potential_energy(&bodies);
+    assert!(((after - before) / after).abs() < 1e-12, "{} before, {} after the first evaluation", before, after);
+}
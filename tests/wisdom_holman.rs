@@ -0,0 +1,119 @@
+mod common;
+
+use common::newtonian;
+use galaxyy::body::{Body, Mass, Radius};
+use galaxyy::diagnostics::Diagnostics;
+use galaxyy::integrator::{Integrator, IntegratorWisdomHolman};
+
+type Vec3 = nalgebra::Vector3<f64>;
+
+const PI: f64 = std::f64::consts::PI;
+
+fn body(mass: f64, pos: Vec3, vel: Vec3) -> Body {
+    let mut body = Body::new(Mass(mass), Radius(0.0));
+    body.pos = pos;
+    body.vel = vel;
+    body
+}
+
+// planet at the pericentre of an orbit with semi-major axis a (negative for hyperbolae) and
+// eccentricity e around a unit mass in the origin, G M = 1
+fn planet(mass: f64, a: f64, e: f64) -> Body {
+    let q = a * (1.0 - e);
+    body(mass, Vec3::new(q, 0.0, 0.0), Vec3::new(0.0, ((1.0 + e) / q).sqrt(), 0.0))
+}
+
+// pulls of a massless planet cancel in the kicks, only the Kepler drift moves it
+fn at_pericentre(a: f64, e: f64) -> Vec<Body> {
+    vec![body(1.0, Vec3::zeros(), Vec3::zeros()), planet(0.0, a, e)]
+}
+
+fn drift(bodies: &mut [Body], time: f64, steps: usize) {
+    let mut integrator = IntegratorWisdomHolman::new(1.0);
+    for _ in 0..steps {
+        integrator.integrate(time / steps as f64, bodies, &mut newtonian());
+    }
+}
+
+// one period in a single step and in a few uneven parts of the orbit brings the planet back
+#[test]
+fn elliptic_orbits_close_after_one_period() {
+    for &e in &[0.0, 0.5, 0.9, 0.999] {
+        for &steps in &[1, 7] {
+            let mut bodies = at_pericentre(1.0, e);
+            let start = bodies[1];
+            drift(&mut bodies, 2.0 * PI, steps);
+            let (pos, vel) = (bodies[1].pos, bodies[1].vel);
+            assert!((pos - start.pos).norm() < 1e-9 * start.pos.norm(), "e = {}: {:?}", e, pos);
+            assert!((vel - start.vel).norm() < 1e-9 * start.vel.norm(), "e = {}: {:?}", e, vel);
+            assert_eq!(bodies[0].pos, Vec3::zeros());
+        }
+    }
+}
+
+/*
+ * Hyperbolae have no period, the planet is compared to the analytic orbit instead: at hyperbolic
+ * anomaly H it is at a (cosh H - e), |a| sqrt(e^2 - 1) sinh H after the time (e sinh H - H) / n, n^2 = 1 / |a|^3.
+ * Going back for the same time returns it to the pericentre, where the nearly parabolic orbit swings
+ * around within 1e-3 and turns rounding errors into a slightly larger velocity error.
+ */
+#[test]
+fn hyperbolic_orbits_follow_the_analytic_ones() {
+    for &e in &[1.001, 1.5, 5.0] {
+        let a = -1.0;
+        let h: f64 = 2.0;
+        let time = e * h.sinh() - h;
+        let expected = Vec3::new(a * (h.cosh() - e), -a * (e * e - 1.0).sqrt() * h.sinh(), 0.0);
+
+        let mut bodies = at_pericentre(a, e);
+        let start = bodies[1];
+        drift(&mut bodies, time, 1);
+        let pos = bodies[1].pos;
+        assert!((pos - expected).norm() < 1e-10 * expected.norm(), "e = {}: {:?}, {:?}", e, pos, expected);
+
+        drift(&mut bodies, -time, 3);
+        assert!((bodies[1].pos - start.pos).norm() < 1e-10 * expected.norm(), "e = {}: {:?}", e, bodies[1].pos);
+        assert!((bodies[1].vel - start.vel).norm() < 1e-9 * start.vel.norm(), "e = {}: {:?}", e, bodies[1].vel);
+    }
+}
+
+/*
+ * Sun, Jupiter and Saturn with their real mass ratios on orbits of eccentricity 0.05, twenty steps per
+ * orbit of Jupiter. The energy error of the map stays bounded at the size of the planet pulls times
+ * the mass ratio and doesn't grow over a hundred orbits.
+ */
+#[test]
+fn planets_conserve_energy() {
+    let mut bodies = vec![
+        body(1.0, Vec3::zeros(), Vec3::zeros()),
+        planet(9.5e-4, 5.2, 0.05),
+        planet(2.9e-4, 9.5, 0.05),
+    ];
+    // Saturn on the other side of the sun
+    bodies[2].pos = -bodies[2].pos;
+    bodies[2].vel = -bodies[2].vel;
+    // centre of mass at rest in the origin
+    let total: f64 = bodies.iter().map(|body| body.mass).sum();
+    let (pos, vel) = (
+        bodies.iter().map(|body| body.mass * body.pos).sum::<Vec3>() / total,
+        bodies.iter().map(|body| body.mass * body.vel).sum::<Vec3>() / total,
+    );
+    for body in bodies.iter_mut() {
+        body.pos -= pos;
+        body.vel -= vel;
+    }
+
+    let mut solver = newtonian();
+    let initial = Diagnostics::compute(&bodies, &mut solver).total_energy();
+    let period = 2.0 * PI * 5.2f64.powf(1.5);
+    let mut integrator = IntegratorWisdomHolman::new(1.0);
+    let mut errors = Vec::new();
+    for _ in 0..100 * 20 {
+        integrator.integrate(period / 20.0, &mut bodies, &mut solver);
+        errors.push(((Diagnostics::compute(&bodies, &mut solver).total_energy() - initial) / initial).abs());
+    }
+    let largest = |errors: &[f64]| errors.iter().fold(0.0f64, |largest, &error| largest.max(error));
+    let (first, all) = (largest(&errors[..errors.len() / 10]), largest(&errors));
+    assert!(all < 1e-5, "energy error {:e}", all);
+    assert!(all < 1.5 * first, "energy error {:e} in the first 10 orbits, {:e} in 100", first, all);
+}